@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS words_plain (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    word TEXT NOT NULL UNIQUE
+);
+
+INSERT INTO words_plain (id, word) SELECT min(id), word FROM words GROUP BY word;
+
+-- vectors of a word in other languages move to its kept id unless it has them already
+UPDATE OR IGNORE vectors
+SET word_id = (SELECT words_plain.id FROM words JOIN words_plain ON words_plain.word = words.word WHERE words.id = vectors.word_id)
+WHERE word_id NOT IN (SELECT id FROM words_plain);
+
+DELETE FROM vectors WHERE word_id NOT IN (SELECT id FROM words_plain);
+
+DROP TABLE words;
+
+ALTER TABLE words_plain RENAME TO words;
\ No newline at end of file
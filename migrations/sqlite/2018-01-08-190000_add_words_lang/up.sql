@@ -0,0 +1,12 @@
+CREATE TABLE IF NOT EXISTS words_lang (
+    id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    word TEXT NOT NULL,
+    lang TEXT NOT NULL DEFAULT '',
+    UNIQUE (word, lang)
+);
+
+INSERT INTO words_lang (id, word) SELECT id, word FROM words;
+
+DROP TABLE words;
+
+ALTER TABLE words_lang RENAME TO words;
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP INDEX ranks_kind_rank;
+
+DROP TABLE ranks;
\ No newline at end of file
@@ -0,0 +1,11 @@
+CREATE TABLE IF NOT EXISTS ranks (
+    word_id INTEGER NOT NULL,
+    kind_id INTEGER NOT NULL,
+    rank INTEGER NOT NULL,
+    source_index INTEGER,
+    PRIMARY KEY (word_id, kind_id),
+    FOREIGN KEY (word_id) REFERENCES words(id),
+    FOREIGN KEY (kind_id) REFERENCES kinds(id)
+);
+
+CREATE INDEX IF NOT EXISTS ranks_kind_rank ON ranks (kind_id, rank);
\ No newline at end of file
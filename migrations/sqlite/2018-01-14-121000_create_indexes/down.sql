@@ -0,0 +1 @@
+DROP TABLE indexes;
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS indexes (
+    kind_id INTEGER NOT NULL PRIMARY KEY,
+    m INTEGER NOT NULL,
+    ef_construction INTEGER NOT NULL,
+    checksum INTEGER NOT NULL,
+    data BLOB NOT NULL,
+    FOREIGN KEY (kind_id) REFERENCES kinds(id)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE properties;
\ No newline at end of file
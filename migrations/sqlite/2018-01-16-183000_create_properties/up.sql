@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS properties (
+    kind_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (kind_id, name),
+    FOREIGN KEY (kind_id) REFERENCES kinds(id)
+);
\ No newline at end of file
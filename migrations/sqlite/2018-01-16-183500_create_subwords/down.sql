@@ -0,0 +1 @@
+DROP TABLE subwords;
\ No newline at end of file
@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS subwords (
+    kind_id INTEGER NOT NULL,
+    bucket INTEGER NOT NULL,
+    vector BLOB NOT NULL,
+    PRIMARY KEY (kind_id, bucket),
+    FOREIGN KEY (kind_id) REFERENCES kinds(id)
+);
\ No newline at end of file
@@ -0,0 +1 @@
+DROP TABLE packed_vectors;
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE IF NOT EXISTS packed_vectors (
+    word_id INTEGER NOT NULL,
+    kind_id INTEGER NOT NULL,
+    vector BLOB NOT NULL,
+    PRIMARY KEY (word_id, kind_id),
+    FOREIGN KEY (word_id) REFERENCES words(id),
+    FOREIGN KEY (kind_id) REFERENCES kinds(id)
+);
\ No newline at end of file
@@ -0,0 +1,3 @@
+DROP TABLE pq_codes;
+
+DROP TABLE pq_codebooks;
\ No newline at end of file
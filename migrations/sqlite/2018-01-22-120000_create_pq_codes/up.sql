@@ -0,0 +1,17 @@
+CREATE TABLE IF NOT EXISTS pq_codebooks (
+    kind_id INTEGER NOT NULL,
+    subspace INTEGER NOT NULL,
+    dim INTEGER NOT NULL,
+    centroids BLOB NOT NULL,
+    PRIMARY KEY (kind_id, subspace),
+    FOREIGN KEY (kind_id) REFERENCES kinds(id)
+);
+
+CREATE TABLE IF NOT EXISTS pq_codes (
+    word_id INTEGER NOT NULL,
+    kind_id INTEGER NOT NULL,
+    codes BLOB NOT NULL,
+    PRIMARY KEY (word_id, kind_id),
+    FOREIGN KEY (word_id) REFERENCES words(id),
+    FOREIGN KEY (kind_id) REFERENCES kinds(id)
+);
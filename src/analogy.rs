@@ -0,0 +1,184 @@
+use space::{Neighbor, Space, norm};
+
+const EPSILON: f32 = 0.001;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    CosAdd,
+    CosMul,
+}
+
+impl Method {
+    pub fn parse(method: &str) -> Option<Method> {
+        match method {
+            "add" | "3cosadd" => Some(Method::CosAdd),
+            "mul" | "3cosmul" => Some(Method::CosMul),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Method::CosAdd => "add",
+            Method::CosMul => "mul",
+        }
+    }
+}
+
+/// Words of a vector arithmetic expression, "король - мужчина + женщина".
+#[derive(Debug, PartialEq)]
+pub struct Expression {
+    pub positive: Vec<String>,
+    pub negative: Vec<String>,
+}
+
+impl Expression {
+    /// `a b c` is a - b + c, e.g. king man woman → king - man + woman,
+    /// unlike the questions-words order of `eval analogy` answered by b - a + c.
+    pub fn analogy(a: &str, b: &str, c: &str) -> Expression {
+        Expression {
+            positive: vec![a.to_string(), c.to_string()],
+            negative: vec![b.to_string()],
+        }
+    }
+
+    /// Three words "king man woman" or an expression "king - man + woman".
+    pub fn query(query: &str) -> Result<Expression, String> {
+        let tokens = query.split_whitespace().collect::<Vec<_>>();
+
+        if tokens.iter().any(|&token| token == "+" || token == "-") {
+            Expression::parse(query)
+        } else if tokens.len() == 3 {
+            Ok(Expression::analogy(tokens[0], tokens[1], tokens[2]))
+        } else {
+            Err(format!("expected three words or an expression, got '{}'", query))
+        }
+    }
+
+    pub fn parse(expression: &str) -> Result<Expression, String> {
+        let mut result = Expression {
+            positive: Vec::new(),
+            negative: Vec::new(),
+        };
+
+        let mut sign: Option<bool> = Some(true);
+
+        for token in expression.split_whitespace() {
+            match (token, sign) {
+                ("+", None) => sign = Some(true),
+                ("-", None) => sign = Some(false),
+                // a leading sign of the first word
+                ("+", Some(true)) if result.is_empty() => sign = Some(true),
+                ("-", Some(true)) if result.is_empty() => sign = Some(false),
+                ("+", _) | ("-", _) => return Err(format!("unexpected '{}' in '{}'", token, expression)),
+                (word, Some(true)) => {
+                    result.positive.push(word.to_string());
+                    sign = None;
+                }
+                (word, Some(false)) => {
+                    result.negative.push(word.to_string());
+                    sign = None;
+                }
+                (word, None) => return Err(format!("expected '+' or '-' before '{}' in '{}'", word, expression)),
+            }
+        }
+
+        if sign.is_some() {
+            return Err(format!("expected a word at the end of '{}'", expression));
+        }
+
+        Ok(result)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positive.is_empty() && self.negative.is_empty()
+    }
+}
+
+/// Ranks a space against vectors of positive and negative terms.
+///
+/// 3CosAdd sums cosine similarities to positive terms minus negative ones,
+/// 3CosMul multiplies similarities shifted to [0, 1] and divides them by
+/// negative ones (Levy and Goldberg, 2014).
+pub fn solve(space: &Space, positive: &[Vec<f32>], negative: &[Vec<f32>], method: Method, top: usize, exclude: &[usize]) -> Vec<Neighbor> {
+    let positive = positive.iter().map(|vec| (vec.as_slice(), norm(vec))).collect::<Vec<_>>();
+    let negative = negative.iter().map(|vec| (vec.as_slice(), norm(vec))).collect::<Vec<_>>();
+
+    match method {
+        Method::CosAdd => space.rank_by(top, exclude, |row| {
+            let plus: f32 = positive.iter().map(|&(vec, vec_norm)| space.similarity(vec, vec_norm, row)).sum();
+            let minus: f32 = negative.iter().map(|&(vec, vec_norm)| space.similarity(vec, vec_norm, row)).sum();
+
+            plus - minus
+        }),
+        Method::CosMul => space.rank_by(top, exclude, |row| {
+            let shifted = |&(vec, vec_norm): &(&[f32], f32)| (space.similarity(vec, vec_norm, row) + 1.0) / 2.0;
+
+            let plus = positive.iter().map(&shifted).fold(1.0f32, |acc, value| acc * value);
+            let minus = negative.iter().map(&shifted).fold(1.0f32, |acc, value| acc * value);
+
+            plus / (minus + EPSILON)
+        }),
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use space::testing::test_space;
+
+    #[test]
+    fn test_parse_expression() {
+        match Expression::parse("король - мужчина + женщина") {
+            Ok(expression) => {
+                assert_eq!(expression, Expression::analogy("король", "мужчина", "женщина"), "check analogy");
+                assert_eq!(expression.positive, vec!["король", "женщина"], "check positive words");
+            }
+            Err(err) => assert!(false, "failed to parse expression with {:?}", err),
+        }
+
+        match Expression::parse("- кто-то + что-то") {
+            Ok(expression) => assert_eq!(expression, Expression {
+                positive: vec!["что-то".to_string()],
+                negative: vec!["кто-то".to_string()],
+            }, "check leading sign and hyphen words"),
+            Err(err) => assert!(false, "failed to parse expression with {:?}", err),
+        }
+
+        assert!(Expression::parse("король мужчина").is_err(), "check missed operator");
+        assert!(Expression::parse("король - + женщина").is_err(), "check double operator");
+        assert!(Expression::parse("король -").is_err(), "check trailing operator");
+        assert!(Expression::parse("").is_err(), "check empty expression");
+
+        match (Expression::query("король мужчина женщина"), Expression::query("король - мужчина + женщина")) {
+            (Ok(words), Ok(expression)) => assert_eq!(words, expression, "check query of three words"),
+            (words, expression) => assert!(false, "failed to parse queries with {:?} {:?}", words, expression),
+        }
+
+        assert!(Expression::query("король мужчина").is_err(), "check query of two words");
+    }
+
+    #[test]
+    fn test_solve() {
+        let space = test_space();
+        let find = |word: &str| (0..space.len()).find(|&row| space.word(row).word == word);
+        let vectors = |words: &[&str]| words.iter()
+            .filter_map(|&word| find(word))
+            .map(|row| space.vector(row).to_vec())
+            .collect::<Vec<_>>();
+
+        let positive = vectors(&["король", "женщина"]);
+        let negative = vectors(&["мужчина"]);
+        let exclude = ["король", "женщина", "мужчина"].iter()
+            .filter_map(|&word| find(word))
+            .collect::<Vec<_>>();
+
+        for &method in [Method::CosAdd, Method::CosMul].iter() {
+            let answers = solve(&space, &positive, &negative, method, 2, &exclude);
+
+            assert_eq!(answers.len(), 2, "check count for {:?}", method);
+            assert_eq!(space.word(answers[0].row).word, "королева", "check answer for {:?}", method);
+            assert!(answers.iter().all(|answer| !exclude.contains(&answer.row)), "check excluded for {:?}", method);
+        }
+    }
+}
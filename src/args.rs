@@ -1,5 +1,11 @@
-use clap::{Arg, App};
-use wordvector::VectorFile;
+use std::str::FromStr;
+use clap::{Arg, App, ArgMatches, Error, ErrorKind};
+use regex::Regex;
+use command;
+use converter::{DUPLICATES, Duplicates};
+use filter::{Filter, read_words};
+use quantize::{PRECISIONS, Precision};
+use wordvector::{Source, VectorFile};
 
 struct Argument<'a> {
     name: &'a str,
@@ -11,8 +17,14 @@ struct Argument<'a> {
 
 pub struct Args<'a> {
     app: App<'a, 'a>,
-    file_path: Option<Vec<VectorFile>>,
+    sub_command: Option<(String, ArgMatches<'a>)>,
+    sources: Option<Vec<Source>>,
     database_uri: Option<String>,
+    index: bool,
+    transform: Option<String>,
+    filter: Filter,
+    duplicates: Duplicates,
+    precision: Precision,
 }
 
 impl<'a> Default for Args<'a> {
@@ -20,13 +32,14 @@ impl<'a> Default for Args<'a> {
         let app = App::new("Word Vector To DB")
             .version("0.1")
             .author("Alexey Piyanin")
-            .about("Convert a word vector file (fasttext, glove, word2vec, gensim) to DB")
+            .about("Convert a word vector file (fasttext, glove, word2vec, gensim, safetensors) to DB")
             .arg(Arg::with_name("db_uri")
                 .short("db")
                 .long("db")
                 .takes_value(true)
                 .default_value("wordvector.db")
-                .help("path to result database"));
+                .help("path to result database"))
+            .subcommands(command::sub_commands());
 
         let arguments = [
             Argument {
@@ -36,6 +49,13 @@ impl<'a> Default for Args<'a> {
                 help: "fasttext word vector text file (*.vec)",
                 file_path: VectorFile::FastText,
             },
+            Argument {
+                name: "fasttext-bin",
+                short: "b",
+                long: "fasttext-bin",
+                help: "fasttext model with n-gram buckets for unknown words (*.bin)",
+                file_path: VectorFile::FastTextBin,
+            },
             Argument {
                 name: "glove",
                 short: "gl",
@@ -50,6 +70,13 @@ impl<'a> Default for Args<'a> {
                 help: "word2vec word vector text file (*.txt)",
                 file_path: VectorFile::Word2Vec,
             },
+            Argument {
+                name: "numberbatch",
+                short: "nb",
+                long: "numberbatch",
+                help: "ConceptNet Numberbatch word vector text file (*.txt)",
+                file_path: VectorFile::Numberbatch,
+            },
             Argument {
                 name: "word2vec/gensim",
                 short: "t",
@@ -66,37 +93,212 @@ impl<'a> Default for Args<'a> {
                     .short(argument.short)
                     .long(argument.long)
                     .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .value_name("FILE[@LANG]")
                     .help(argument.help)
             ),
         );
 
+        let app = app
+            .arg(Arg::with_name("safetensors")
+                .short("st")
+                .long("safetensors")
+                .takes_value(true)
+                .requires("vocab")
+                .help("transformer checkpoint with token embeddings (*.safetensors)"))
+            .arg(Arg::with_name("vocab")
+                .long("vocab")
+                .takes_value(true)
+                .help("vocabulary of a safetensors checkpoint (vocab.txt or tokenizer.json)"))
+            .arg(Arg::with_name("tensor")
+                .long("tensor")
+                .takes_value(true)
+                .default_value("embeddings.word_embeddings.weight")
+                .help("name of an embedding tensor in a safetensors checkpoint"))
+            .arg(Arg::with_name("lang")
+                .long("lang")
+                .takes_value(true)
+                .help("language of words in files without an own @LANG suffix"))
+            .arg(Arg::with_name("index")
+                .long("index")
+                .help("build an approximate nearest-neighbour index of each kind after import"))
+            .arg(Arg::with_name("transform")
+                .long("transform")
+                .takes_value(true)
+                .value_name("CHAIN")
+                .help("transforms of imported vectors in order: l2, center, center:FILE with a mean vector, abtt:D removing D top principal components, pca:D projecting onto D top principal components"))
+            .arg(Arg::with_name("duplicates")
+                .long("duplicates")
+                .takes_value(true)
+                .possible_values(&DUPLICATES)
+                .default_value("first")
+                .help("a word repeated in a file keeps the first or the last vector, an average of them or fails an import"))
+            .arg(Arg::with_name("precision")
+                .long("precision")
+                .takes_value(true)
+                .possible_values(&PRECISIONS)
+                .default_value("f32")
+                .help("storage of vectors: f32 points, packed f16 or int8 with a scale per dimension or per vector"))
+            .arg(Arg::with_name("limit")
+                .long("limit")
+                .takes_value(true)
+                .value_name("N")
+                .help("import the first N records of each file only"))
+            .arg(Arg::with_name("min_length")
+                .long("min-length")
+                .takes_value(true)
+                .value_name("N")
+                .help("skip words shorter than N characters"))
+            .arg(Arg::with_name("max_length")
+                .long("max-length")
+                .takes_value(true)
+                .value_name("N")
+                .help("skip words longer than N characters"))
+            .arg(Arg::with_name("include")
+                .long("include")
+                .takes_value(true)
+                .value_name("REGEX")
+                .help("import matching words only, e.g. '^\\p{Cyrillic}+$'"))
+            .arg(Arg::with_name("exclude")
+                .long("exclude")
+                .takes_value(true)
+                .value_name("REGEX")
+                .help("skip matching words"))
+            .arg(Arg::with_name("allowlist")
+                .long("allowlist")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("import words of a file only, one per line"))
+            .arg(Arg::with_name("denylist")
+                .long("denylist")
+                .takes_value(true)
+                .value_name("FILE")
+                .help("skip words of a file, one per line"))
+            .arg(Arg::with_name("no_digits")
+                .long("no-digits")
+                .help("skip words with digits"))
+            .arg(Arg::with_name("no_urls")
+                .long("no-urls")
+                .help("skip URLs and domain names"));
+
         let args = app.clone().get_matches();
 
         let database_uri = match args.value_of("db_uri") {
             Some(database_uri) => Some(database_uri.to_string()),
             None => None,
         };
-        let file_path: Vec<VectorFile> = arguments.iter().filter_map(|arg_info| {
-            let file_path = args.value_of(arg_info.name)?;
+        let sub_command = match args.subcommand() {
+            (name, Some(matches)) => Some((name.to_string(), matches.clone())),
+            _ => None,
+        };
+
+        let default_lang = args.value_of("lang");
+        let index = args.is_present("index");
+        let transform = args.value_of("transform").map(|transform| transform.to_string());
+        let duplicates = args.value_of("duplicates").and_then(Duplicates::parse).unwrap_or(Duplicates::First);
+        let precision = args.value_of("precision").and_then(Precision::parse).unwrap_or(Precision::F32);
+        let filter = filter(&args).unwrap_or_else(|err| Error::with_description(&err, ErrorKind::InvalidValue).exit());
 
-            Some((arg_info.file_path)(file_path.to_string()))
+        let mut sources: Vec<Source> = arguments.iter().flat_map(|arg_info| {
+            args.values_of(arg_info.name)
+                .map(|values| values.collect::<Vec<_>>())
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |value| {
+                    let (file_path, lang) = split_lang(value);
+
+                    Source {
+                        file: (arg_info.file_path)(file_path.to_string()),
+                        lang: lang.or(default_lang).map(|lang| lang.to_string()),
+                    }
+                })
         }).collect();
 
+        if let (Some(safetensors), Some(vocab), Some(tensor)) = (args.value_of("safetensors"), args.value_of("vocab"), args.value_of("tensor")) {
+            let (file_path, lang) = split_lang(safetensors);
+
+            sources.push(Source {
+                file: VectorFile::SafeTensors {
+                    file_path: file_path.to_string(),
+                    vocab_path: vocab.to_string(),
+                    tensor: tensor.to_string(),
+                },
+                lang: lang.or(default_lang).map(|lang| lang.to_string()),
+            });
+        }
+
         Args {
             app,
-            file_path: if file_path.len() > 0 {
-                Some(file_path)
+            sub_command,
+            sources: if sources.len() > 0 {
+                Some(sources)
             } else {
                 None
             },
             database_uri,
+            index,
+            transform,
+            filter,
+            duplicates,
+            precision,
         }
     }
 }
 
+fn parse<T: FromStr>(args: &ArgMatches, name: &str) -> Result<Option<T>, String> {
+    match args.value_of(name) {
+        Some(value) => value.parse::<T>()
+            .map(Some)
+            .map_err(|_| format!("invalid value '{}' of '{}'", value, name)),
+        None => Ok(None),
+    }
+}
+
+fn regex(args: &ArgMatches, name: &str) -> Result<Option<Regex>, String> {
+    match args.value_of(name) {
+        Some(value) => Regex::new(value)
+            .map(Some)
+            .map_err(|err| format!("invalid regex of '{}': {}", name, err)),
+        None => Ok(None),
+    }
+}
+
+fn filter(args: &ArgMatches) -> Result<Filter, String> {
+    Ok(Filter {
+        limit: parse(args, "limit")?,
+        min_length: parse(args, "min_length")?,
+        max_length: parse(args, "max_length")?,
+        include: regex(args, "include")?,
+        exclude: regex(args, "exclude")?,
+        allowlist: match args.value_of("allowlist") {
+            Some(path) => Some(read_words(path)?),
+            None => None,
+        },
+        denylist: match args.value_of("denylist") {
+            Some(path) => Some(read_words(path)?),
+            None => None,
+        },
+        digits: args.is_present("no_digits"),
+        urls: args.is_present("no_urls"),
+    })
+}
+
+// "cc.ru.300.vec@ru" names a file and a language of its words
+fn split_lang(value: &str) -> (&str, Option<&str>) {
+    match value.rfind('@') {
+        Some(pos) if is_lang(&value[pos + 1..]) => (&value[..pos], Some(&value[pos + 1..])),
+        _ => (value, None),
+    }
+}
+
+fn is_lang(lang: &str) -> bool {
+    lang.len() > 0 && lang.len() <= 8 && lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
 impl<'a> Args<'a> {
     pub fn is_incomplete(&self) -> bool {
-        if let None = self.file_path {
+        if let None = self.sources {
             return true;
         }
 
@@ -112,13 +314,43 @@ impl<'a> Args<'a> {
         println!();
     }
 
-    pub fn file_path(&self) -> Option<&[VectorFile]> {
-        match self.file_path {
-            Some(ref file_path) => Some(&file_path),
+    pub fn sub_command(&self) -> Option<(&str, &ArgMatches<'a>)> {
+        match self.sub_command {
+            Some((ref name, ref matches)) => Some((&name, matches)),
+            None => None,
+        }
+    }
+
+    pub fn sources(&self) -> Option<&[Source]> {
+        match self.sources {
+            Some(ref sources) => Some(&sources),
+            None => None,
+        }
+    }
+
+    pub fn index(&self) -> bool {
+        self.index
+    }
+
+    pub fn transform(&self) -> Option<&str> {
+        match self.transform {
+            Some(ref transform) => Some(&transform),
             None => None,
         }
     }
 
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    pub fn duplicates(&self) -> Duplicates {
+        self.duplicates
+    }
+
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
     pub fn database_uri(&self) -> Option<&str> {
         match self.database_uri {
             Some(ref database_uri) => Some(&database_uri),
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use rand::{self, Rng};
+
+use command::{EXIT_OK, connect, db_arg, kind_by_name, limit, limit_arg};
+use command::reduce::derive;
+use db::connection::DsjConnection;
+use db::models::kind::{Kind, create_kind, get_kind};
+use linalg::orthogonal_polar;
+use space::Space;
+use transform::{Pipeline, Transform};
+
+const TOP: usize = 5;
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("align")
+        .about("Write a new kind of vectors of a source kind mapped to a target kind by orthogonal Procrustes on a bilingual dictionary")
+        .arg(db_arg())
+        .arg(Arg::with_name("source")
+            .long("source")
+            .takes_value(true)
+            .required(true)
+            .help("kind to map, e.g. fasttext_ru"))
+        .arg(Arg::with_name("target")
+            .long("target")
+            .takes_value(true)
+            .required(true)
+            .help("kind to map to, e.g. fasttext_en"))
+        .arg(Arg::with_name("dictionary")
+            .long("dictionary")
+            .takes_value(true)
+            .required(true)
+            .help("file of 'source target' word pairs, one per line"))
+        .arg(Arg::with_name("into")
+            .long("into")
+            .takes_value(true)
+            .required(true)
+            .help("name of a new kind"))
+        .arg(Arg::with_name("holdout")
+            .long("holdout")
+            .takes_value(true)
+            .default_value("0.2")
+            .help("share of source words of the dictionary held out to measure translation precision"))
+        .arg(limit_arg())
+}
+
+/// Pairs of words of a dictionary file, whitespace separated.
+pub fn read_pairs(path: &str) -> Result<Vec<(String, String)>, String> {
+    let file = File::open(path).map_err(|err| format!("failed to open '{}': {}", path, err))?;
+    let mut pairs = Vec::new();
+
+    for (i, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|err| format!("failed to read '{}': {}", path, err))?;
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words.len() {
+            0 => continue,
+            2 => pairs.push((words[0].to_string(), words[1].to_string())),
+            _ => return Err(format!("{}:{}: expected a source and a target word", path, i + 1)),
+        }
+    }
+
+    Ok(pairs)
+}
+
+/// An aligned kind, its number of words, pairs of a dictionary found in both kinds and missing ones,
+/// counts of source words to learn a map and to test it with precision@1 and @5 of translations.
+pub struct Aligned {
+    pub kind: Kind,
+    pub words: usize,
+    pub pairs: usize,
+    pub missing: usize,
+    pub train: usize,
+    pub test: usize,
+    pub precision: Option<(f32, f32)>,
+}
+
+// rows of words in the file order, the first one of homonyms of different languages
+fn word_rows(space: &Space) -> HashMap<&str, usize> {
+    let mut rows = HashMap::with_capacity(space.len());
+
+    for row in 0..space.len() {
+        rows.entry(space.word(row).word.as_str()).or_insert(row);
+    }
+
+    rows
+}
+
+// an orthogonal W minimizing |XW - Y| for rows X and Y of pairs is U V^T of an SVD of X^T Y
+fn learn(source: &Space, target: &Space, pairs: &[(usize, usize)]) -> Result<Pipeline, String> {
+    let dim = source.dim();
+    let mut cross = vec![vec![0f64; dim]; dim];
+
+    for &(from, to) in pairs.iter() {
+        let (x, y) = (source.vector(from), target.vector(to));
+
+        for i in 0..dim {
+            for j in 0..dim {
+                cross[i][j] += x[i] as f64 * y[j] as f64;
+            }
+        }
+    }
+
+    // pairs which don't span the space leave W only a partial isometry
+    let w = match orthogonal_polar(&cross) {
+        Some(w) => w,
+        None => return Err(format!("training pairs don't span {} dimensions, add more pairs to the dictionary", dim)),
+    };
+
+    // x W has columns of W as rows of a map
+    let rows = (0..dim).map(|j| (0..dim).map(|i| w[i][j] as f32).collect()).collect();
+
+    Ok(Pipeline::new(vec![Transform::Map(rows)]))
+}
+
+fn precision(source: &Space, target: &Space, map: &Pipeline, test: &[(usize, Vec<usize>)]) -> Option<(f32, f32)> {
+    if test.is_empty() {
+        return None;
+    }
+
+    let (mut first, mut top) = (0, 0);
+
+    for &(from, ref translations) in test.iter() {
+        let mut query = source.vector(from).to_vec();
+
+        if map.apply(&mut query).is_err() {
+            continue;
+        }
+
+        let neighbors = target.neighbors(&query, TOP, &[]);
+
+        if neighbors.first().map(|neighbor| translations.contains(&neighbor.row)).unwrap_or(false) {
+            first += 1;
+        }
+
+        if neighbors.iter().any(|neighbor| translations.contains(&neighbor.row)) {
+            top += 1;
+        }
+    }
+
+    Some((first as f32 / test.len() as f32, top as f32 / test.len() as f32))
+}
+
+pub fn align(conn: &DsjConnection, source: &Kind, target: &Kind, into: &str, pairs: &[(String, String)], holdout: f32, limit: Option<i64>) -> Result<Aligned, String> {
+    if get_kind(conn, into).is_some() {
+        return Err(format!("kind '{}' already exists", into));
+    }
+
+    if holdout < 0.0 || holdout >= 1.0 {
+        return Err("a held out share should be from 0 to 1".to_string());
+    }
+
+    let source_space = Space::load(conn, source, None)?;
+    let target_space = Space::load(conn, target, limit)?;
+
+    if source_space.dim() != target_space.dim() {
+        return Err(format!("dimensions of '{}' and '{}' differ: {} and {}", source.name, target.name, source_space.dim(), target_space.dim()));
+    }
+
+    // translations of a source word stay together in a training or a test split
+    let (source_rows, target_rows) = (word_rows(&source_space), word_rows(&target_space));
+    let mut translations: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut missing = 0;
+
+    for &(ref from, ref to) in pairs.iter() {
+        match (source_rows.get(from.as_str()), target_rows.get(to.as_str())) {
+            (Some(&from), Some(&to)) => translations.entry(from).or_insert_with(Vec::new).push(to),
+            _ => missing += 1,
+        }
+    }
+
+    let mut words = translations.into_iter().collect::<Vec<_>>();
+
+    rand::thread_rng().shuffle(&mut words);
+
+    let test_count = ((words.len() as f32 * holdout).round() as usize).min(words.len().saturating_sub(1));
+    let (test, train) = words.split_at(test_count);
+
+    if train.is_empty() {
+        return Err("no pairs of the dictionary are found in both kinds".to_string());
+    }
+
+    let train_pairs = train.iter()
+        .flat_map(|&(from, ref to)| to.iter().map(move |&to| (from, to)))
+        .collect::<Vec<_>>();
+
+    if train_pairs.len() < source_space.dim() {
+        return Err(format!("{} training pairs are fewer than {} dimensions", train_pairs.len(), source_space.dim()));
+    }
+
+    let map = learn(&source_space, &target_space, &train_pairs)?;
+    let precision = precision(&source_space, &target_space, &map, test);
+
+    let aligned = match create_kind(conn, into) {
+        Some(aligned) => aligned,
+        None => return Err(format!("failed to create kind '{}'", into)),
+    };
+
+    derive(conn, source, &source_space, &aligned, map)?;
+
+    Ok(Aligned {
+        kind: aligned,
+        words: source_space.len(),
+        pairs: pairs.len() - missing,
+        missing,
+        train: train.len(),
+        test: test.len(),
+        precision,
+    })
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let source = kind_by_name(&conn, args.value_of("source").unwrap_or_default())?;
+    let target = kind_by_name(&conn, args.value_of("target").unwrap_or_default())?;
+    let into = args.value_of("into").unwrap_or_default();
+    let holdout = value_t!(args, "holdout", f32).map_err(|err| err.to_string())?;
+    let pairs = read_pairs(args.value_of("dictionary").unwrap_or_default())?;
+
+    let aligned = align(&conn, &source, &target, into, &pairs, holdout, limit(args)?)?;
+
+    println!("{}: {} words mapped to '{}' by {} pairs of {} source words, {} pairs missing",
+             aligned.kind.name, aligned.words, target.name, aligned.pairs, aligned.train + aligned.test, aligned.missing);
+
+    if let Some((first, top)) = aligned.precision {
+        println!("precision@1 {:.4}, precision@{} {:.4} on {} held out words", first, TOP, top, aligned.test);
+    }
+
+    Ok(EXIT_OK)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::testing::test_connection;
+    use db::models::vector::{Vector, add_vectors, lookup_vector};
+    use db::models::word::words_list;
+
+    #[test]
+    fn test_align() {
+        let conn = test_connection();
+        let source = get_kind(&conn, "fasttext").unwrap_or_default();
+        let target = create_kind(&conn, "fasttext_en").unwrap_or_default();
+
+        // the target kind is the source one rotated and reflected
+        for word in words_list(&conn).unwrap_or_default() {
+            if let Some((_, x)) = lookup_vector(&conn, &word.word, None, &source) {
+                add_vectors(&conn, &Vector::from_vec(&word, &target, &[x[1], -x[0], x[3], x[2]]));
+            }
+        }
+
+        let mut pairs = words_list(&conn).unwrap_or_default()
+            .into_iter()
+            .map(|word| (word.word.clone(), word.word))
+            .collect::<Vec<_>>();
+
+        pairs.push(("нет".to_string(), "no".to_string()));
+
+        assert!(align(&conn, &source, &target, "fasttext", &pairs, 0.2, None).is_err(), "check existing kind");
+        assert!(align(&conn, &source, &target, "fasttext_aligned", &pairs, 1.0, None).is_err(), "check held out share");
+        assert!(align(&conn, &source, &target, "fasttext_aligned", &pairs[..3], 0.0, None).is_err(), "check fewer pairs than dimensions");
+        assert!(get_kind(&conn, "fasttext_aligned").is_none(), "check no kind created");
+
+        let aligned = match align(&conn, &source, &target, "fasttext_aligned", &pairs, 0.2, None) {
+            Ok(aligned) => aligned,
+            Err(err) => {
+                assert!(false, "failed to align: {}", err);
+                return;
+            }
+        };
+
+        assert_eq!((aligned.words, aligned.pairs, aligned.missing, aligned.train, aligned.test), (5, 5, 1, 4, 1), "check counts");
+        assert_eq!(aligned.precision, Some((1.0, 1.0)), "check precision");
+
+        match (lookup_vector(&conn, "яблоко", None, &aligned.kind), lookup_vector(&conn, "яблоко", None, &target)) {
+            (Some((_, mapped)), Some((_, expected))) => assert!(mapped.iter().zip(expected.iter()).all(|(a, b)| (a - b).abs() < 1e-4), "check mapped {:?}", mapped),
+            _ => assert!(false, "failed to look up an aligned word"),
+        }
+    }
+}
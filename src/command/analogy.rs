@@ -0,0 +1,88 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use analogy::{Expression, Method, solve};
+use command::{EXIT_OK, EXIT_MISSING, connect, report_match, strategies, strategy_arg, db_arg, format_arg, kind, kind_arg, limit, limit_arg, print_neighbors, top, top_arg};
+use lookup::Lookup;
+use space::Space;
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("analogy")
+        .about("Answer 'a b c' as a - b + c (king man woman) or a vector expression 'король - мужчина + женщина'")
+        .arg(db_arg())
+        .arg(kind_arg())
+        .arg(strategy_arg())
+        .arg(Arg::with_name("lang")
+            .long("lang")
+            .takes_value(true)
+            .help("language of words"))
+        .arg(Arg::with_name("method")
+            .short("m")
+            .long("method")
+            .takes_value(true)
+            .possible_values(&["add", "mul"])
+            .default_value("add")
+            .help("scoring: 3CosAdd or 3CosMul"))
+        .arg(top_arg())
+        .arg(limit_arg())
+        .arg(format_arg("output format: 'word score' lines or JSON lines"))
+        .arg(Arg::with_name("words")
+            .multiple(true)
+            .required(true)
+            .help("three words or an expression"))
+}
+
+fn expression(args: &ArgMatches) -> Result<Expression, String> {
+    let words = args.values_of("words")
+        .map(|values| values.collect::<Vec<_>>())
+        .unwrap_or_default()
+        .join(" ");
+
+    Expression::query(&words)
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let expression = expression(args)?;
+    let method = Method::parse(args.value_of("method").unwrap_or_default()).unwrap_or(Method::CosAdd);
+
+    let conn = connect(args)?;
+    let kind = kind(&conn, args)?;
+    let top = top(args)?;
+    let limit = limit(args)?;
+    let lang = args.value_of("lang");
+    let lookup = Lookup::new(&conn, &kind, &strategies(args)?);
+
+    let mut missing = Vec::new();
+    let mut word_ids = Vec::new();
+
+    let (positive, negative) = {
+        let mut vectors = |words: &[String]| words.iter()
+            .filter_map(|w| match lookup.find(&conn, w, lang) {
+                Some(found) => {
+                    report_match(w, &found);
+                    word_ids.extend(found.word_id());
+                    Some(found.vec)
+                }
+                None => {
+                    missing.push(w.to_string());
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        (vectors(&expression.positive), vectors(&expression.negative))
+    };
+
+    if !missing.is_empty() {
+        eprintln!("missing: {}", missing.join(" "));
+        return Ok(EXIT_MISSING);
+    }
+
+    let space = Space::load(&conn, &kind, limit)?;
+    let exclude = word_ids.iter()
+        .filter_map(|&id| space.row(id))
+        .collect::<Vec<_>>();
+
+    print_neighbors(&space, &solve(&space, &positive, &negative, method, top, &exclude), args);
+
+    Ok(EXIT_OK)
+}
@@ -0,0 +1,258 @@
+use std::collections::HashSet;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use rand::{self, Rng};
+use rayon::prelude::*;
+
+use command::{EXIT_OK, connect, db_arg, kind, kind_arg, top, top_arg};
+use command::reduce::{inherit, write_rows};
+use db::connection::DsjConnection;
+use db::models::kind::{Kind, create_kind, delete_kind, get_kind};
+use db::models::pq::{Codes, add_codes};
+use pq::{CompressedSpace, ProductQuantizer};
+use space::Space;
+use transform::Pipeline;
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("compress")
+        .about("Write a new kind of codes of a kind compressed by product quantization")
+        .arg(db_arg())
+        .arg(kind_arg())
+        .arg(Arg::with_name("pq")
+            .long("pq")
+            .required(true)
+            .help("compress by product quantization, the only method yet"))
+        .arg(Arg::with_name("into")
+            .long("into")
+            .takes_value(true)
+            .required(true)
+            .help("name of a new kind"))
+        .arg(Arg::with_name("subspaces")
+            .short("m")
+            .long("subspaces")
+            .takes_value(true)
+            .default_value("8")
+            .help("count of subspaces, bytes of a code of a word"))
+        .arg(Arg::with_name("centroids")
+            .long("centroids")
+            .takes_value(true)
+            .default_value("256")
+            .help("count of centroids of a subspace, up to 256"))
+        .arg(Arg::with_name("iterations")
+            .long("iterations")
+            .takes_value(true)
+            .default_value("20")
+            .help("count of k-means iterations"))
+        .arg(Arg::with_name("train")
+            .long("train")
+            .takes_value(true)
+            .help("train codebooks on N random words instead of all words"))
+        .arg(top_arg())
+        .arg(Arg::with_name("sample")
+            .short("s")
+            .long("sample")
+            .takes_value(true)
+            .default_value("100")
+            .help("count of words to measure recall against exact search, 0 skips it"))
+}
+
+pub struct Params {
+    pub subspaces: usize,
+    pub centroids: usize,
+    pub iterations: usize,
+    pub train: Option<usize>,
+}
+
+/// A compressed kind, its number of words, a root mean square error of decoded vectors
+/// and a share of exact top neighbours found by codes of a sample of words.
+pub struct Compressed {
+    pub kind: Kind,
+    pub words: usize,
+    pub bytes: usize,
+    pub rmse: f32,
+    pub recall: Option<f32>,
+}
+
+pub fn compress(conn: &DsjConnection, kind: &Kind, into: &str, params: &Params, sample: usize, top: usize) -> Result<Compressed, String> {
+    if get_kind(conn, into).is_some() {
+        return Err(format!("kind '{}' already exists", into));
+    }
+
+    let space = Space::load(conn, kind, None)?;
+
+    if space.len() == 0 {
+        return Err(format!("kind '{}' has no vectors", kind.name));
+    }
+
+    let rows = match params.train {
+        Some(train) if train < space.len() => {
+            let mut rows = (0..space.len()).collect::<Vec<_>>();
+
+            rand::thread_rng().shuffle(&mut rows);
+            rows.truncate(train);
+            rows
+        },
+        _ => (0..space.len()).collect(),
+    };
+
+    let vectors = rows.into_iter().map(|row| space.vector(row)).collect::<Vec<_>>();
+    let quantizer = ProductQuantizer::train(&vectors, params.subspaces, params.centroids, params.iterations)?;
+
+    let codes = (0..space.len()).into_par_iter()
+        .map(|row| quantizer.encode(space.vector(row)))
+        .collect::<Vec<_>>();
+
+    let squares = codes.iter().enumerate()
+        .map(|(row, codes)| quantizer.decode(codes).iter().zip(space.vector(row).iter()).map(|(a, b)| ((a - b) * (a - b)) as f64).sum::<f64>())
+        .sum::<f64>();
+
+    let compressed = match create_kind(conn, into) {
+        Some(compressed) => compressed,
+        None => return Err(format!("failed to create kind '{}'", into)),
+    };
+
+    if !quantizer.save(conn, &compressed) {
+        delete_kind(conn, &compressed);
+
+        return Err(format!("failed to save codebooks of '{}'", into));
+    }
+
+    write_rows(conn, kind, &space, &compressed, |row| {
+        add_codes(conn, &[Codes::new(space.word(row), &compressed, codes[row].clone())]) == 1
+    });
+
+    // codes are of stored vectors, so composed vectors need no transform beyond the original pipeline
+    inherit(conn, kind, &compressed, Pipeline::default())?;
+
+    let bytes = quantizer.subspaces();
+    let compressed_space = CompressedSpace::new((0..space.len()).map(|row| space.word(row).clone()).collect(), quantizer, codes);
+
+    Ok(Compressed {
+        kind: compressed,
+        words: space.len(),
+        bytes,
+        rmse: (squares / (space.len() * space.dim()) as f64).sqrt() as f32,
+        recall: if sample > 0 { Some(recall(&space, &compressed_space, sample, top)) } else { None },
+    })
+}
+
+fn recall(space: &Space, compressed: &CompressedSpace, sample: usize, top: usize) -> f32 {
+    let step = (space.len() / sample.max(1)).max(1);
+    let mut expected = 0;
+    let mut found = 0;
+
+    for row in (0..space.len()).filter(|row| row % step == 0).take(sample) {
+        let query = space.vector(row);
+
+        let exact = space.neighbors(query, top, &[row])
+            .into_iter()
+            .map(|neighbor| neighbor.row)
+            .collect::<HashSet<_>>();
+
+        found += compressed.neighbors(query, top, &[row])
+            .iter()
+            .filter(|neighbor| exact.contains(&neighbor.row))
+            .count();
+
+        expected += exact.len();
+    }
+
+    if expected > 0 {
+        found as f32 / expected as f32
+    } else {
+        0.0
+    }
+}
+
+fn params(args: &ArgMatches) -> Result<Params, String> {
+    Ok(Params {
+        subspaces: value_t!(args, "subspaces", usize).map_err(|err| err.to_string())?,
+        centroids: value_t!(args, "centroids", usize).map_err(|err| err.to_string())?,
+        iterations: value_t!(args, "iterations", usize).map_err(|err| err.to_string())?,
+        train: match args.value_of("train") {
+            Some(_) => Some(value_t!(args, "train", usize).map_err(|err| err.to_string())?),
+            None => None,
+        },
+    })
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let kind = kind(&conn, args)?;
+    let params = params(args)?;
+    let into = args.value_of("into").unwrap_or_default();
+    let top = top(args)?;
+    let sample = value_t!(args, "sample", usize).map_err(|err| err.to_string())?;
+
+    let compressed = compress(&conn, &kind, into, &params, sample, top)?;
+
+    println!("{}: {} words, {} bytes per word, rmse {:.6}", compressed.kind.name, compressed.words, compressed.bytes, compressed.rmse);
+
+    if let Some(recall) = compressed.recall {
+        println!("recall@{} on {} words: {:.4}", top, sample.min(compressed.words), recall);
+    }
+
+    Ok(EXIT_OK)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::property::{get_property, set_property};
+    use db::models::testing::test_connection;
+    use db::models::vector::{kind_size, lookup_vector};
+    use subword::{BUCKET, MAXN, MINN};
+
+    #[test]
+    fn test_compress() {
+        let conn = test_connection();
+        let kind = get_kind(&conn, "fasttext").unwrap_or_default();
+        let params = Params {
+            subspaces: 2,
+            centroids: 5,
+            iterations: 10,
+            train: None,
+        };
+
+        for &(name, value) in [(MINN, "3"), (MAXN, "6"), (BUCKET, "10")].iter() {
+            set_property(&conn, &kind, name, value);
+        }
+
+        let pipeline = match Pipeline::parse("l2") {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                assert!(false, "failed to parse a pipeline: {}", err);
+                return;
+            }
+        };
+
+        pipeline.save(&conn, &kind);
+
+        assert!(compress(&conn, &kind, "fasttext", &params, 0, 2).is_err(), "check existing kind");
+        assert!(compress(&conn, &kind, "fasttext_pq", &Params { centroids: 6, ..params }, 0, 2).is_err(), "check too many centroids");
+
+        // a centroid per word keeps vectors and neighbours
+        let compressed = match compress(&conn, &kind, "fasttext_pq", &Params { centroids: 5, ..params }, 5, 2) {
+            Ok(compressed) => compressed,
+            Err(err) => {
+                assert!(false, "failed to compress: {}", err);
+                return;
+            }
+        };
+
+        assert!(compressed.words == 5 && compressed.bytes == 2 && compressed.rmse < 1e-6, "check compressed {} {}", compressed.words, compressed.rmse);
+        assert_eq!(compressed.recall, Some(1.0), "check recall");
+        assert_eq!(kind_size(&conn, &compressed.kind), Some((5, 4)), "check size");
+        assert_eq!(Pipeline::load(&conn, &compressed.kind), Some(pipeline), "check pipeline");
+        assert_eq!(get_property(&conn, &compressed.kind, BUCKET), Some("10".to_string()), "check n-gram properties");
+
+        match (lookup_vector(&conn, "король", None, &kind), lookup_vector(&conn, "король", None, &compressed.kind)) {
+            (Some((_, vec)), Some((_, decoded))) => assert_eq!(vec, decoded, "check decoded"),
+            _ => assert!(false, "failed to look up a compressed word"),
+        }
+
+        match CompressedSpace::load(&conn, &compressed.kind, Some(3)) {
+            Ok(space) => assert_eq!(space.len(), 3, "check limit"),
+            Err(err) => assert!(false, "failed to load codes: {}", err),
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use command::{EXIT_OK, connect, strategies, strategy_arg, db_arg, json_vec, kind, kind_arg};
+use db::models::vector::kind_size;
+use embed::{Embedding, Weighting, embed};
+use lookup::Lookup;
+use npy;
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("embed-text")
+        .about("Print a vector of every line of a text by word vectors of a kind")
+        .arg(db_arg())
+        .arg(kind_arg())
+        .arg(strategy_arg())
+        .arg(Arg::with_name("lang")
+            .long("lang")
+            .takes_value(true)
+            .help("language of words"))
+        .arg(Arg::with_name("weighting")
+            .short("w")
+            .long("weighting")
+            .takes_value(true)
+            .possible_values(&["mean", "tfidf", "sif"])
+            .default_value("mean")
+            .help("mean of word vectors, TF-IDF over input lines or SIF by frequency ranks"))
+        .arg(Arg::with_name("sif_a")
+            .long("sif-a")
+            .takes_value(true)
+            .default_value("0.001")
+            .help("smoothing parameter of SIF weights a / (a + p(word))"))
+        .arg(Arg::with_name("format")
+            .short("f")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&["json", "npy"])
+            .default_value("json")
+            .help("output format: JSON lines or a float32 matrix of numpy"))
+        .arg(Arg::with_name("output")
+            .short("o")
+            .long("output")
+            .takes_value(true)
+            .help("output file instead of stdout"))
+        .arg(Arg::with_name("input")
+            .help("text file, stdin without it or with '-'"))
+}
+
+fn texts(args: &ArgMatches) -> Result<Vec<String>, String> {
+    let lines = match args.value_of("input") {
+        Some(path) if path != "-" => {
+            let file = File::open(path).map_err(|err| format!("failed to open '{}': {}", path, err))?;
+
+            BufReader::new(file).lines().collect::<Result<Vec<_>, _>>()
+        }
+        _ => {
+            let stdin = io::stdin();
+            let lines = stdin.lock().lines().collect();
+
+            lines
+        }
+    };
+
+    lines.map_err(|err| format!("failed to read texts: {}", err))
+}
+
+fn weighting(args: &ArgMatches) -> Result<Weighting, String> {
+    let sif_a = value_t!(args, "sif_a", f32).map_err(|err| err.to_string())?;
+
+    if sif_a <= 0.0 {
+        return Err(format!("SIF parameter should be positive, got {}", sif_a));
+    }
+
+    Weighting::parse(args.value_of("weighting").unwrap_or_default(), sif_a)
+        .ok_or_else(|| "unknown weighting".to_string())
+}
+
+fn write_json(out: &mut Write, texts: &[String], embeddings: &[Embedding]) -> io::Result<()> {
+    for (text, embedding) in texts.iter().zip(embeddings.iter()) {
+        writeln!(out, "{}", json!({
+            "text": text,
+            "tokens": embedding.tokens,
+            "missing": embedding.missing,
+            "vector": json_vec(&embedding.vec),
+        }))?;
+    }
+
+    out.flush()
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let kind = kind(&conn, args)?;
+    let weighting = weighting(args)?;
+    let lookup = Lookup::new(&conn, &kind, &strategies(args)?);
+    let texts = texts(args)?;
+
+    let embeddings = embed(&conn, &lookup, &texts, args.value_of("lang"), weighting);
+
+    let mut out: Box<Write> = match args.value_of("output") {
+        Some(path) => Box::new(BufWriter::new(File::create(path).map_err(|err| format!("failed to create '{}': {}", path, err))?)),
+        None => Box::new(BufWriter::new(io::stdout())),
+    };
+
+    let written = if args.value_of("format") == Some("npy") {
+        let (_, dim) = kind_size(&conn, &kind).unwrap_or_default();
+        let rows = embeddings.iter().map(|embedding| embedding.vec.clone()).collect::<Vec<_>>();
+
+        npy::write_f32(&mut out, &rows, dim as usize)
+    } else {
+        write_json(&mut *out, &texts, &embeddings)
+    };
+
+    written.map_err(|err| format!("failed to write vectors: {}", err))?;
+
+    let tokens = embeddings.iter().map(|embedding| embedding.tokens).sum::<usize>();
+    let missing = embeddings.iter().map(|embedding| embedding.missing.len()).sum::<usize>();
+
+    eprintln!("{} texts, {} tokens, {} missing ({:.1}%)", texts.len(), tokens, missing,
+              if tokens > 0 { 100.0 * missing as f64 / tokens as f64 } else { 0.0 });
+
+    Ok(EXIT_OK)
+}
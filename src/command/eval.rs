@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::path::Path;
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+
+use analogy::{Method, solve};
+use command::{EXIT_OK, connect, db_arg, format_arg, kind_by_name, limit, limit_arg, strategies, strategy_arg};
+use db::connection::DsjConnection;
+use eval::{Pair, Section, pearson, read_pairs, read_questions, spearman};
+use lookup::Lookup;
+use space::{Space, cosine};
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("eval")
+        .about("Evaluate kinds on word benchmarks")
+        .setting(AppSettings::SubcommandRequiredElseHelp)
+        .subcommand(SubCommand::with_name("similarity")
+            .about("Correlate cosine similarities of word pairs with human scores of SimLex, WordSim or RuSim files")
+            .arg(db_arg())
+            .arg(kinds_arg())
+            .arg(strategy_arg())
+            .arg(Arg::with_name("dataset")
+                .long("dataset")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .required(true)
+                .help("file of word pairs with scores, repeated for several files"))
+            .arg(format_arg("output format: a table or JSON lines")))
+        .subcommand(SubCommand::with_name("analogy")
+            .about("Answer questions of the word2vec questions-words format and report accuracy by sections")
+            .arg(db_arg())
+            .arg(kinds_arg())
+            .arg(Arg::with_name("method")
+                .short("m")
+                .long("method")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1)
+                .possible_values(&["add", "mul"])
+                .default_value("add")
+                .help("scoring: 3CosAdd or 3CosMul, repeated to compare them"))
+            .arg(limit_arg()
+                .default_value("30000")
+                .help("answer among the top N ranked (most frequent) words, questions with other words are skipped"))
+            .arg(format_arg("output format: tables or JSON lines"))
+            .arg(Arg::with_name("questions")
+                .required(true)
+                .help("file of ': section' headers and 'a b c d' questions")))
+}
+
+fn kinds_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("kind")
+        .short("k")
+        .long("kind")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .required(true)
+        .help("kind of word vectors, repeated to compare several kinds")
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    match args.subcommand() {
+        ("similarity", Some(args)) => run_similarity(args),
+        ("analogy", Some(args)) => run_analogy(args),
+        (name, _) => Err(format!("unknown eval command '{}'", name)),
+    }
+}
+
+fn lookups(conn: &DsjConnection, args: &ArgMatches) -> Result<Vec<Lookup>, String> {
+    let strategies = strategies(args)?;
+
+    args.values_of("kind").map(|kinds| kinds.collect::<Vec<_>>()).unwrap_or_default()
+        .into_iter()
+        .map(|name| kind_by_name(conn, name).map(|kind| Lookup::new(conn, &kind, &strategies)))
+        .collect()
+}
+
+/// Correlations of cosine similarities of pairs found in a kind with human scores.
+#[derive(Debug, PartialEq)]
+pub struct Similarity {
+    pub pairs: usize,
+    pub found: usize,
+    pub spearman: Option<f64>,
+    pub pearson: Option<f64>,
+}
+
+impl Similarity {
+    pub fn coverage(&self) -> f64 {
+        self.found as f64 / self.pairs.max(1) as f64
+    }
+}
+
+pub fn similarity(conn: &DsjConnection, lookup: &Lookup, pairs: &[Pair]) -> Similarity {
+    let mut vectors: HashMap<&str, Option<Vec<f32>>> = HashMap::new();
+    let (mut scores, mut similarities) = (Vec::new(), Vec::new());
+
+    for pair in pairs.iter() {
+        for w in [&pair.first, &pair.second].iter() {
+            if !vectors.contains_key(w.as_str()) {
+                vectors.insert(w.as_str(), lookup.find(conn, w, None).map(|found| found.vec));
+            }
+        }
+
+        if let (&Some(ref a), &Some(ref b)) = (&vectors[pair.first.as_str()], &vectors[pair.second.as_str()]) {
+            scores.push(pair.score);
+            similarities.push(cosine(a, b) as f64);
+        }
+    }
+
+    Similarity {
+        pairs: pairs.len(),
+        found: scores.len(),
+        spearman: spearman(&scores, &similarities),
+        pearson: pearson(&scores, &similarities),
+    }
+}
+
+fn correlation(value: Option<f64>) -> String {
+    value.map(|value| format!("{:.4}", value)).unwrap_or_else(|| "-".to_string())
+}
+
+fn run_similarity(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let lookups = lookups(&conn, args)?;
+    let paths = args.values_of("dataset").map(|paths| paths.collect::<Vec<_>>()).unwrap_or_default();
+    let json = args.value_of("format") == Some("json");
+
+    let mut rows = Vec::new();
+
+    for path in paths {
+        let pairs = read_pairs(path)?;
+        let dataset = Path::new(path).file_name().and_then(|name| name.to_str()).unwrap_or(path).to_string();
+
+        for lookup in lookups.iter() {
+            rows.push((dataset.clone(), lookup.kind().name.clone(), similarity(&conn, lookup, &pairs)));
+        }
+    }
+
+    if json {
+        for &(ref dataset, ref kind, ref result) in rows.iter() {
+            println!("{}", json!({
+                "dataset": dataset,
+                "kind": kind,
+                "pairs": result.pairs,
+                "found": result.found,
+                "coverage": result.coverage(),
+                "spearman": result.spearman,
+                "pearson": result.pearson,
+            }));
+        }
+
+        return Ok(EXIT_OK);
+    }
+
+    let dataset_width = rows.iter().map(|row| row.0.chars().count()).max().unwrap_or_default().max("dataset".len());
+    let kind_width = rows.iter().map(|row| row.1.chars().count()).max().unwrap_or_default().max("kind".len());
+
+    println!("{:<dw$}  {:<kw$}  {:>6}  {:>6}  {:>8}  {:>8}  {:>8}", "dataset", "kind", "pairs", "found", "coverage", "spearman", "pearson",
+             dw = dataset_width, kw = kind_width);
+
+    for &(ref dataset, ref kind, ref result) in rows.iter() {
+        println!("{:<dw$}  {:<kw$}  {:>6}  {:>6}  {:>7.2}%  {:>8}  {:>8}", dataset, kind, result.pairs, result.found, result.coverage() * 100.0,
+                 correlation(result.spearman), correlation(result.pearson), dw = dataset_width, kw = kind_width);
+    }
+
+    Ok(EXIT_OK)
+}
+
+/// Answers of questions of a section, ones with words out of a vocabulary are skipped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Accuracy {
+    pub section: String,
+    pub questions: usize,
+    pub skipped: usize,
+    pub correct: usize,
+}
+
+impl Accuracy {
+    pub fn accuracy(&self) -> f64 {
+        self.correct as f64 / (self.questions - self.skipped).max(1) as f64
+    }
+
+    fn add(&mut self, other: &Accuracy) {
+        self.questions += other.questions;
+        self.skipped += other.skipped;
+        self.correct += other.correct;
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "section": self.section,
+            "questions": self.questions,
+            "skipped": self.skipped,
+            "correct": self.correct,
+            "accuracy": self.accuracy(),
+        })
+    }
+}
+
+/// Answers "a b c d" by the nearest word to b - a + c except a, b and c, words are matched case-insensitively.
+pub fn analogies(space: &Space, sections: &[Section], method: Method) -> Vec<Accuracy> {
+    let mut rows = HashMap::with_capacity(space.len());
+
+    // the most frequent word of ones differing in case
+    for row in 0..space.len() {
+        rows.entry(space.word(row).word.to_lowercase()).or_insert(row);
+    }
+
+    sections.iter()
+        .map(|section| {
+            let mut accuracy = Accuracy {
+                section: section.name.clone(),
+                questions: section.questions.len(),
+                ..Accuracy::default()
+            };
+
+            for question in section.questions.iter() {
+                let found = question.iter().map(|w| rows.get(&w.to_lowercase()).cloned()).collect::<Option<Vec<_>>>();
+
+                let found = match found {
+                    Some(found) => found,
+                    None => {
+                        accuracy.skipped += 1;
+                        continue;
+                    }
+                };
+
+                let positive = [space.vector(found[1]).to_vec(), space.vector(found[2]).to_vec()];
+                let negative = [space.vector(found[0]).to_vec()];
+
+                let answer = solve(space, &positive, &negative, method, 1, &found[..3]);
+
+                if answer.first().map(|neighbor| neighbor.row == found[3]).unwrap_or(false) {
+                    accuracy.correct += 1;
+                }
+            }
+
+            accuracy
+        })
+        .collect()
+}
+
+fn total(sections: &[Accuracy]) -> Accuracy {
+    let mut total = Accuracy {
+        section: "total".to_string(),
+        ..Accuracy::default()
+    };
+
+    for section in sections.iter() {
+        total.add(section);
+    }
+
+    total
+}
+
+fn run_analogy(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let sections = read_questions(args.value_of("questions").unwrap_or_default())?;
+    let limit = limit(args)?;
+    let json = args.value_of("format") == Some("json");
+
+    let methods = args.values_of("method").map(|methods| methods.collect::<Vec<_>>()).unwrap_or_default()
+        .into_iter()
+        .filter_map(Method::parse)
+        .collect::<Vec<_>>();
+
+    let kinds = args.values_of("kind").map(|kinds| kinds.collect::<Vec<_>>()).unwrap_or_default()
+        .into_iter()
+        .map(|name| kind_by_name(&conn, name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for kind in kinds.iter() {
+        let space = Space::load(&conn, kind, limit)?;
+
+        for &method in methods.iter() {
+            let accuracies = analogies(&space, &sections, method);
+            let total = total(&accuracies);
+
+            if json {
+                println!("{}", json!({
+                    "kind": kind.name,
+                    "method": method.name(),
+                    "words": space.len(),
+                    "sections": accuracies.iter().map(Accuracy::to_json).collect::<Vec<_>>(),
+                    "total": total.to_json(),
+                }));
+                continue;
+            }
+
+            let width = accuracies.iter().map(|accuracy| accuracy.section.chars().count()).max().unwrap_or_default().max("section".len());
+
+            println!("{}, 3Cos{} among {} words", kind.name, if method == Method::CosAdd { "Add" } else { "Mul" }, space.len());
+            println!("{:<w$}  {:>9}  {:>7}  {:>7}  {:>8}", "section", "questions", "skipped", "correct", "accuracy", w = width);
+
+            for accuracy in accuracies.iter().chain(Some(&total)) {
+                println!("{:<w$}  {:>9}  {:>7}  {:>7}  {:>7.2}%", accuracy.section, accuracy.questions, accuracy.skipped, accuracy.correct,
+                         accuracy.accuracy() * 100.0, w = width);
+            }
+
+            println!();
+        }
+    }
+
+    Ok(EXIT_OK)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::kind::get_kind;
+    use db::models::testing::test_connection;
+    use eval::parse_questions;
+    use lookup::Strategy;
+    use space::testing::test_space;
+
+    #[test]
+    fn test_similarity() {
+        let conn = test_connection();
+        let kind = get_kind(&conn, "fasttext").unwrap_or_default();
+        let lookup = Lookup::new(&conn, &kind, &[Strategy::Exact]);
+
+        let pairs = [("король", "королева", 8.0), ("король", "яблоко", 1.0), ("мужчина", "женщина", 7.0), ("яблоко", "груша", 9.0)].iter()
+            .map(|&(first, second, score)| Pair {
+                first: first.to_string(),
+                second: second.to_string(),
+                score,
+            })
+            .collect::<Vec<_>>();
+
+        let result = similarity(&conn, &lookup, &pairs);
+
+        assert_eq!((result.pairs, result.found), (4, 3), "check found");
+        assert!((result.coverage() - 0.75).abs() < 1e-12, "check coverage");
+        assert!((result.spearman.unwrap_or_default() - 1.0).abs() < 1e-12, "check spearman {:?}", result.spearman);
+        assert!(result.pearson.map(|pearson| pearson > 0.5 && pearson < 1.0).unwrap_or(false), "check pearson {:?}", result.pearson);
+    }
+
+    #[test]
+    fn test_analogies() {
+        let space = test_space();
+        let text = ": royal\nмужчина король женщина королева\nКороль мужчина королева женщина\n: fruit\nяблоко груша слива вишня\n";
+
+        let sections = match parse_questions(text) {
+            Ok(sections) => sections,
+            Err(err) => {
+                assert!(false, "failed to parse questions: {}", err);
+                return;
+            }
+        };
+
+        for &method in [Method::CosAdd, Method::CosMul].iter() {
+            let accuracies = analogies(&space, &sections, method);
+
+            assert_eq!(accuracies[0], Accuracy {
+                section: "royal".to_string(),
+                questions: 2,
+                skipped: 0,
+                correct: 2,
+            }, "check royal {}", method.name());
+            assert_eq!((accuracies[1].skipped, accuracies[1].correct), (1, 0), "check skipped {}", method.name());
+
+            let total = total(&accuracies);
+
+            assert_eq!((total.questions, total.skipped, total.correct), (3, 1, 2), "check total");
+            assert!((total.accuracy() - 1.0).abs() < 1e-12, "check accuracy");
+        }
+    }
+}
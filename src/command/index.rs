@@ -0,0 +1,76 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use command::{EXIT_OK, EXIT_FAILURE, connect, db_arg, kind, kind_arg, limit, limit_arg, top, top_arg};
+use hnsw::{Hnsw, Params};
+use space::Space;
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("index")
+        .about("Build an approximate nearest-neighbour (HNSW) index of a kind and report its recall")
+        .arg(db_arg())
+        .arg(kind_arg())
+        .arg(limit_arg())
+        .arg(Arg::with_name("m")
+            .short("m")
+            .long("m")
+            .takes_value(true)
+            .default_value("16")
+            .help("count of links of a word per graph layer"))
+        .arg(Arg::with_name("ef_construction")
+            .long("ef-construction")
+            .takes_value(true)
+            .default_value("200")
+            .help("size of a candidate list while building"))
+        .arg(ef_arg())
+        .arg(top_arg())
+        .arg(Arg::with_name("sample")
+            .short("s")
+            .long("sample")
+            .takes_value(true)
+            .default_value("100")
+            .help("count of words to measure recall against exact search, 0 skips it"))
+}
+
+pub fn ef_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("ef")
+        .long("ef")
+        .takes_value(true)
+        .default_value("64")
+        .help("size of a candidate list while searching with an index")
+}
+
+pub fn ef(args: &ArgMatches) -> Result<usize, String> {
+    value_t!(args, "ef", usize).map_err(|err| err.to_string())
+}
+
+fn params(args: &ArgMatches) -> Result<Params, String> {
+    Ok(Params {
+        m: value_t!(args, "m", usize).map_err(|err| err.to_string())?,
+        ef_construction: value_t!(args, "ef_construction", usize).map_err(|err| err.to_string())?,
+    })
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let kind = kind(&conn, args)?;
+    let limit = limit(args)?;
+    let params = params(args)?;
+    let sample = value_t!(args, "sample", usize).map_err(|err| err.to_string())?;
+
+    let space = Space::load(&conn, &kind, limit)?;
+    let hnsw = Hnsw::build(&space, params);
+
+    if !hnsw.save(&conn, &kind, &space) {
+        return Ok(EXIT_FAILURE);
+    }
+
+    println!("{}: indexed {} words (m = {}, ef construction = {})", kind.name, space.len(), params.m, params.ef_construction);
+
+    if sample > 0 {
+        let (top, ef) = (top(args)?, ef(args)?);
+
+        println!("recall@{} (ef = {}) on {} words: {:.4}", top, ef, sample.min(space.len()), hnsw.recall(&space, sample, top, ef));
+    }
+
+    Ok(EXIT_OK)
+}
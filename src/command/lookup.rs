@@ -0,0 +1,125 @@
+use std::io::{self, BufRead};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use command::{EXIT_OK, EXIT_MISSING, connect, report_match, strategies, strategy_arg, db_arg, json_vec, kind, kind_arg};
+use lookup::{Found, Lookup};
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("lookup")
+        .about("Print vectors of words, read words from stdin without arguments or with '-'")
+        .arg(db_arg())
+        .arg(kind_arg())
+        .arg(strategy_arg())
+        .arg(Arg::with_name("lang")
+            .long("lang")
+            .takes_value(true)
+            .help("language of words"))
+        .arg(Arg::with_name("format")
+            .short("f")
+            .long("format")
+            .takes_value(true)
+            .possible_values(&["text", "json"])
+            .default_value("text")
+            .help("output format: 'word v1 v2 ...' lines or JSON lines"))
+        .arg(Arg::with_name("words")
+            .multiple(true)
+            .help("words to look up"))
+}
+
+fn words(args: &ArgMatches) -> Vec<String> {
+    let words = args.values_of("words")
+        .map(|values| values.map(|value| value.to_string()).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    if words.len() > 0 && words.iter().all(|word| word != "-") {
+        return words;
+    }
+
+    let stdin = io::stdin();
+    let stdin_words = stdin.lock().lines()
+        .filter_map(|line| line.ok())
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>();
+
+    words.into_iter()
+        .filter(|word| word != "-")
+        .chain(stdin_words.into_iter())
+        .collect()
+}
+
+fn format_text(word: &str, vec: &[f32]) -> String {
+    let points = vec.iter()
+        .map(|point| point.to_string())
+        .collect::<Vec<_>>();
+
+    format!("{} {}", word, points.join(" "))
+}
+
+fn format_json(word: &str, found: &Found) -> String {
+    json!({
+        "word": word,
+        "lang": found.lang(),
+        "match": found.matched.name(),
+        "found": found.stored(),
+        "vector": json_vec(&found.vec),
+    }).to_string()
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let kind = kind(&conn, args)?;
+    let lang = args.value_of("lang");
+    let json = args.value_of("format") == Some("json");
+    let lookup = Lookup::new(&conn, &kind, &strategies(args)?);
+
+    let mut missing = Vec::new();
+
+    for w in words(args).iter() {
+        match lookup.find(&conn, w, lang) {
+            Some(found) => if json {
+                println!("{}", format_json(w, &found));
+            } else {
+                report_match(w, &found);
+                println!("{}", format_text(w, &found.vec));
+            },
+            None => missing.push(w.to_string()),
+        }
+    }
+
+    if missing.is_empty() {
+        return Ok(EXIT_OK);
+    }
+
+    if json {
+        eprintln!("{}", json!({ "missing": missing }));
+    } else {
+        eprintln!("missing: {}", missing.join(" "));
+    }
+
+    Ok(EXIT_MISSING)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use serde_json;
+    use db::models::word::Word;
+    use lookup::Strategy;
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format_text("мир", &[0.5f32, -1.25, 3.0]), "мир 0.5 -1.25 3", "check text");
+
+        let found = Found {
+            word: Some(Word { word: "мир".to_string(), lang: "ru".to_string(), ..Word::default() }),
+            vec: vec![0.5f32, -1.25],
+            matched: Strategy::Exact,
+        };
+
+        match serde_json::from_str::<serde_json::Value>(&format_json("мир", &found)) {
+            Ok(value) => assert_eq!(value, json!({"word": "мир", "lang": "ru", "match": "exact", "found": "мир", "vector": [0.5, -1.25]}), "check json"),
+            Err(err) => assert!(false, "failed to parse json output with {:?}", err),
+        }
+    }
+}
@@ -0,0 +1,208 @@
+mod align;
+mod analogy;
+mod compress;
+mod embed_text;
+mod eval;
+mod index;
+mod lookup;
+mod neighbors;
+mod quantization;
+mod reduce;
+mod serve;
+mod shell;
+mod sql;
+mod stats;
+mod translate;
+mod wmd;
+
+use clap::{App, Arg, ArgMatches};
+use serde_json::Value;
+use db::connection::{DsjConnection, establish_connection};
+use db::models::kind::{Kind, get_kind};
+use db::models::word::Word;
+use lookup::{Found, STRATEGIES, Strategy};
+use space::{Neighbor, Space};
+
+pub const EXIT_OK: i32 = 0;
+pub const EXIT_MISSING: i32 = 1;
+pub const EXIT_FAILURE: i32 = 2;
+
+pub fn sub_commands<'a>() -> Vec<App<'a, 'a>> {
+    vec![
+        align::sub_command(),
+        analogy::sub_command(),
+        compress::sub_command(),
+        embed_text::sub_command(),
+        eval::sub_command(),
+        index::sub_command(),
+        lookup::sub_command(),
+        neighbors::sub_command(),
+        quantization::sub_command(),
+        reduce::sub_command(),
+        serve::sub_command(),
+        shell::sub_command(),
+        sql::sub_command(),
+        stats::sub_command(),
+        translate::sub_command(),
+        wmd::sub_command(),
+    ]
+}
+
+pub fn run(name: &str, args: &ArgMatches) -> i32 {
+    let result = match name {
+        "align" => align::run(args),
+        "analogy" => analogy::run(args),
+        "compress" => compress::run(args),
+        "embed-text" => embed_text::run(args),
+        "eval" => eval::run(args),
+        "index" => index::run(args),
+        "lookup" => lookup::run(args),
+        "neighbors" => neighbors::run(args),
+        "quantization" => quantization::run(args),
+        "reduce" => reduce::run(args),
+        "serve" => serve::run(args),
+        "shell" => shell::run(args),
+        "sql" => sql::run(args),
+        "stats" => stats::run(args),
+        "translate" => translate::run(args),
+        "wmd" => wmd::run(args),
+        _ => Err(format!("unknown command '{}'", name)),
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("{}", err);
+            EXIT_FAILURE
+        }
+    }
+}
+
+fn db_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("db_uri")
+        .short("d")
+        .long("db")
+        .takes_value(true)
+        .default_value("wordvector.db")
+        .help("path to a database")
+}
+
+fn kind_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("kind")
+        .short("k")
+        .long("kind")
+        .takes_value(true)
+        .required(true)
+        .help("kind of word vectors, e.g. fasttext or glove_en")
+}
+
+fn top_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("top")
+        .short("n")
+        .long("top")
+        .takes_value(true)
+        .default_value("10")
+        .help("count of nearest words")
+}
+
+fn limit_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("limit")
+        .short("l")
+        .long("limit")
+        .takes_value(true)
+        .help("search among the top N ranked (most frequent) words only")
+}
+
+fn format_arg<'a>(help: &'a str) -> Arg<'a, 'a> {
+    Arg::with_name("format")
+        .short("f")
+        .long("format")
+        .takes_value(true)
+        .possible_values(&["text", "json"])
+        .default_value("text")
+        .help(help)
+}
+
+fn strategy_arg<'a>() -> Arg<'a, 'a> {
+    Arg::with_name("strategy")
+        .long("strategy")
+        .takes_value(true)
+        .default_value(STRATEGIES)
+        .help("comma separated chain of lookup strategies")
+}
+
+fn connect(args: &ArgMatches) -> Result<DsjConnection, String> {
+    establish_connection(args.value_of("db_uri").unwrap_or("wordvector.db"))
+}
+
+fn kind(conn: &DsjConnection, args: &ArgMatches) -> Result<Kind, String> {
+    kind_by_name(conn, args.value_of("kind").unwrap_or_default())
+}
+
+fn kind_by_name(conn: &DsjConnection, name: &str) -> Result<Kind, String> {
+    match get_kind(conn, name) {
+        Some(kind) => Ok(kind),
+        None => Err(format!("kind '{}' doesn't found", name)),
+    }
+}
+
+fn top(args: &ArgMatches) -> Result<usize, String> {
+    value_t!(args, "top", usize).map_err(|err| err.to_string())
+}
+
+fn limit(args: &ArgMatches) -> Result<Option<i64>, String> {
+    match args.value_of("limit") {
+        Some(_) => value_t!(args, "limit", i64).map(Some).map_err(|err| err.to_string()),
+        None => Ok(None),
+    }
+}
+
+fn strategies(args: &ArgMatches) -> Result<Vec<Strategy>, String> {
+    Strategy::parse_chain(args.value_of("strategy").unwrap_or(STRATEGIES))
+}
+
+// a word found by another strategy than an exact match is reported to stderr
+fn report_match(w: &str, found: &Found) {
+    match (found.matched, found.stored()) {
+        (Strategy::Exact, _) => (),
+        (matched, Some(stored)) => eprintln!("{}: {} match '{}'", w, matched.name(), stored),
+        (matched, None) => eprintln!("{}: {} match", w, matched.name()),
+    }
+}
+
+fn print_neighbors(space: &Space, neighbors: &[Neighbor], args: &ArgMatches) {
+    for neighbor in neighbors.iter() {
+        print_neighbor(space.word(neighbor.row), neighbor.similarity, args);
+    }
+}
+
+fn print_neighbor(word: &Word, similarity: f32, args: &ArgMatches) {
+    if args.value_of("format") == Some("json") {
+        println!("{}", json_word(word, similarity));
+    } else {
+        println!("{}\t{:.6}", word.word, similarity);
+    }
+}
+
+pub fn json_neighbor(space: &Space, neighbor: &Neighbor) -> Value {
+    json_word(space.word(neighbor.row), neighbor.similarity)
+}
+
+fn json_word(word: &Word, similarity: f32) -> Value {
+    json!({
+        "word": word.word,
+        "lang": word.lang,
+        "similarity": json_f32(similarity),
+    })
+}
+
+// f32 -> f64 keeps the shortest f32 representation in JSON output
+pub fn json_f32(value: f32) -> f64 {
+    value.to_string().parse::<f64>().unwrap_or_default()
+}
+
+pub fn json_vec(vec: &[f32]) -> Vec<f64> {
+    vec.iter()
+        .map(|&point| json_f32(point))
+        .collect()
+}
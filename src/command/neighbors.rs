@@ -0,0 +1,79 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use command::index::{ef, ef_arg};
+use command::{EXIT_OK, EXIT_MISSING, connect, report_match, strategies, strategy_arg, db_arg, format_arg, kind, kind_arg, limit, limit_arg, print_neighbor, print_neighbors, top, top_arg};
+use hnsw::Hnsw;
+use lookup::Lookup;
+use pq::{CompressedSpace, ProductQuantizer};
+use space::Space;
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("neighbors")
+        .about("Print the nearest words by cosine similarity")
+        .arg(db_arg())
+        .arg(kind_arg())
+        .arg(strategy_arg())
+        .arg(Arg::with_name("lang")
+            .long("lang")
+            .takes_value(true)
+            .help("language of a word"))
+        .arg(top_arg())
+        .arg(limit_arg())
+        .arg(ef_arg())
+        .arg(Arg::with_name("exact")
+            .long("exact")
+            .help("search all words even if the kind has an index, decoded vectors of a compressed kind"))
+        .arg(format_arg("output format: 'word similarity' lines or JSON lines"))
+        .arg(Arg::with_name("word")
+            .required(true)
+            .help("word to search neighbours for"))
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let kind = kind(&conn, args)?;
+    let top = top(args)?;
+    let limit = limit(args)?;
+    let w = args.value_of("word").unwrap_or_default();
+
+    let found = match Lookup::new(&conn, &kind, &strategies(args)?).find(&conn, w, args.value_of("lang")) {
+        Some(found) => found,
+        None => {
+            eprintln!("missing: {}", w);
+            return Ok(EXIT_MISSING);
+        }
+    };
+
+    report_match(w, &found);
+
+    // codes of a compressed kind are searched by asymmetric distances without decoding
+    if !args.is_present("exact") && ProductQuantizer::load(&conn, &kind).is_some() {
+        let space = CompressedSpace::load(&conn, &kind, limit)?;
+        let exclude = found.word_id().and_then(|id| space.row(id)).into_iter().collect::<Vec<_>>();
+
+        for neighbor in space.neighbors(&found.vec, top, &exclude) {
+            print_neighbor(space.word(neighbor.row), neighbor.similarity, args);
+        }
+
+        return Ok(EXIT_OK);
+    }
+
+    let space = Space::load(&conn, &kind, limit)?;
+    let exclude = found.word_id().and_then(|id| space.row(id)).into_iter().collect::<Vec<_>>();
+
+    // an index is used only if it was built for the same words and vectors
+    let hnsw = if args.is_present("exact") {
+        None
+    } else {
+        Hnsw::load(&conn, &kind, &space)
+    };
+
+    let neighbors = match hnsw {
+        Some(hnsw) => hnsw.search(&space, &found.vec, top, ef(args)?, &exclude),
+        None => space.neighbors(&found.vec, top, &exclude),
+    };
+
+    print_neighbors(&space, &neighbors, args);
+
+    Ok(EXIT_OK)
+}
@@ -0,0 +1,168 @@
+use std::collections::HashSet;
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use command::{EXIT_OK, connect, db_arg, kind, kind_arg, limit, limit_arg, top, top_arg};
+use quantize::{PRECISIONS, Precision, Quantizer};
+use space::{Space, cosine};
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("quantization")
+        .about("Report reconstruction error and changes of nearest words of a kind stored at a lower precision")
+        .arg(db_arg())
+        .arg(kind_arg())
+        .arg(limit_arg())
+        .arg(top_arg())
+        .arg(Arg::with_name("precision")
+            .short("p")
+            .long("precision")
+            .takes_value(true)
+            .possible_values(&PRECISIONS[1..])
+            .default_value("int8")
+            .help("storage precision to compare with f32 vectors"))
+        .arg(Arg::with_name("sample")
+            .short("s")
+            .long("sample")
+            .takes_value(true)
+            .default_value("100")
+            .help("count of words to compare nearest words, 0 skips it"))
+}
+
+/// Differences of quantized vectors from f32 ones.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub bytes: usize,
+    pub rmse: f32,
+    pub max_error: f32,
+    // a mean cosine similarity of a vector and its reconstruction
+    pub cosine: f32,
+    // a share of f32 top neighbours kept by quantized vectors
+    pub overlap: f32,
+    // a mean absolute change of similarities to f32 top neighbours
+    pub similarity: f32,
+}
+
+pub fn report(space: &Space, precision: Precision, sample: usize, top: usize) -> Result<Report, String> {
+    if space.len() == 0 {
+        return Err("no vectors to quantize".to_string());
+    }
+
+    let mut quantizer = Quantizer::new(precision);
+
+    if !quantizer.is_fitted() {
+        quantizer.fit((0..space.len()).map(|row| space.vector(row).to_vec()))?;
+    }
+
+    let mut report = Report::default();
+    let mut squares = 0.0f64;
+    let mut cosines = 0.0f64;
+
+    let decoded = (0..space.len())
+        .map(|row| {
+            let vec = space.vector(row);
+            let bytes = quantizer.encode(vec);
+            let decoded = quantizer.decode(&bytes);
+
+            for (a, b) in vec.iter().zip(decoded.iter()) {
+                squares += ((a - b) * (a - b)) as f64;
+                report.max_error = report.max_error.max((a - b).abs());
+            }
+
+            cosines += cosine(vec, &decoded) as f64;
+            report.bytes = report.bytes.max(bytes.len());
+
+            decoded
+        })
+        .collect::<Vec<_>>();
+
+    report.rmse = (squares / (space.len() * space.dim()).max(1) as f64).sqrt() as f32;
+    report.cosine = (cosines / space.len() as f64) as f32;
+
+    let quantized = Space::new((0..space.len()).map(|row| space.word(row).clone()).collect(), decoded);
+    let step = (space.len() / sample.max(1)).max(1);
+    let mut expected = 0;
+    let mut kept = 0;
+    let mut changes = 0.0f64;
+
+    for row in (0..space.len()).filter(|row| row % step == 0).take(sample) {
+        let exact = space.neighbors(space.vector(row), top, &[row]);
+        let query = quantized.vector(row);
+        let query_norm = quantized.norm(row);
+
+        let found = quantized.neighbors(query, top, &[row])
+            .into_iter()
+            .map(|neighbor| neighbor.row)
+            .collect::<HashSet<_>>();
+
+        for neighbor in exact.iter() {
+            if found.contains(&neighbor.row) {
+                kept += 1;
+            }
+
+            changes += (quantized.similarity(query, query_norm, neighbor.row) - neighbor.similarity).abs() as f64;
+        }
+
+        expected += exact.len();
+    }
+
+    if expected > 0 {
+        report.overlap = kept as f32 / expected as f32;
+        report.similarity = (changes / expected as f64) as f32;
+    }
+
+    Ok(report)
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let kind = kind(&conn, args)?;
+    let limit = limit(args)?;
+    let top = top(args)?;
+    let sample = value_t!(args, "sample", usize).map_err(|err| err.to_string())?;
+    let precision = args.value_of("precision").and_then(Precision::parse).unwrap_or(Precision::Int8);
+
+    if let Some(quantizer) = Quantizer::load(&conn, &kind) {
+        return Err(format!("kind '{}' is stored as {}, its f32 vectors are lost", kind.name, quantizer.precision().name()));
+    }
+
+    let space = Space::load(&conn, &kind, limit)?;
+    let report = report(&space, precision, sample, top)?;
+
+    println!("{}: {} words, {} bytes per vector instead of {}", kind.name, space.len(), report.bytes, space.dim() * 4);
+    println!("rmse {:.6}, max error {:.6}, cosine to f32 {:.6}", report.rmse, report.max_error, report.cosine);
+
+    if sample > 0 {
+        println!("top@{} overlap on {} words: {:.4}, mean similarity change {:.6}", top, sample.min(space.len()), report.overlap, report.similarity);
+    }
+
+    Ok(EXIT_OK)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use space::testing::test_space;
+
+    #[test]
+    fn test_report() {
+        let space = test_space();
+
+        match report(&space, Precision::F16, space.len(), 2) {
+            Ok(report) => {
+                assert_eq!(report.bytes, space.dim() * 2, "check f16 size");
+                assert!(report.rmse < 1e-3 && report.cosine > 0.9999, "check f16 error {:?}", report);
+                assert!(report.overlap > 0.99 && report.similarity < 1e-3, "check f16 neighbors {:?}", report);
+            }
+            Err(err) => assert!(false, "failed to report: {}", err),
+        }
+
+        match report(&space, Precision::Int8, space.len(), 2) {
+            Ok(report) => {
+                assert_eq!(report.bytes, space.dim(), "check int8 size");
+                assert!(report.max_error > 0.0 && report.overlap > 0.0 && report.overlap <= 1.0, "check int8 {:?}", report);
+            }
+            Err(err) => assert!(false, "failed to report: {}", err),
+        }
+
+        assert!(report(&Space::new(Vec::new(), Vec::new()), Precision::Int8, 1, 1).is_err(), "check empty");
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use diesel::Connection;
+use diesel::result::Error;
+use rand::{self, Rng};
+
+use command::{EXIT_OK, connect, db_arg, kind, kind_arg};
+use db::connection::DsjConnection;
+use db::models::kind::{Kind, create_kind, get_kind};
+use db::models::property::{get_property, set_property};
+use db::models::rank::{Rank, add_rank, ranked_words};
+use db::models::subword::copy_subwords;
+use db::models::vector::{Vector, add_vectors};
+use space::Space;
+use subword::{BUCKET, MAXN, MINN};
+use transform::{Pipeline, Transform};
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("reduce")
+        .about("Write a new kind of vectors projected onto top principal components (PCA) of a kind")
+        .arg(db_arg())
+        .arg(kind_arg())
+        .arg(Arg::with_name("dims")
+            .long("dims")
+            .takes_value(true)
+            .required(true)
+            .help("count of dimensions to keep"))
+        .arg(Arg::with_name("into")
+            .long("into")
+            .takes_value(true)
+            .required(true)
+            .help("name of a new kind"))
+        .arg(Arg::with_name("sample")
+            .short("s")
+            .long("sample")
+            .takes_value(true)
+            .help("fit principal components on N random words instead of all words"))
+}
+
+/// A reduced kind, its number of words and a share of the total variance kept.
+pub struct Reduced {
+    pub kind: Kind,
+    pub words: usize,
+    pub explained: f32,
+}
+
+pub fn reduce(conn: &DsjConnection, kind: &Kind, into: &str, dims: usize, sample: Option<usize>) -> Result<Reduced, String> {
+    if get_kind(conn, into).is_some() {
+        return Err(format!("kind '{}' already exists", into));
+    }
+
+    let space = Space::load(conn, kind, None)?;
+
+    if space.len() == 0 {
+        return Err(format!("kind '{}' has no vectors", kind.name));
+    }
+
+    if dims == 0 || dims > space.dim() {
+        return Err(format!("dimensions should be from 1 to {}", space.dim()));
+    }
+
+    let rows = match sample {
+        Some(sample) if sample < space.len() => {
+            let mut rows = (0..space.len()).collect::<Vec<_>>();
+
+            rand::thread_rng().shuffle(&mut rows);
+            rows.truncate(sample);
+            rows
+        },
+        _ => (0..space.len()).collect(),
+    };
+
+    let mut pca = Pipeline::parse(&format!("pca:{}", dims))?;
+
+    pca.fit(rows.into_iter().map(|row| space.vector(row).to_vec()))?;
+
+    let explained = pca.transforms().iter().filter_map(Transform::explained).next().unwrap_or_default();
+
+    let reduced = match create_kind(conn, into) {
+        Some(reduced) => reduced,
+        None => return Err(format!("failed to create kind '{}'", into)),
+    };
+
+    derive(conn, kind, &space, &reduced, pca)?;
+
+    Ok(Reduced {
+        kind: reduced,
+        words: space.len(),
+        explained,
+    })
+}
+
+/// Writes vectors of a kind changed by a transform as a derived kind with ranks of the original one,
+/// unknown words are composed from n-gram rows of the original kind and changed by its pipeline and the transform.
+pub fn derive(conn: &DsjConnection, kind: &Kind, space: &Space, derived: &Kind, transform: Pipeline) -> Result<(), String> {
+    write_rows(conn, kind, space, derived, |row| {
+        let mut vec = space.vector(row).to_vec();
+
+        if transform.apply(&mut vec).is_err() {
+            return false;
+        }
+
+        add_vectors(conn, &Vector::from_vec(space.word(row), derived, &vec)) > 0
+    });
+
+    inherit(conn, kind, derived, transform)
+}
+
+/// Writes rows of a space to a derived kind in chunks, a written row gets a rank of the original kind.
+pub fn write_rows<F>(conn: &DsjConnection, kind: &Kind, space: &Space, derived: &Kind, write: F)
+    where F: Fn(usize) -> bool
+{
+    let ranks = ranked_words(conn, kind, None).unwrap_or_default()
+        .into_iter()
+        .map(|(word, rank)| (word.id, rank))
+        .collect::<HashMap<_, _>>();
+
+    for chunk in (0..space.len()).collect::<Vec<_>>().chunks(1000) {
+        conn.transaction::<_, Error, _>(|| {
+            for &row in chunk {
+                if !write(row) {
+                    continue;
+                }
+
+                let word = space.word(row);
+
+                if let Some(rank) = ranks.get(&word.id) {
+                    add_rank(conn, &Rank::new(word, derived, rank.rank, rank.source_index));
+                }
+            }
+
+            Ok(())
+        });
+    }
+}
+
+/// Copies n-gram rows of a kind with their properties to a derived kind,
+/// the derived kind keeps the pipeline of the original one followed by a transform.
+pub fn inherit(conn: &DsjConnection, kind: &Kind, derived: &Kind, transform: Pipeline) -> Result<(), String> {
+    let properties = [MINN, MAXN, BUCKET].iter()
+        .filter_map(|&name| get_property(conn, kind, name).map(|value| (name, value)))
+        .collect::<Vec<_>>();
+
+    if properties.len() == 3 {
+        for &(name, ref value) in properties.iter() {
+            set_property(conn, derived, name, value);
+        }
+
+        copy_subwords(conn, kind, derived);
+    }
+
+    let mut pipeline = Pipeline::load(conn, kind).unwrap_or_default();
+
+    pipeline.append(transform);
+
+    if !pipeline.save(conn, derived) {
+        return Err(format!("failed to save transforms of '{}'", derived.name));
+    }
+
+    Ok(())
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let kind = kind(&conn, args)?;
+    let dims = value_t!(args, "dims", usize).map_err(|err| err.to_string())?;
+    let into = args.value_of("into").unwrap_or_default();
+
+    let sample = match args.value_of("sample") {
+        Some(_) => Some(value_t!(args, "sample", usize).map_err(|err| err.to_string())?),
+        None => None,
+    };
+
+    let reduced = reduce(&conn, &kind, into, dims, sample)?;
+
+    println!("{}: {} words of {} dimensions, explained variance {:.4}", reduced.kind.name, reduced.words, dims, reduced.explained);
+
+    Ok(EXIT_OK)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::testing::test_connection;
+    use db::models::vector::kind_size;
+    use lookup::{Lookup, Strategy};
+
+    #[test]
+    fn test_reduce() {
+        let conn = test_connection();
+        let kind = get_kind(&conn, "fasttext").unwrap_or_default();
+
+        assert!(reduce(&conn, &kind, "fasttext", 2, None).is_err(), "check existing kind");
+        assert!(reduce(&conn, &kind, "fasttext_5", 5, None).is_err(), "check too many dimensions");
+
+        let reduced = match reduce(&conn, &kind, "fasttext_2", 2, Some(4)) {
+            Ok(reduced) => reduced,
+            Err(err) => {
+                assert!(false, "failed to reduce: {}", err);
+                return;
+            }
+        };
+
+        assert!(reduced.words == 5 && reduced.explained > 0.0 && reduced.explained <= 1.0, "check reduced {} {}", reduced.words, reduced.explained);
+        assert_eq!(kind_size(&conn, &reduced.kind), Some((5, 2)), "check size");
+
+        // a vector projected by the stored pipeline is the stored one
+        let lookup = Lookup::new(&conn, &reduced.kind, &[Strategy::Exact]);
+        let mut vec = vec![0.9f32, 0.8, 0.1, 0.0];
+
+        match (Pipeline::load(&conn, &reduced.kind), lookup.find(&conn, "король", None)) {
+            (Some(pipeline), Some(found)) => {
+                assert!(pipeline.apply(&mut vec).is_ok(), "check projection");
+                assert!(vec.iter().zip(found.vec.iter()).all(|(a, b)| (a - b).abs() < 1e-5), "check projected {:?} {:?}", vec, found.vec);
+            }
+            _ => assert!(false, "failed to load a projection"),
+        }
+    }
+}
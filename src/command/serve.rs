@@ -0,0 +1,31 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use command::{EXIT_OK, connect, db_arg};
+use server::Server;
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("serve")
+        .about("Serve vectors, similarity, neighbours and analogies as a local HTTP JSON service")
+        .arg(db_arg())
+        .arg(Arg::with_name("bind")
+            .short("b")
+            .long("bind")
+            .takes_value(true)
+            .default_value("127.0.0.1:8080")
+            .help("address to listen on"))
+        .arg(Arg::with_name("cache")
+            .short("c")
+            .long("cache")
+            .takes_value(true)
+            .default_value("10000")
+            .help("count of recently used word vectors kept in memory"))
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let cache = value_t!(args, "cache", usize).map_err(|err| err.to_string())?;
+    let conn = connect(args)?;
+
+    Server::new(conn, cache).serve(args.value_of("bind").unwrap_or_default())?;
+
+    Ok(EXIT_OK)
+}
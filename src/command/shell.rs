@@ -0,0 +1,86 @@
+use std::env;
+use std::path::PathBuf;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use rustyline::Editor;
+use rustyline::error::ReadlineError;
+
+use command::{EXIT_OK, connect, db_arg, top, top_arg};
+use shell::{Session, WordCompleter};
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("shell")
+        .about("Interactive queries with history and completion of words, type 'help' for commands")
+        .arg(db_arg())
+        .arg(Arg::with_name("kind")
+            .short("k")
+            .long("kind")
+            .takes_value(true)
+            .help("kind of word vectors to start with"))
+        .arg(top_arg())
+        .arg(Arg::with_name("history")
+            .long("history")
+            .takes_value(true)
+            .help("history file, ~/.dsj_history by default"))
+}
+
+fn history(args: &ArgMatches) -> Option<PathBuf> {
+    match args.value_of("history") {
+        Some(path) => Some(PathBuf::from(path)),
+        None => env::home_dir().map(|home| home.join(".dsj_history")),
+    }
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let mut session = Session::new(connect(args)?, top(args)?);
+    let mut editor = Editor::<WordCompleter>::new();
+    let history = history(args);
+
+    // completion queries words while a session holds its own connection
+    editor.set_completer(Some(WordCompleter::new(connect(args)?)));
+
+    if let Some(ref path) = history {
+        editor.load_history(path).ok();
+    }
+
+    if let Some(kind) = args.value_of("kind") {
+        match session.execute(&format!("use {}", kind)) {
+            Ok(out) => println!("{}", out),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    loop {
+        let line = match editor.readline(&session.prompt()) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(format!("failed to read a command with {:?}", err)),
+        };
+
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        editor.add_history_entry(line);
+
+        if line == "quit" || line == "exit" {
+            break;
+        }
+
+        match session.execute(line) {
+            Ok(ref out) if out.is_empty() => (),
+            Ok(out) => println!("{}", out),
+            Err(err) => eprintln!("{}", err),
+        }
+    }
+
+    if let Some(ref path) = history {
+        if let Err(err) = editor.save_history(path) {
+            eprintln!("failed to save history to {:?} with {:?}", path, err);
+        }
+    }
+
+    Ok(EXIT_OK)
+}
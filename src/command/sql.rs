@@ -0,0 +1,67 @@
+use std::io::{self, Read};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::{Map, Value};
+
+use command::{EXIT_OK, db_arg, format_arg};
+use db::functions::SqlConnection;
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("sql")
+        .about("Run SQL with dsj_cosine(a, b, kind), dsj_dot(a, b, kind) and dsj_norm(word, kind) functions")
+        .arg(db_arg())
+        .arg(format_arg("output format: tab separated values or JSON lines"))
+        .arg(Arg::with_name("query")
+            .help("SQL statements, read from stdin without it"))
+}
+
+fn format_text(row: &[Value]) -> String {
+    row.iter()
+        .map(|value| match *value {
+            Value::Null => String::new(),
+            Value::String(ref value) => value.clone(),
+            ref value => value.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\t")
+}
+
+fn format_json(columns: &[String], row: Vec<Value>) -> String {
+    Value::Object(columns.iter().cloned().zip(row.into_iter()).collect::<Map<_, _>>()).to_string()
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = SqlConnection::open(args.value_of("db_uri").unwrap_or("wordvector.db"))?;
+    let json = args.value_of("format") == Some("json");
+
+    let query = match args.value_of("query") {
+        Some(query) => query.to_string(),
+        None => {
+            let mut query = String::new();
+
+            io::stdin().read_to_string(&mut query).map_err(|err| format!("failed to read a query: {}", err))?;
+            query
+        }
+    };
+
+    conn.execute(&query, |columns, row| if json {
+        println!("{}", format_json(columns, row));
+    } else {
+        println!("{}", format_text(&row));
+    })?;
+
+    Ok(EXIT_OK)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let row = vec![json!("король"), json!(0.5), Value::Null];
+
+        assert_eq!(format_text(&row), "король\t0.5\t", "check text");
+        assert_eq!(format_json(&["word".to_string(), "cosine".to_string(), "norm".to_string()], row),
+                   r#"{"cosine":0.5,"norm":null,"word":"король"}"#, "check json");
+    }
+}
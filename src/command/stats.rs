@@ -0,0 +1,258 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use serde_json::Value;
+
+use command::{EXIT_OK, connect, db_arg, format_arg, json_f32, kind, kind_arg};
+use db::models::vector::kind_vectors;
+use db::models::word::words_list;
+use space::norm;
+
+const BINS: usize = 10;
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("stats")
+        .about("Report a vocabulary size, dimensions, zero and non-finite vectors, norms and dimension statistics of a kind")
+        .arg(db_arg())
+        .arg(kind_arg())
+        .arg(Arg::with_name("longest")
+            .long("longest")
+            .takes_value(true)
+            .default_value("10")
+            .help("count of the longest words to list"))
+        .arg(format_arg("output format: tables or JSON"))
+}
+
+/// Statistics of vectors of a kind. Norms and dimensions are measured on finite vectors
+/// of the most common dimension.
+#[derive(Debug, Default, PartialEq)]
+pub struct Stats {
+    pub words: usize,
+    // counts of words by a dimension
+    pub dims: BTreeMap<usize, usize>,
+    pub zero: usize,
+    pub nan: usize,
+    pub infinite: usize,
+    // min, median and max
+    pub norms: Option<(f32, f32, f32)>,
+    pub histogram: Vec<usize>,
+    pub means: Vec<f32>,
+    pub deviations: Vec<f32>,
+    pub longest: Vec<String>,
+}
+
+impl Stats {
+    pub fn dim(&self) -> usize {
+        self.dims.iter().max_by_key(|&(_, count)| count).map(|(&dim, _)| dim).unwrap_or_default()
+    }
+
+    /// Bounds of a bin of the norm histogram.
+    pub fn bin(&self, i: usize) -> (f32, f32) {
+        let (min, _, max) = self.norms.unwrap_or_default();
+        let width = (max - min) / self.histogram.len().max(1) as f32;
+
+        (min + width * i as f32, min + width * (i + 1) as f32)
+    }
+
+    fn to_json(&self) -> Value {
+        let norms = self.norms.map(|(min, median, max)| json!({
+            "min": json_f32(min),
+            "median": json_f32(median),
+            "max": json_f32(max),
+            "histogram": self.histogram.iter().enumerate().map(|(i, &count)| {
+                let (from, to) = self.bin(i);
+
+                json!({"from": json_f32(from), "to": json_f32(to), "count": count})
+            }).collect::<Vec<_>>(),
+        }));
+
+        json!({
+            "words": self.words,
+            "dimensions": self.dims.iter().map(|(dim, count)| json!({"dimension": dim, "words": count})).collect::<Vec<_>>(),
+            "zero": self.zero,
+            "nan": self.nan,
+            "infinite": self.infinite,
+            "norms": norms,
+            "means": self.means.iter().map(|&value| json_f32(value)).collect::<Vec<_>>(),
+            "deviations": self.deviations.iter().map(|&value| json_f32(value)).collect::<Vec<_>>(),
+            "longest": self.longest,
+        })
+    }
+}
+
+pub fn stats(vectors: &[(&str, Vec<f32>)], longest: usize) -> Stats {
+    let mut stats = Stats {
+        words: vectors.len(),
+        ..Stats::default()
+    };
+
+    for &(_, ref vec) in vectors.iter() {
+        *stats.dims.entry(vec.len()).or_insert(0) += 1;
+
+        if vec.iter().all(|&value| value == 0.0) {
+            stats.zero += 1;
+        }
+
+        if vec.iter().any(|value| value.is_nan()) {
+            stats.nan += 1;
+        }
+
+        if vec.iter().any(|value| value.is_infinite()) {
+            stats.infinite += 1;
+        }
+    }
+
+    let dim = stats.dim();
+    let finite = vectors.iter()
+        .map(|&(_, ref vec)| vec)
+        .filter(|vec| vec.len() == dim && vec.iter().all(|value| value.is_finite()))
+        .collect::<Vec<_>>();
+
+    let mut norms = finite.iter().map(|vec| norm(vec)).collect::<Vec<_>>();
+
+    norms.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    if let (Some(&min), Some(&max)) = (norms.first(), norms.last()) {
+        let median = if norms.len() % 2 == 1 {
+            norms[norms.len() / 2]
+        } else {
+            (norms[norms.len() / 2 - 1] + norms[norms.len() / 2]) / 2.0
+        };
+
+        stats.norms = Some((min, median, max));
+        stats.histogram = vec![0; BINS];
+
+        for &value in norms.iter() {
+            let bin = if max > min { ((value - min) / (max - min) * BINS as f32) as usize } else { 0 };
+
+            stats.histogram[bin.min(BINS - 1)] += 1;
+        }
+    }
+
+    if !finite.is_empty() {
+        let count = finite.len() as f64;
+        let mut sums = vec![0f64; dim];
+        let mut squares = vec![0f64; dim];
+
+        for vec in finite.iter() {
+            for (i, &value) in vec.iter().enumerate() {
+                sums[i] += value as f64;
+                squares[i] += value as f64 * value as f64;
+            }
+        }
+
+        stats.means = sums.iter().map(|sum| (sum / count) as f32).collect();
+        stats.deviations = sums.iter().zip(squares.iter())
+            .map(|(sum, square)| (square / count - (sum / count) * (sum / count)).max(0.0).sqrt() as f32)
+            .collect();
+    }
+
+    let mut words = vectors.iter().map(|&(word, _)| word).collect::<Vec<_>>();
+
+    words.sort_by(|a, b| b.chars().count().cmp(&a.chars().count()).then_with(|| a.cmp(b)));
+    stats.longest = words.into_iter().take(longest).map(|word| word.to_string()).collect();
+
+    stats
+}
+
+fn print_stats(name: &str, stats: &Stats) {
+    println!("kind          {}", name);
+    println!("words         {}", stats.words);
+
+    for (dim, count) in stats.dims.iter() {
+        println!("dimension     {} ({} words)", dim, count);
+    }
+
+    println!("zero vectors  {}", stats.zero);
+    println!("NaN vectors   {}", stats.nan);
+    println!("Inf vectors   {}", stats.infinite);
+
+    if let Some((min, median, max)) = stats.norms {
+        println!("norms         min {:.6}, median {:.6}, max {:.6}", min, median, max);
+
+        let largest = stats.histogram.iter().cloned().max().unwrap_or_default().max(1);
+
+        for (i, &count) in stats.histogram.iter().enumerate() {
+            let (from, to) = stats.bin(i);
+
+            let line = format!("  {:>12.6} - {:<12.6} {:>8} {}", from, to, count, "#".repeat(count * 40 / largest));
+
+            println!("{}", line.trim_end());
+        }
+    }
+
+    if !stats.longest.is_empty() {
+        println!("longest       {}", stats.longest.iter().map(|word| format!("{} ({})", word, word.chars().count())).collect::<Vec<_>>().join(", "));
+    }
+
+    if !stats.means.is_empty() {
+        println!();
+        println!("{:>9}  {:>12}  {:>12}", "dimension", "mean", "std");
+
+        for (i, (mean, deviation)) in stats.means.iter().zip(stats.deviations.iter()).enumerate() {
+            println!("{:>9}  {:>12.6}  {:>12.6}", i, mean, deviation);
+        }
+    }
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let kind = kind(&conn, args)?;
+    let longest = value_t!(args, "longest", usize).map_err(|err| err.to_string())?;
+
+    let vectors = match kind_vectors(&conn, &kind) {
+        Some(vectors) => vectors,
+        None => return Err(format!("failed to load vectors of '{}'", kind.name)),
+    };
+
+    let words = words_list(&conn).unwrap_or_default()
+        .into_iter()
+        .map(|word| (word.id, word.word))
+        .collect::<HashMap<_, _>>();
+
+    let vectors = vectors.into_iter()
+        .map(|(id, vec)| (words.get(&id).map(|word| word.as_str()).unwrap_or_default(), vec))
+        .collect::<Vec<_>>();
+
+    let stats = stats(&vectors, longest);
+
+    if args.value_of("format") == Some("json") {
+        let mut value = stats.to_json();
+
+        value["kind"] = json!(kind.name);
+        println!("{}", value);
+    } else {
+        print_stats(&kind.name, &stats);
+    }
+
+    Ok(EXIT_OK)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let vectors = vec![
+            ("a", vec![3.0f32, 4.0]),
+            ("bb", vec![1.0f32, 0.0]),
+            ("ccc", vec![0.0f32, 0.0]),
+            ("dddd", vec![::std::f32::NAN, 1.0]),
+            ("ee", vec![::std::f32::INFINITY, 1.0]),
+            ("f", vec![1.0f32, 2.0, 3.0]),
+        ];
+
+        let stats = stats(&vectors, 2);
+
+        assert_eq!((stats.words, stats.dim(), stats.dims.get(&3)), (6, 2, Some(&1)), "check dimensions");
+        assert_eq!((stats.zero, stats.nan, stats.infinite), (1, 1, 1), "check zero and non-finite");
+        assert_eq!(stats.norms, Some((0.0, 1.0, 5.0)), "check norms");
+        assert_eq!((stats.histogram[0], stats.histogram[2], stats.histogram[BINS - 1], stats.histogram.iter().sum::<usize>()), (1, 1, 1, 3), "check histogram");
+        assert_eq!(stats.bin(2), (1.0, 1.5), "check bin");
+        assert_eq!(stats.means, vec![4.0 / 3.0, 4.0 / 3.0], "check means");
+        assert!((stats.deviations[1] - (32f32 / 9.0).sqrt()).abs() < 1e-6, "check deviation {:?}", stats.deviations);
+        assert_eq!(stats.longest, vec!["dddd", "ccc"], "check longest");
+        assert_eq!(stats.to_json()["norms"]["median"], json!(1.0), "check json");
+    }
+}
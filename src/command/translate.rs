@@ -0,0 +1,88 @@
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use command::{EXIT_OK, EXIT_MISSING, connect, db_arg, format_arg, kind_by_name, limit, limit_arg, print_neighbors, report_match, strategies, strategy_arg, top, top_arg};
+use lookup::Lookup;
+use space::Space;
+use translate::{METHODS, Method, translate};
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("translate")
+        .about("Print the nearest words of a target kind to a word of a source kind sharing its space, e.g. aligned by `align`")
+        .arg(db_arg())
+        .arg(Arg::with_name("from")
+            .long("from")
+            .takes_value(true)
+            .required(true)
+            .help("kind of a word, e.g. fasttext_ru_aligned"))
+        .arg(Arg::with_name("to")
+            .long("to")
+            .takes_value(true)
+            .required(true)
+            .help("kind of translations, e.g. fasttext_en"))
+        .arg(strategy_arg())
+        .arg(Arg::with_name("lang")
+            .long("lang")
+            .takes_value(true)
+            .help("language of a word"))
+        .arg(top_arg())
+        .arg(limit_arg())
+        .arg(Arg::with_name("method")
+            .short("m")
+            .long("method")
+            .takes_value(true)
+            .possible_values(&METHODS)
+            .default_value("csls")
+            .help("cosine similarity or cross-domain similarity local scaling, which demotes hubs"))
+        .arg(Arg::with_name("knn")
+            .long("knn")
+            .takes_value(true)
+            .default_value("10")
+            .help("count of neighbours of a CSLS neighbourhood"))
+        .arg(Arg::with_name("candidates")
+            .long("candidates")
+            .takes_value(true)
+            .default_value("100")
+            .help("count of the nearest words by cosine reranked by CSLS"))
+        .arg(format_arg("output format: 'word score' lines or JSON lines"))
+        .arg(Arg::with_name("word")
+            .required(true)
+            .help("word to translate"))
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let from = kind_by_name(&conn, args.value_of("from").unwrap_or_default())?;
+    let to = kind_by_name(&conn, args.value_of("to").unwrap_or_default())?;
+    let top = top(args)?;
+    let limit = limit(args)?;
+    let method = args.value_of("method").and_then(Method::parse).unwrap_or(Method::Csls);
+    let knn = value_t!(args, "knn", usize).map_err(|err| err.to_string())?;
+    let candidates = value_t!(args, "candidates", usize).map_err(|err| err.to_string())?;
+    let w = args.value_of("word").unwrap_or_default();
+
+    let found = match Lookup::new(&conn, &from, &strategies(args)?).find(&conn, w, args.value_of("lang")) {
+        Some(found) => found,
+        None => {
+            eprintln!("missing: {}", w);
+            return Ok(EXIT_MISSING);
+        }
+    };
+
+    report_match(w, &found);
+
+    let target = Space::load(&conn, &to, limit)?;
+
+    if found.vec.len() != target.dim() {
+        return Err(format!("dimensions of '{}' and '{}' differ: {} and {}", from.name, to.name, found.vec.len(), target.dim()));
+    }
+
+    // densities of target words are measured among source words
+    let source = match method {
+        Method::Csls => Space::load(&conn, &from, limit)?,
+        Method::Cosine => Space::new(Vec::new(), Vec::new()),
+    };
+
+    print_neighbors(&target, &translate(&found.vec, &source, &target, method, top, knn, candidates), args);
+
+    Ok(EXIT_OK)
+}
@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use clap::{App, Arg, ArgMatches, SubCommand};
+
+use command::{EXIT_OK, EXIT_MISSING, connect, strategies, strategy_arg, db_arg, format_arg, kind, kind_arg};
+use lookup::Lookup;
+use wmd::{Document, distance, relaxed};
+
+pub fn sub_command<'a>() -> App<'a, 'a> {
+    SubCommand::with_name("wmd")
+        .about("Print Word Mover's Distance between two texts")
+        .arg(db_arg())
+        .arg(kind_arg())
+        .arg(strategy_arg())
+        .arg(Arg::with_name("lang")
+            .long("lang")
+            .takes_value(true)
+            .help("language of words"))
+        .arg(Arg::with_name("relaxed")
+            .long("relaxed")
+            .help("relaxed lower bound, every word moves to the nearest word of the other text"))
+        .arg(Arg::with_name("stop_words")
+            .long("stop-words")
+            .takes_value(true)
+            .help("file of stop words to skip, one per line"))
+        .arg(format_arg("output format: a distance or JSON"))
+        .arg(Arg::with_name("a")
+            .required(true)
+            .help("first text"))
+        .arg(Arg::with_name("b")
+            .required(true)
+            .help("second text"))
+}
+
+fn stop_words(args: &ArgMatches) -> Result<HashSet<String>, String> {
+    let path = match args.value_of("stop_words") {
+        Some(path) => path,
+        None => return Ok(HashSet::new()),
+    };
+
+    let file = File::open(path).map_err(|err| format!("failed to open '{}': {}", path, err))?;
+
+    BufReader::new(file).lines()
+        .map(|line| line.map(|line| line.trim().to_lowercase()))
+        .filter(|line| line.as_ref().map(|line| !line.is_empty()).unwrap_or(true))
+        .collect::<Result<HashSet<_>, _>>()
+        .map_err(|err| format!("failed to read stop words: {}", err))
+}
+
+pub fn run(args: &ArgMatches) -> Result<i32, String> {
+    let conn = connect(args)?;
+    let kind = kind(&conn, args)?;
+    let lookup = Lookup::new(&conn, &kind, &strategies(args)?);
+    let stop_words = stop_words(args)?;
+    let lang = args.value_of("lang");
+    let is_relaxed = args.is_present("relaxed");
+
+    let a = Document::new(&conn, &lookup, args.value_of("a").unwrap_or_default(), lang, &stop_words);
+    let b = Document::new(&conn, &lookup, args.value_of("b").unwrap_or_default(), lang, &stop_words);
+
+    let missing = a.missing.iter().chain(b.missing.iter()).cloned().collect::<Vec<_>>();
+
+    if !missing.is_empty() {
+        eprintln!("missing: {}", missing.join(" "));
+    }
+
+    let result = if is_relaxed {
+        relaxed(&a, &b)
+    } else {
+        distance(&a, &b)
+    };
+
+    let wmd = match result {
+        Some(wmd) => wmd,
+        None => {
+            eprintln!("no known words in {}", if a.is_empty() { "the first text" } else { "the second text" });
+            return Ok(EXIT_MISSING);
+        }
+    };
+
+    if args.value_of("format") == Some("json") {
+        println!("{}", json!({
+            "distance": wmd,
+            "relaxed": is_relaxed,
+            "missing": missing,
+        }));
+    } else {
+        println!("{:.6}", wmd);
+    }
+
+    Ok(EXIT_OK)
+}
@@ -1,12 +1,85 @@
+use std::collections::{HashMap, HashSet};
 use diesel::Connection;
 use diesel::result::Error;
 
 use db::connection::{DsjConnection, establish_connection, run_migrations};
-use db::models::word::{create_word, get_word};
-use db::models::kind::create_kind;
-use db::models::vector::{Vector, add_vectors};
+use db::models::word::{Word, create_word, get_word};
+use db::models::kind::{Kind, create_kind, delete_kind, get_kind};
+use db::models::packed::{PackedVector, add_packed, replace_packed};
+use db::models::property::set_property;
+use db::models::rank::{Rank, add_rank};
+use db::models::subword::{Subword, add_subwords};
+use db::models::vector::{Vector, add_vectors, replace_vectors, word_2_vector};
+use hnsw::{Hnsw, Params};
+use quantize::Quantizer;
+use space::Space;
+use subword::{BUCKET, MAXN, MINN};
+use wordvector::Subwords;
 use wordvector::dataiterator::DataIterator;
 
+pub const DUPLICATES: [&str; 4] = ["first", "last", "average", "fail"];
+
+/// Handling of a word repeated in a file.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Duplicates {
+    First,
+    Last,
+    Average,
+    Fail,
+}
+
+impl Duplicates {
+    pub fn parse(name: &str) -> Option<Duplicates> {
+        match name {
+            "first" => Some(Duplicates::First),
+            "last" => Some(Duplicates::Last),
+            "average" => Some(Duplicates::Average),
+            "fail" => Some(Duplicates::Fail),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Duplicates::First => "first",
+            Duplicates::Last => "last",
+            Duplicates::Average => "average",
+            Duplicates::Fail => "fail",
+        }
+    }
+}
+
+// duplicate words listed in a summary
+const LISTED: usize = 10;
+
+/// Counts of an import, duplicate words with their number of occurrences.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Imported {
+    pub words: u64,
+    pub duplicates: Vec<(String, u64)>,
+    pub policy: Duplicates,
+}
+
+impl Imported {
+    /// "6 words, 2 duplicates (first): да 2, нет 3".
+    pub fn summary(&self) -> String {
+        if self.duplicates.is_empty() {
+            return format!("{} words", self.words);
+        }
+
+        let mut listed = self.duplicates.iter()
+            .take(LISTED)
+            .map(|&(ref word, count)| format!("{} {}", word, count))
+            .collect::<Vec<_>>();
+
+        if self.duplicates.len() > LISTED {
+            listed.push(format!("{} more", self.duplicates.len() - LISTED));
+        }
+
+        format!("{} words, {} duplicates ({}): {}", self.words, self.duplicates.len(), self.policy.name(), listed.join(", "))
+    }
+}
+
 pub struct Converter {
     connection: DsjConnection,
 }
@@ -24,43 +97,257 @@ impl Converter {
         run_migrations(&self.connection)
     }
 
-    pub fn convert(&self, data_iterator: &mut DataIterator) -> Result<(), String> {
+    /// Vectors are packed by a quantizer, f32 points are stored without it.
+    pub fn convert(&self, data_iterator: &mut DataIterator, duplicates: Duplicates, quantizer: Option<&Quantizer>) -> Result<Imported, String> {
+        let mut imported = Imported {
+            words: 0,
+            duplicates: Vec::new(),
+            policy: duplicates,
+        };
+
+        let created = get_kind(&self.connection, data_iterator.kind()).is_none();
+
         if let Some(kind) = create_kind(&self.connection, data_iterator.kind()) {
-            let mut iter = data_iterator.iter();
+            // saved before words as averaged duplicates read packed vectors back
+            if let Some(quantizer) = quantizer {
+                quantizer.save(&self.connection, &kind);
+            }
+
+            // chunks already committed are removed with a kind created by this import
+            if let Err(err) = self.convert_words(&kind, data_iterator, &mut imported, quantizer) {
+                if created {
+                    delete_kind(&self.connection, &kind);
+                }
+
+                return Err(err);
+            }
+
+            if let Some(pipeline) = data_iterator.pipeline() {
+                pipeline.save(&self.connection, &kind);
+            }
+
+            if let Some(subwords) = data_iterator.subwords() {
+                self.convert_subwords(&kind, subwords);
+            }
+        }
+
+        Ok(imported)
+    }
+
+    fn store(&self, word: &Word, kind: &Kind, vec: &[f32], quantizer: Option<&Quantizer>, replace: bool) {
+        match (quantizer, replace) {
+            (Some(quantizer), false) => add_packed(&self.connection, &[PackedVector::new(word, kind, quantizer.encode(vec))]),
+            (Some(quantizer), true) => replace_packed(&self.connection, &PackedVector::new(word, kind, quantizer.encode(vec))),
+            (None, false) => add_vectors(&self.connection, &Vector::from_vec(word, kind, vec)),
+            (None, true) => replace_vectors(&self.connection, word, kind, vec),
+        };
+    }
+
+    fn convert_words(&self, kind: &Kind, data_iterator: &mut DataIterator, imported: &mut Imported, quantizer: Option<&Quantizer>) -> Result<(), String> {
+        let default_lang = data_iterator.lang().to_string();
+        let mut iter = data_iterator.iter();
+
+        let mut count = 0;
+        let mut rank = 0;
+        let mut seen: HashSet<i32> = HashSet::new();
+        let mut positions: HashMap<i32, usize> = HashMap::new();
+        let mut failed: Option<String> = None;
+
+        loop {
+            self.connection.transaction::<_, Error, _>(|| {
+                count = 0;
 
-            let mut count = 0;
+                for record in &mut iter {
+                    let lang = match record.lang {
+                        Some(ref lang) => lang,
+                        None => &default_lang,
+                    };
 
-            loop {
-                self.connection.transaction::<_, Error, _>(|| {
-                    count = 0;
+                    let word = if let Some(word) = get_word(&self.connection, &record.word, lang) {
+                        word
+                    } else if let Some(word) = create_word(&self.connection, &record.word, lang) {
+                        word
+                    } else {
+                        continue;
+                    };
 
-                    for record in &mut iter {
-                        let word = if let Some(word) = get_word(&self.connection, &record.word) {
-                            word
-                        } else if let Some(word) = create_word(&self.connection, &record.word) {
-                            word
-                        } else {
-                            continue;
-                        };
+                    count += 1;
 
-                        add_vectors(&self.connection, &Vector::from_vec(&word, &kind, &record.vec));
+                    if seen.insert(word.id) {
+                        self.store(&word, kind, &record.vec, quantizer, false);
+                        add_rank(&self.connection, &Rank::new(&word, kind, rank, record.index));
 
-                        count += 1;
+                        rank += 1;
+                    } else {
+                        let duplicates = &mut imported.duplicates;
+                        let position = *positions.entry(word.id).or_insert_with(|| {
+                            duplicates.push((record.word.clone(), 1));
+                            duplicates.len() - 1
+                        });
 
-                        if count >= 1000 {
-                            break;
+                        duplicates[position].1 += 1;
+
+                        // a duplicate keeps the rank of the first occurrence
+                        match imported.policy {
+                            Duplicates::First => {}
+                            Duplicates::Last => self.store(&word, kind, &record.vec, quantizer, true),
+                            Duplicates::Average => {
+                                let n = (duplicates[position].1 - 1) as f32;
+
+                                if let Some(mean) = word_2_vector(&self.connection, &word, kind) {
+                                    let mean = mean.iter()
+                                        .zip(record.vec.iter())
+                                        .map(|(mean, point)| mean + (point - mean) / (n + 1.0))
+                                        .collect::<Vec<_>>();
+
+                                    self.store(&word, kind, &mean, quantizer, true);
+                                }
+                            }
+                            Duplicates::Fail => {
+                                failed = Some(record.word.clone());
+                                return Err(Error::RollbackTransaction);
+                            }
                         }
                     }
 
-                    Ok(())
-                });
-
-                if count == 0 {
-                    break;
+                    if count >= 1000 {
+                        break;
+                    }
                 }
+
+                Ok(())
+            });
+
+            if let Some(word) = failed {
+                return Err(format!("stopped at a duplicate word '{}'", word));
+            }
+
+            if count == 0 {
+                break;
             }
         }
 
+        imported.words = rank as u64;
+
         Ok(())
     }
+
+    fn convert_subwords(&self, kind: &Kind, subwords: &mut Subwords) {
+        let ngrams = subwords.ngrams;
+
+        set_property(&self.connection, kind, MINN, &ngrams.minn.to_string());
+        set_property(&self.connection, kind, MAXN, &ngrams.maxn.to_string());
+        set_property(&self.connection, kind, BUCKET, &ngrams.bucket.to_string());
+
+        let mut rows = subwords.rows.by_ref()
+            .map(|(bucket, vec)| Subword::new(kind, bucket, &vec))
+            .peekable();
+
+        while rows.peek().is_some() {
+            let chunk = rows.by_ref().take(1000).collect::<Vec<_>>();
+
+            self.connection.transaction::<_, Error, _>(|| {
+                add_subwords(&self.connection, &chunk);
+
+                Ok(())
+            });
+        }
+    }
+
+    pub fn index(&self, kind_name: &str, params: Params) -> Result<usize, String> {
+        let kind = match get_kind(&self.connection, kind_name) {
+            Some(kind) => kind,
+            None => return Err(format!("kind '{}' doesn't found", kind_name)),
+        };
+
+        let space = Space::load(&self.connection, &kind, None)?;
+
+        if Hnsw::build(&space, params).save(&self.connection, &kind, &space) {
+            Ok(space.len())
+        } else {
+            Err(format!("failed to save an index of '{}'", kind_name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use std::rc::Rc;
+    use rand;
+    use wordvector::{Source, VectorFile};
+    use quantize::Precision;
+    use wordvector::testing::TestIncCounter;
+
+    // a failed import is kept inside, a failure to set it up is returned
+    fn convert(text: &str, duplicates: Duplicates, quantizer: Option<Quantizer>) -> Result<(Result<Imported, String>, Option<Vec<f32>>), String> {
+        let path = env::temp_dir().join(format!("dsj-duplicates-{}.vec", rand::random::<u32>()));
+
+        if let Err(err) = File::create(&path).and_then(|mut file| file.write_all(text.as_bytes())) {
+            return Err(format!("failed to write {:?}: {}", path, err));
+        }
+
+        let source = Source {
+            file: VectorFile::FastText(path.to_string_lossy().to_string()),
+            lang: None,
+        };
+
+        let converter = Converter::new(":memory:")?;
+
+        converter.prepare()?;
+
+        let mut data_iter = match DataIterator::new(Rc::new(TestIncCounter::default()), &source) {
+            Some(data_iter) => data_iter,
+            None => return Err(format!("failed to open {:?}", path)),
+        };
+
+        let imported = converter.convert(&mut data_iter, duplicates, quantizer.as_ref());
+        let vec = match (get_kind(&converter.connection, "fasttext"), get_word(&converter.connection, "да", "")) {
+            (Some(kind), Some(word)) => word_2_vector(&converter.connection, &word, &kind),
+            _ => None,
+        };
+
+        fs::remove_file(&path).ok();
+
+        Ok((imported, vec))
+    }
+
+    #[test]
+    fn test_duplicates() {
+        let text = "4 2\nда 1 0\nнет 0 1\nда 3 2\nда 5 6\n";
+
+        match convert(text, Duplicates::First, None) {
+            Ok((Ok(imported), vec)) => {
+                assert_eq!((imported.words, imported.duplicates.clone()), (2, vec![("да".to_string(), 3)]), "check counts");
+                assert_eq!(imported.summary(), "2 words, 1 duplicates (first): да 3", "check summary");
+                assert_eq!(vec, Some(vec![1.0, 0.0]), "check first");
+            }
+            Ok((Err(err), _)) => assert!(false, "failed to import: {}", err),
+            Err(err) => assert!(false, "failed to set up an import: {}", err),
+        }
+
+        assert_eq!(convert(text, Duplicates::Last, None).map(|(_, vec)| vec), Ok(Some(vec![5.0, 6.0])), "check last");
+        let average = convert(text, Duplicates::Average, None).ok().and_then(|(_, vec)| vec).unwrap_or_default();
+
+        assert!(average.len() == 2 && (average[0] - 3.0).abs() < 1e-5 && (average[1] - 8.0 / 3.0).abs() < 1e-5, "check average {:?}", average);
+        assert_eq!(convert(text, Duplicates::Fail, None).map(|(imported, _)| imported), Ok(Err("stopped at a duplicate word 'да'".to_string())), "check fail");
+        assert_eq!(convert(text, Duplicates::Last, Some(Quantizer::new(Precision::F16))).map(|(_, vec)| vec), Ok(Some(vec![5.0, 6.0])), "check packed");
+    }
+
+    #[test]
+    fn test_duplicates_fail_in_later_chunk() {
+        let mut text = "1500 2\n".to_string();
+
+        for i in 0..1500 {
+            match i {
+                100 | 1300 => text.push_str("да 1 0\n"),
+                _ => text.push_str(&format!("w{} 0 1\n", i)),
+            }
+        }
+
+        assert_eq!(convert(&text, Duplicates::Fail, None), Ok((Err("stopped at a duplicate word 'да'".to_string()), None)), "check kind removed");
+    }
 }
@@ -0,0 +1,451 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_void};
+use std::ptr;
+use std::slice;
+use libsqlite3_sys as ffi;
+use serde_json::{self, Value};
+
+use db::models::pq::Codebook;
+use pq::ProductQuantizer;
+use quantize::{QUANTIZATION, Quantizer};
+use space::{cosine, dot, norm};
+
+/// SQL functions of word vectors, a word is a text or a word id and a kind is a name or an id:
+/// `dsj_dot(a, b, kind)`, `dsj_cosine(a, b, kind)` and `dsj_norm(word, kind)`.
+/// They return NULL for a missing word.
+pub const FUNCTIONS: [(&str, c_int); 3] = [("dsj_dot", 3), ("dsj_cosine", 3), ("dsj_norm", 2)];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Key {
+    Id(i64),
+    Name(String),
+}
+
+// a vector of the last word of an argument, kept by SQLite while an argument is a constant
+struct Cached {
+    word: Key,
+    kind: Key,
+    vec: Option<Vec<f32>>,
+}
+
+unsafe fn errmsg(db: *mut ffi::sqlite3) -> String {
+    CStr::from_ptr(ffi::sqlite3_errmsg(db)).to_string_lossy().to_string()
+}
+
+unsafe fn key(value: *mut ffi::sqlite3_value) -> Option<Key> {
+    match ffi::sqlite3_value_type(value) {
+        ffi::SQLITE_INTEGER => Some(Key::Id(ffi::sqlite3_value_int64(value))),
+        ffi::SQLITE_TEXT => {
+            let text = ffi::sqlite3_value_text(value);
+            let bytes = slice::from_raw_parts(text, ffi::sqlite3_value_bytes(value) as usize);
+
+            Some(Key::Name(String::from_utf8_lossy(bytes).to_string()))
+        }
+        _ => None,
+    }
+}
+
+unsafe fn bind(stmt: *mut ffi::sqlite3_stmt, index: c_int, key: &Key) -> c_int {
+    match *key {
+        Key::Id(id) => ffi::sqlite3_bind_int64(stmt, index, id),
+        Key::Name(ref name) => ffi::sqlite3_bind_text(stmt, index, name.as_ptr() as *const c_char, name.len() as c_int, ffi::SQLITE_TRANSIENT()),
+    }
+}
+
+// runs a query of a word and a kind, passes every row
+unsafe fn query<F>(db: *mut ffi::sqlite3, sql: &str, word: &Key, kind: &Key, mut row: F) -> Result<(), String>
+    where F: FnMut(*mut ffi::sqlite3_stmt)
+{
+    let sql = CString::new(sql).map_err(|err| err.to_string())?;
+    let mut stmt = ptr::null_mut();
+
+    if ffi::sqlite3_prepare_v2(db, sql.as_ptr(), -1, &mut stmt, ptr::null_mut()) != ffi::SQLITE_OK {
+        return Err(errmsg(db));
+    }
+
+    bind(stmt, 1, word);
+    bind(stmt, 2, kind);
+
+    let mut code = ffi::sqlite3_step(stmt);
+
+    while code == ffi::SQLITE_ROW {
+        row(stmt);
+        code = ffi::sqlite3_step(stmt);
+    }
+
+    let result = if code == ffi::SQLITE_DONE {
+        Ok(())
+    } else {
+        Err(errmsg(db))
+    };
+
+    ffi::sqlite3_finalize(stmt);
+
+    result
+}
+
+unsafe fn column_bytes<'a>(stmt: *mut ffi::sqlite3_stmt, column: c_int, text: bool) -> &'a [u8] {
+    let data = if text {
+        ffi::sqlite3_column_text(stmt, column) as *const u8
+    } else {
+        ffi::sqlite3_column_blob(stmt, column) as *const u8
+    };
+
+    if data.is_null() {
+        return &[];
+    }
+
+    slice::from_raw_parts(data, ffi::sqlite3_column_bytes(stmt, column) as usize)
+}
+
+// the first word id of a word of a kind in any language as lookups do, packed vectors are unpacked
+// and product quantized codes are decoded
+unsafe fn lookup(db: *mut ffi::sqlite3, word: &Key, kind: &Key) -> Result<Option<Vec<f32>>, String> {
+    let kind_id = match *kind {
+        Key::Id(_) => "?2",
+        Key::Name(_) => "(SELECT id FROM kinds WHERE name = ?2)",
+    };
+
+    let word_id = |table: &str, first: &str| match *word {
+        Key::Id(_) => "?1".to_string(),
+        Key::Name(_) => format!("(SELECT min(words.id) FROM words JOIN {table} ON {table}.word_id = words.id \
+                                 WHERE words.word = ?1 AND {table}.kind_id = {kind}{first})", table = table, kind = kind_id, first = first),
+    };
+
+    let mut vec = Vec::new();
+
+    query(db, &format!("SELECT point FROM vectors WHERE kind_id = {} AND word_id = {} ORDER BY position",
+                       kind_id, word_id("vectors", " AND vectors.position = 0")), word, kind, |stmt| {
+        vec.push(ffi::sqlite3_column_double(stmt, 0) as f32);
+    })?;
+
+    if vec.is_empty() {
+        let sql = format!("SELECT packed_vectors.vector, properties.value FROM packed_vectors JOIN properties \
+                           ON properties.kind_id = packed_vectors.kind_id AND properties.name = '{}' \
+                           WHERE packed_vectors.kind_id = {} AND packed_vectors.word_id = {}",
+                          QUANTIZATION, kind_id, word_id("packed_vectors", ""));
+
+        query(db, &sql, word, kind, |stmt| {
+            let quantizer = serde_json::from_slice(column_bytes(stmt, 1, true)).ok()
+                .and_then(|value| Quantizer::from_json(&value));
+
+            if let Some(quantizer) = quantizer {
+                vec = quantizer.decode(column_bytes(stmt, 0, false));
+            }
+        })?;
+    }
+
+    if vec.is_empty() {
+        let mut codes = Vec::new();
+
+        query(db, &format!("SELECT codes FROM pq_codes WHERE kind_id = {} AND word_id = {}", kind_id, word_id("pq_codes", "")), word, kind, |stmt| {
+            codes = column_bytes(stmt, 0, false).to_vec();
+        })?;
+
+        if !codes.is_empty() {
+            let mut codebooks = Vec::new();
+
+            query(db, &format!("SELECT subspace, dim, centroids FROM pq_codebooks WHERE kind_id = {} ORDER BY subspace", kind_id), word, kind, |stmt| {
+                codebooks.push(Codebook {
+                    kind_id: 0,
+                    subspace: ffi::sqlite3_column_int(stmt, 0),
+                    dim: ffi::sqlite3_column_int(stmt, 1),
+                    centroids: column_bytes(stmt, 2, false).to_vec(),
+                });
+            })?;
+
+            // a code out of its codebook is a corrupt row, decoding it would panic across FFI
+            let valid = codes.len() == codebooks.len() && codes.iter().zip(codebooks.iter())
+                .all(|(&code, codebook)| (code as usize + 1) * codebook.dim.max(0) as usize * 4 <= codebook.centroids.len());
+
+            if valid {
+                if let Some(quantizer) = ProductQuantizer::from_codebooks(codebooks) {
+                    vec = quantizer.decode(&codes);
+                }
+            }
+        }
+    }
+
+    Ok(if vec.is_empty() { None } else { Some(vec) })
+}
+
+unsafe extern "C" fn drop_cached(data: *mut c_void) {
+    drop(Box::from_raw(data as *mut Cached));
+}
+
+unsafe fn vector(ctx: *mut ffi::sqlite3_context, index: c_int, word: Key, kind: &Key) -> Result<Option<Vec<f32>>, String> {
+    let cached = ffi::sqlite3_get_auxdata(ctx, index) as *const Cached;
+
+    if !cached.is_null() && (*cached).word == word && (*cached).kind == *kind {
+        return Ok((*cached).vec.clone());
+    }
+
+    let vec = lookup(ffi::sqlite3_context_db_handle(ctx), &word, kind)?;
+
+    let cached = Box::new(Cached {
+        word,
+        kind: kind.clone(),
+        vec: vec.clone(),
+    });
+
+    ffi::sqlite3_set_auxdata(ctx, index, Box::into_raw(cached) as *mut c_void, Some(drop_cached));
+
+    Ok(vec)
+}
+
+unsafe fn result(ctx: *mut ffi::sqlite3_context, value: Result<Option<f32>, String>) {
+    match value {
+        Ok(Some(value)) => ffi::sqlite3_result_double(ctx, value as f64),
+        Ok(None) => ffi::sqlite3_result_null(ctx),
+        Err(err) => ffi::sqlite3_result_error(ctx, err.as_ptr() as *const c_char, err.len() as c_int),
+    }
+}
+
+// vectors of all arguments except the last one, a kind, none if an argument is NULL or a word is missing
+unsafe fn arguments(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) -> Result<Option<Vec<Vec<f32>>>, String> {
+    let args = slice::from_raw_parts(argv, argc as usize);
+
+    let kind = match key(args[args.len() - 1]) {
+        Some(kind) => kind,
+        None => return Ok(None),
+    };
+
+    let mut vectors = Vec::new();
+
+    for (index, &arg) in args[..args.len() - 1].iter().enumerate() {
+        let word = match key(arg) {
+            Some(word) => word,
+            None => return Ok(None),
+        };
+
+        match vector(ctx, index as c_int, word, &kind)? {
+            Some(vec) => vectors.push(vec),
+            None => return Ok(None),
+        }
+    }
+
+    Ok(Some(vectors))
+}
+
+unsafe extern "C" fn dsj_dot(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+    result(ctx, arguments(ctx, argc, argv).map(|vectors| vectors.map(|vectors| dot(&vectors[0], &vectors[1]))));
+}
+
+unsafe extern "C" fn dsj_cosine(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+    result(ctx, arguments(ctx, argc, argv).map(|vectors| vectors.map(|vectors| cosine(&vectors[0], &vectors[1]))));
+}
+
+unsafe extern "C" fn dsj_norm(ctx: *mut ffi::sqlite3_context, argc: c_int, argv: *mut *mut ffi::sqlite3_value) {
+    result(ctx, arguments(ctx, argc, argv).map(|vectors| vectors.map(|vectors| norm(&vectors[0]))));
+}
+
+/// Registers dsj functions on a raw SQLite handle.
+pub unsafe fn register(db: *mut ffi::sqlite3) -> Result<(), String> {
+    for &(name, argc) in FUNCTIONS.iter() {
+        let func: unsafe extern "C" fn(*mut ffi::sqlite3_context, c_int, *mut *mut ffi::sqlite3_value) = match name {
+            "dsj_dot" => dsj_dot,
+            "dsj_cosine" => dsj_cosine,
+            _ => dsj_norm,
+        };
+
+        let name = CString::new(name).map_err(|err| err.to_string())?;
+
+        if ffi::sqlite3_create_function(db, name.as_ptr(), argc, ffi::SQLITE_UTF8, ptr::null_mut(), Some(func), None, None) != ffi::SQLITE_OK {
+            return Err(errmsg(db));
+        }
+    }
+
+    Ok(())
+}
+
+/// A raw connection for ad-hoc SQL with dsj functions, diesel doesn't expose its handle to register them.
+pub struct SqlConnection {
+    db: *mut ffi::sqlite3,
+}
+
+impl SqlConnection {
+    pub fn open(path: &str) -> Result<SqlConnection, String> {
+        let path = CString::new(path).map_err(|err| err.to_string())?;
+        let mut db = ptr::null_mut();
+
+        unsafe {
+            let code = ffi::sqlite3_open_v2(path.as_ptr(), &mut db, ffi::SQLITE_OPEN_READWRITE, ptr::null());
+            let connection = SqlConnection {
+                db,
+            };
+
+            if code != ffi::SQLITE_OK {
+                return Err(format!("failed to open a database: {}", errmsg(db)));
+            }
+
+            register(db)?;
+
+            Ok(connection)
+        }
+    }
+
+    /// Runs statements of a query, passes names of columns and values of every row of results.
+    pub fn execute<F>(&self, sql: &str, mut row: F) -> Result<(), String>
+        where F: FnMut(&[String], Vec<Value>)
+    {
+        let sql = CString::new(sql).map_err(|err| err.to_string())?;
+        let mut tail = sql.as_ptr();
+
+        unsafe {
+            while *tail != 0 {
+                let mut stmt = ptr::null_mut();
+
+                if ffi::sqlite3_prepare_v2(self.db, tail, -1, &mut stmt, &mut tail) != ffi::SQLITE_OK {
+                    return Err(errmsg(self.db));
+                }
+
+                // a comment or a whitespace
+                if stmt.is_null() {
+                    continue;
+                }
+
+                let result = self.rows(stmt, &mut row);
+
+                ffi::sqlite3_finalize(stmt);
+                result?;
+            }
+        }
+
+        Ok(())
+    }
+
+    unsafe fn rows<F>(&self, stmt: *mut ffi::sqlite3_stmt, row: &mut F) -> Result<(), String>
+        where F: FnMut(&[String], Vec<Value>)
+    {
+        let count = ffi::sqlite3_column_count(stmt);
+
+        let columns = (0..count)
+            .map(|column| CStr::from_ptr(ffi::sqlite3_column_name(stmt, column)).to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+
+        loop {
+            match ffi::sqlite3_step(stmt) {
+                ffi::SQLITE_ROW => row(&columns, (0..count).map(|column| column_value(stmt, column)).collect()),
+                ffi::SQLITE_DONE => return Ok(()),
+                _ => return Err(errmsg(self.db)),
+            }
+        }
+    }
+}
+
+// blobs as hex strings
+unsafe fn column_value(stmt: *mut ffi::sqlite3_stmt, column: c_int) -> Value {
+    match ffi::sqlite3_column_type(stmt, column) {
+        ffi::SQLITE_INTEGER => Value::from(ffi::sqlite3_column_int64(stmt, column)),
+        ffi::SQLITE_FLOAT => Value::from(ffi::sqlite3_column_double(stmt, column)),
+        ffi::SQLITE_TEXT => {
+            let text = ffi::sqlite3_column_text(stmt, column);
+            let bytes = slice::from_raw_parts(text, ffi::sqlite3_column_bytes(stmt, column) as usize);
+
+            Value::from(String::from_utf8_lossy(bytes).to_string())
+        }
+        ffi::SQLITE_BLOB => {
+            let blob = ffi::sqlite3_column_blob(stmt, column) as *const u8;
+            let bytes = slice::from_raw_parts(blob, ffi::sqlite3_column_bytes(stmt, column) as usize);
+
+            Value::from(bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>())
+        }
+        _ => Value::Null,
+    }
+}
+
+impl Drop for SqlConnection {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::sqlite3_close(self.db);
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use rand;
+    use db::connection::run_migrations;
+    use db::models::kind::create_kind;
+    use db::models::packed::{PackedVector, add_packed};
+    use db::models::pq::{Codes, add_codes};
+    use db::models::testing::test_connection;
+    use db::models::word::get_word;
+    use db::sqlite::establish_connection;
+    use quantize::Precision;
+
+    fn query(conn: &SqlConnection, sql: &str) -> Result<Vec<Vec<Value>>, String> {
+        let mut rows = Vec::new();
+
+        conn.execute(sql, |_, row| rows.push(row))?;
+
+        Ok(rows)
+    }
+
+    #[test]
+    fn test_functions() {
+        let path = env::temp_dir().join(format!("dsj_functions_{}.db", rand::random::<u32>()));
+        let path = path.to_string_lossy().to_string();
+
+        {
+            // copies the test database to a file as a raw connection can't share a memory one
+            let source = test_connection();
+            let target = match establish_connection(&path) {
+                Ok(target) => target,
+                Err(err) => {
+                    assert!(false, "failed to create a database with {:?}", err);
+                    return;
+                }
+            };
+
+            let _ = run_migrations(&target);
+            let _ = ::diesel::connection::SimpleConnection::batch_execute(&source, &format!("ATTACH DATABASE '{}' AS target; \
+                INSERT INTO target.kinds SELECT * FROM kinds; INSERT INTO target.words SELECT * FROM words; \
+                INSERT INTO target.vectors SELECT * FROM vectors; DETACH DATABASE target;", path));
+
+            let quantizer = Quantizer::new(Precision::F16);
+
+            if let (Some(kind), Some(word)) = (create_kind(&target, "fasttext_f16"), get_word(&target, "король", "ru")) {
+                quantizer.save(&target, &kind);
+                add_packed(&target, &[PackedVector::new(&word, &kind, quantizer.encode(&[0.5, 0.25, 0.0, 0.0]))]);
+            }
+
+            let vectors = [&[0.5f32, 0.25, 1.0, 0.0][..], &[-0.5f32, 0.0, 0.0, 1.0][..]];
+
+            if let (Some(kind), Some(word), Ok(pq)) = (create_kind(&target, "fasttext_pq"), get_word(&target, "король", "ru"), ProductQuantizer::train(&vectors, 2, 2, 5)) {
+                pq.save(&target, &kind);
+                add_codes(&target, &[Codes::new(&word, &kind, pq.encode(vectors[0]))]);
+            }
+        }
+
+        let conn = match SqlConnection::open(&path) {
+            Ok(conn) => conn,
+            Err(err) => {
+                assert!(false, "failed to open a database with {:?}", err);
+                return;
+            }
+        };
+
+        let round = |rows: Result<Vec<Vec<Value>>, String>| rows.map(|rows| rows.into_iter()
+            .map(|row| row.into_iter().map(|value| value.as_f64().map(|value| json!((value * 1e4).round() / 1e4)).unwrap_or(value)).collect())
+            .collect::<Vec<Vec<Value>>>());
+
+        assert_eq!(round(query(&conn, "SELECT dsj_cosine('король', 'король', 'fasttext'), dsj_norm('яблоко', 1), dsj_dot('король', 'яблоко', 'fasttext')")),
+                   Ok(vec![vec![json!(1.0), json!(0.9055), json!(0.01)]]), "check functions");
+        assert_eq!(query(&conn, "SELECT dsj_cosine('король', 'груша', 'fasttext'), dsj_norm(NULL, 'fasttext'), dsj_norm('король', 'glove')"),
+                   Ok(vec![vec![Value::Null, Value::Null, Value::Null]]), "check missing words");
+        assert_eq!(query(&conn, "SELECT w.word FROM words w WHERE w.word <> 'король' ORDER BY dsj_cosine(w.id, 'король', 'fasttext') DESC LIMIT 1; -- the nearest"),
+                   Ok(vec![vec![json!("мужчина")]]), "check ranking");
+        assert_eq!(query(&conn, "SELECT dsj_norm('король', 'fasttext_f16'), dsj_dot('король', 'король', 'fasttext_f16'), dsj_norm('яблоко', 'fasttext_f16')"),
+                   Ok(vec![vec![json!((0.3125f32).sqrt()), json!(0.3125), Value::Null]]), "check packed vectors");
+        assert_eq!(round(query(&conn, "SELECT dsj_dot('король', 'король', 'fasttext_pq'), dsj_norm('яблоко', 'fasttext_pq')")),
+                   Ok(vec![vec![json!(1.3125), Value::Null]]), "check product quantized codes");
+        assert!(query(&conn, "SELECT dsj_norm('король')").is_err(), "check arguments");
+
+        drop(conn);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod connection;
+pub mod functions;
 pub mod models;
 pub mod schema;
 #[cfg(feature = "pg")]
@@ -0,0 +1,61 @@
+use diesel::{self};
+use diesel::prelude::*;
+
+use db::connection::DsjConnection;
+use db::schema::indexes;
+use db::models::kind::Kind;
+
+#[derive(Debug, Insertable, Queryable, PartialEq, Clone)]
+#[table_name = "indexes"]
+pub struct Index {
+    pub kind_id: i32,
+    pub m: i32,
+    pub ef_construction: i32,
+    pub checksum: i64,
+    pub data: Vec<u8>,
+}
+
+pub fn save_index(conn: &DsjConnection, index: &Index) -> bool {
+    use db::schema::indexes::dsl::{indexes, kind_id};
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::delete(indexes.filter(kind_id.eq(index.kind_id)))
+            .execute(conn)?;
+
+        diesel::insert_into(indexes)
+            .values(index)
+            .execute(conn)
+    });
+
+    match result {
+        Ok(count) => count > 0,
+        Err(err) => {
+            println!("failed to save an index for kind {} {:?}", index.kind_id, err);
+            false
+        }
+    }
+}
+
+pub fn get_index(conn: &DsjConnection, kind: &Kind) -> Option<Index> {
+    use db::schema::indexes::dsl::{indexes, kind_id};
+
+    match indexes
+        .filter(kind_id.eq(kind.id))
+        .load::<Index>(conn) {
+        Ok(res) => Some(res.first()?.clone()),
+        Err(_) => None,
+    }
+}
+
+/// M and ef construction of an index without reading its graph.
+pub fn index_params(conn: &DsjConnection, kind: &Kind) -> Option<(i32, i32)> {
+    use db::schema::indexes::dsl::{indexes, kind_id, m, ef_construction};
+
+    match indexes
+        .filter(kind_id.eq(kind.id))
+        .select((m, ef_construction))
+        .load::<(i32, i32)>(conn) {
+        Ok(res) => Some(*res.first()?),
+        Err(_) => None,
+    }
+}
@@ -60,3 +60,28 @@ pub fn kinds_list(conn: &DsjConnection) -> Option<Vec<Kind>> {
         Err(_) => None,
     }
 }
+
+/// Removes a kind with every row stored for it, words stay shared by other kinds.
+pub fn delete_kind(conn: &DsjConnection, kind: &Kind) -> bool {
+    use db::schema;
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::delete(schema::vectors::table.filter(schema::vectors::kind_id.eq(kind.id))).execute(conn)?;
+        diesel::delete(schema::packed_vectors::table.filter(schema::packed_vectors::kind_id.eq(kind.id))).execute(conn)?;
+        diesel::delete(schema::pq_codes::table.filter(schema::pq_codes::kind_id.eq(kind.id))).execute(conn)?;
+        diesel::delete(schema::pq_codebooks::table.filter(schema::pq_codebooks::kind_id.eq(kind.id))).execute(conn)?;
+        diesel::delete(schema::ranks::table.filter(schema::ranks::kind_id.eq(kind.id))).execute(conn)?;
+        diesel::delete(schema::properties::table.filter(schema::properties::kind_id.eq(kind.id))).execute(conn)?;
+        diesel::delete(schema::subwords::table.filter(schema::subwords::kind_id.eq(kind.id))).execute(conn)?;
+        diesel::delete(schema::indexes::table.filter(schema::indexes::kind_id.eq(kind.id))).execute(conn)?;
+        diesel::delete(schema::kinds::table.filter(schema::kinds::id.eq(kind.id))).execute(conn)
+    });
+
+    match result {
+        Ok(count) => count > 0,
+        Err(err) => {
+            println!("failed to delete kind '{}' {:?}", kind.name, err);
+            false
+        }
+    }
+}
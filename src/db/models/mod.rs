@@ -1,5 +1,11 @@
 pub mod vector;
+pub mod index;
 pub mod kind;
+pub mod packed;
+pub mod pq;
+pub mod property;
+pub mod rank;
+pub mod subword;
 pub mod word;
 #[cfg(test)]
-mod testing;
\ No newline at end of file
+pub mod testing;
\ No newline at end of file
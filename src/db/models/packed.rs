@@ -0,0 +1,101 @@
+use diesel::{self};
+use diesel::prelude::*;
+
+use db::connection::DsjConnection;
+use db::schema::packed_vectors;
+use db::models::kind::Kind;
+use db::models::word::Word;
+
+/// A vector of a word stored with a reduced precision, see `quantize::Quantizer`.
+#[derive(Debug, Insertable, Queryable, PartialEq, Clone)]
+#[table_name = "packed_vectors"]
+pub struct PackedVector {
+    pub word_id: i32,
+    pub kind_id: i32,
+    pub vector: Vec<u8>,
+}
+
+impl PackedVector {
+    pub fn new(word: &Word, kind: &Kind, vector: Vec<u8>) -> PackedVector {
+        PackedVector {
+            word_id: word.id,
+            kind_id: kind.id,
+            vector,
+        }
+    }
+}
+
+pub fn add_packed(conn: &DsjConnection, new_vectors: &[PackedVector]) -> usize {
+    use db::schema::packed_vectors::dsl::packed_vectors;
+
+    match diesel::insert_into(packed_vectors)
+        .values(new_vectors)
+        .execute(conn) {
+        Ok(count) => count,
+        Err(_) => 0,
+    }
+}
+
+pub fn replace_packed(conn: &DsjConnection, new_vector: &PackedVector) -> usize {
+    use db::schema::packed_vectors::dsl::packed_vectors;
+
+    match diesel::replace_into(packed_vectors)
+        .values(new_vector)
+        .execute(conn) {
+        Ok(count) => count,
+        Err(err) => {
+            println!("failed to replace a packed vector of word {} {:?}", new_vector.word_id, err);
+            0
+        }
+    }
+}
+
+pub fn get_packed(conn: &DsjConnection, word: &Word, kind: &Kind) -> Option<Vec<u8>> {
+    use db::schema::packed_vectors::dsl::{packed_vectors, word_id, kind_id};
+
+    match packed_vectors
+        .filter(word_id.eq(word.id))
+        .filter(kind_id.eq(kind.id))
+        .load::<PackedVector>(conn) {
+        Ok(res) => Some(res.into_iter().next()?.vector),
+        Err(_) => None,
+    }
+}
+
+/// All packed vectors of a kind by a word id.
+pub fn kind_packed(conn: &DsjConnection, kind: &Kind) -> Option<Vec<(i32, Vec<u8>)>> {
+    use db::schema::packed_vectors::dsl::{packed_vectors, kind_id, word_id};
+
+    match packed_vectors
+        .filter(kind_id.eq(kind.id))
+        .order(word_id)
+        .load::<PackedVector>(conn) {
+        Ok(res) => Some(res.into_iter().map(|packed| (packed.word_id, packed.vector)).collect()),
+        Err(err) => {
+            println!("failed to get packed vectors of '{}' {:?}", kind.name, err);
+            None
+        }
+    }
+}
+
+/// A count of packed vectors of a kind and one of them.
+pub fn packed_sample(conn: &DsjConnection, kind: &Kind) -> Option<(i64, Option<Vec<u8>>)> {
+    use db::schema::packed_vectors::dsl::{packed_vectors, kind_id};
+
+    let count = packed_vectors
+        .filter(kind_id.eq(kind.id))
+        .count()
+        .get_result::<i64>(conn)
+        .ok()?;
+
+    let first = packed_vectors
+        .filter(kind_id.eq(kind.id))
+        .limit(1)
+        .load::<PackedVector>(conn)
+        .ok()?
+        .into_iter()
+        .next()
+        .map(|packed| packed.vector);
+
+    Some((count, first))
+}
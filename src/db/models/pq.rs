@@ -0,0 +1,110 @@
+use diesel::{self};
+use diesel::prelude::*;
+
+use db::connection::DsjConnection;
+use db::schema::{pq_codebooks, pq_codes};
+use db::models::kind::Kind;
+use db::models::word::Word;
+
+/// Centroids of a subspace of a product quantized kind as little endian f32 rows of `dim` points.
+#[derive(Debug, Insertable, Queryable, PartialEq, Clone)]
+#[table_name = "pq_codebooks"]
+pub struct Codebook {
+    pub kind_id: i32,
+    pub subspace: i32,
+    pub dim: i32,
+    pub centroids: Vec<u8>,
+}
+
+/// Centroid numbers of a word, one byte per subspace.
+#[derive(Debug, Insertable, Queryable, PartialEq, Clone)]
+#[table_name = "pq_codes"]
+pub struct Codes {
+    pub word_id: i32,
+    pub kind_id: i32,
+    pub codes: Vec<u8>,
+}
+
+impl Codes {
+    pub fn new(word: &Word, kind: &Kind, codes: Vec<u8>) -> Codes {
+        Codes {
+            word_id: word.id,
+            kind_id: kind.id,
+            codes,
+        }
+    }
+}
+
+pub fn add_codebooks(conn: &DsjConnection, new_codebooks: &[Codebook]) -> usize {
+    use db::schema::pq_codebooks::dsl::pq_codebooks;
+
+    match diesel::insert_into(pq_codebooks)
+        .values(new_codebooks)
+        .execute(conn) {
+        Ok(count) => count,
+        Err(err) => {
+            println!("failed to add codebooks {:?}", err);
+            0
+        }
+    }
+}
+
+/// Codebooks of a kind in the order of subspaces, empty for a kind without them.
+pub fn kind_codebooks(conn: &DsjConnection, kind: &Kind) -> Option<Vec<Codebook>> {
+    use db::schema::pq_codebooks::dsl::{pq_codebooks, kind_id, subspace};
+
+    pq_codebooks
+        .filter(kind_id.eq(kind.id))
+        .order(subspace)
+        .load::<Codebook>(conn)
+        .ok()
+}
+
+pub fn add_codes(conn: &DsjConnection, new_codes: &[Codes]) -> usize {
+    use db::schema::pq_codes::dsl::pq_codes;
+
+    match diesel::insert_into(pq_codes)
+        .values(new_codes)
+        .execute(conn) {
+        Ok(count) => count,
+        Err(_) => 0,
+    }
+}
+
+pub fn get_codes(conn: &DsjConnection, word: &Word, kind: &Kind) -> Option<Vec<u8>> {
+    use db::schema::pq_codes::dsl::{pq_codes, word_id, kind_id};
+
+    match pq_codes
+        .filter(word_id.eq(word.id))
+        .filter(kind_id.eq(kind.id))
+        .load::<Codes>(conn) {
+        Ok(res) => Some(res.into_iter().next()?.codes),
+        Err(_) => None,
+    }
+}
+
+/// All codes of a kind by a word id.
+pub fn kind_codes(conn: &DsjConnection, kind: &Kind) -> Option<Vec<(i32, Vec<u8>)>> {
+    use db::schema::pq_codes::dsl::{pq_codes, kind_id, word_id};
+
+    match pq_codes
+        .filter(kind_id.eq(kind.id))
+        .order(word_id)
+        .load::<Codes>(conn) {
+        Ok(res) => Some(res.into_iter().map(|codes| (codes.word_id, codes.codes)).collect()),
+        Err(err) => {
+            println!("failed to get codes of '{}' {:?}", kind.name, err);
+            None
+        }
+    }
+}
+
+pub fn codes_count(conn: &DsjConnection, kind: &Kind) -> Option<i64> {
+    use db::schema::pq_codes::dsl::{pq_codes, kind_id};
+
+    pq_codes
+        .filter(kind_id.eq(kind.id))
+        .count()
+        .get_result::<i64>(conn)
+        .ok()
+}
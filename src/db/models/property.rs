@@ -0,0 +1,51 @@
+use diesel::{self};
+use diesel::prelude::*;
+
+use db::connection::DsjConnection;
+use db::schema::properties;
+use db::models::kind::Kind;
+
+#[derive(Debug, Insertable, Queryable, PartialEq, Clone)]
+#[table_name = "properties"]
+pub struct Property {
+    pub kind_id: i32,
+    pub name: String,
+    pub value: String,
+}
+
+pub fn set_property(conn: &DsjConnection, kind: &Kind, n: &str, v: &str) -> bool {
+    use db::schema::properties::dsl::{properties, kind_id, name};
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::delete(properties.filter(kind_id.eq(kind.id)).filter(name.eq(n)))
+            .execute(conn)?;
+
+        diesel::insert_into(properties)
+            .values(&Property {
+                kind_id: kind.id,
+                name: n.to_string(),
+                value: v.to_string(),
+            })
+            .execute(conn)
+    });
+
+    match result {
+        Ok(count) => count > 0,
+        Err(err) => {
+            println!("failed to set property '{}' of kind '{}' {:?}", n, kind.name, err);
+            false
+        }
+    }
+}
+
+pub fn get_property(conn: &DsjConnection, kind: &Kind, n: &str) -> Option<String> {
+    use db::schema::properties::dsl::{properties, kind_id, name};
+
+    match properties
+        .filter(kind_id.eq(kind.id))
+        .filter(name.eq(n))
+        .load::<Property>(conn) {
+        Ok(res) => Some(res.first()?.value.clone()),
+        Err(_) => None,
+    }
+}
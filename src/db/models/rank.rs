@@ -0,0 +1,76 @@
+use diesel::{self};
+use diesel::prelude::*;
+
+use db::connection::DsjConnection;
+use db::schema::ranks;
+use db::models::kind::Kind;
+use db::models::word::Word;
+
+#[derive(Debug, Insertable, Queryable, PartialEq, Clone)]
+#[table_name = "ranks"]
+pub struct Rank {
+    pub word_id: i32,
+    pub kind_id: i32,
+    pub rank: i32,
+    pub source_index: Option<i64>,
+}
+
+impl Rank {
+    pub fn new(word: &Word, kind: &Kind, rank: i32, source_index: Option<i64>) -> Rank {
+        Rank {
+            word_id: word.id,
+            kind_id: kind.id,
+            rank,
+            source_index,
+        }
+    }
+}
+
+pub fn add_rank(conn: &DsjConnection, new_rank: &Rank) -> usize {
+    use db::schema::ranks::dsl::ranks;
+
+    match diesel::insert_into(ranks)
+        .values(new_rank)
+        .execute(conn) {
+        Ok(count) => count,
+        Err(_) => 0,
+    }
+}
+
+pub fn get_rank(conn: &DsjConnection, word: &Word, kind: &Kind) -> Option<Rank> {
+    use db::schema::ranks::dsl::{ranks, word_id, kind_id};
+
+    match ranks
+        .filter(word_id.eq(word.id))
+        .filter(kind_id.eq(kind.id))
+        .load::<Rank>(conn) {
+        Ok(res) => Some(res.first()?.clone()),
+        Err(_) => None,
+    }
+}
+
+/// Words of a kind in the order of a source file, the most frequent first
+/// for frequency sorted files. `limit` keeps only the top N words.
+pub fn ranked_words(conn: &DsjConnection, kind: &Kind, limit: Option<i64>) -> Option<Vec<(Word, Rank)>> {
+    use db::schema::{ranks, words};
+
+    let query = ranks::table
+        .inner_join(words::table)
+        .filter(ranks::kind_id.eq(kind.id))
+        .order(ranks::rank)
+        .select((words::all_columns, ranks::all_columns))
+        .into_boxed();
+
+    let query = match limit {
+        Some(limit) => query.limit(limit),
+        None => query,
+    };
+
+    match query.load::<(Word, Rank)>(conn) {
+        Ok(res) => Some(res),
+        Err(err) => {
+            println!("failed to get ranked words of '{}' {:?}", kind.name, err);
+            None
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use diesel::{self};
+use diesel::prelude::*;
+use diesel::types::Integer;
+use byteorder::{ByteOrder, LittleEndian};
+
+use db::connection::DsjConnection;
+use db::schema::subwords;
+use db::models::kind::Kind;
+
+/// A row of fastText n-gram buckets, points are packed as little endian f32.
+#[derive(Debug, Insertable, Queryable, PartialEq, Clone)]
+#[table_name = "subwords"]
+pub struct Subword {
+    pub kind_id: i32,
+    pub bucket: i32,
+    pub vector: Vec<u8>,
+}
+
+impl Subword {
+    pub fn new(kind: &Kind, bucket: i32, points: &[f32]) -> Subword {
+        let mut vector = vec![0u8; points.len() * 4];
+
+        LittleEndian::write_f32_into(points, &mut vector);
+
+        Subword {
+            kind_id: kind.id,
+            bucket,
+            vector,
+        }
+    }
+
+    pub fn points(&self) -> Vec<f32> {
+        let mut points = vec![0f32; self.vector.len() / 4];
+
+        LittleEndian::read_f32_into(&self.vector[..points.len() * 4], &mut points);
+
+        points
+    }
+}
+
+pub fn add_subwords(conn: &DsjConnection, new_subwords: &[Subword]) -> usize {
+    use db::schema::subwords::dsl::subwords;
+
+    match diesel::insert_into(subwords)
+        .values(new_subwords)
+        .execute(conn) {
+        Ok(count) => count,
+        Err(_) => 0,
+    }
+}
+
+pub fn get_subwords(conn: &DsjConnection, kind: &Kind, buckets: &[i32]) -> Option<Vec<Subword>> {
+    use db::schema::subwords::dsl::{subwords, kind_id, bucket};
+
+    match subwords
+        .filter(kind_id.eq(kind.id))
+        .filter(bucket.eq_any(buckets))
+        .load::<Subword>(conn) {
+        Ok(res) => Some(res),
+        Err(err) => {
+            println!("failed to get subwords of '{}' {:?}", kind.name, err);
+            None
+        }
+    }
+}
+
+/// Copies n-gram rows of a kind to another one, returns a number of copied rows.
+pub fn copy_subwords(conn: &DsjConnection, from: &Kind, to: &Kind) -> usize {
+    match diesel::sql_query("INSERT INTO subwords (kind_id, bucket, vector) SELECT ?, bucket, vector FROM subwords WHERE kind_id = ?")
+        .bind::<Integer, _>(to.id)
+        .bind::<Integer, _>(from.id)
+        .execute(conn) {
+        Ok(count) => count,
+        Err(err) => {
+            println!("failed to copy subwords of '{}' to '{}' {:?}", from.name, to.name, err);
+            0
+        }
+    }
+}
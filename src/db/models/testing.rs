@@ -11,6 +11,36 @@ fn prepare_connection() -> Result<SqliteConnection, String> {
     Ok(connection)
 }
 
+/// A database with a "fasttext" kind of a few words in Russian.
+pub fn test_connection() -> SqliteConnection {
+    use super::kind::create_kind;
+    use super::word::create_word;
+    use super::vector::{Vector, add_vectors};
+
+    let connection = match prepare_connection() {
+        Ok(connection) => connection,
+        Err(err) => panic!("failed to prepare connection with {:?}", err),
+    };
+
+    let kind = create_kind(&connection, "fasttext").unwrap_or_default();
+
+    let data = vec![
+        ("король", vec![0.9f32, 0.8, 0.1, 0.0]),
+        ("королева", vec![0.9f32, 0.1, 0.8, 0.0]),
+        ("мужчина", vec![0.1f32, 0.9, 0.0, 0.1]),
+        ("женщина", vec![0.1f32, 0.1, 0.9, 0.1]),
+        ("яблоко", vec![0.0f32, 0.0, 0.1, 0.9]),
+    ];
+
+    for &(w, ref vec) in data.iter() {
+        if let Some(word) = create_word(&connection, w, "ru") {
+            add_vectors(&connection, &Vector::from_vec(&word, &kind, vec));
+        }
+    }
+
+    connection
+}
+
 fn random_f(probability_zero: f32, min: f32, max: f32) -> Box<Fn() -> f32> {
     Box::new(move || {
         let mut rng = rand::thread_rng();
@@ -85,14 +115,14 @@ fn test_words() {
     let words = vec!["намело", "сугробы", "крыльца"];
 
     for &word in words.iter() {
-        match create_word(&connection, word) {
+        match create_word(&connection, word, "") {
             Some(w) => assert_eq!(w.word, word, "check creation"),
             None => assert!(false, "failed to create word - '{}'", word),
         }
     }
 
     assert!(words.iter().all(|&word| {
-        match get_word(&connection, word) {
+        match get_word(&connection, word, "") {
             Some(rec) => rec.id > 0,
             None => false,
         }
@@ -110,12 +140,59 @@ fn test_words() {
     }
 }
 
+#[test]
+fn test_words_lang() {
+    use super::word::{complete_words, create_word, get_word, find_words};
+
+    let connection = match prepare_connection() {
+        Ok(connection) => connection,
+        Err(err) => {
+            assert!(false, "failed to prepare connection with {:?}", err);
+            return;
+        }
+    };
+
+    let words = vec![("мир", "ru"), ("мир", "uk"), ("мир", ""), ("peace", "en"), ("мирный", "ru"), ("ми", "ru")];
+
+    for &(word, lang) in words.iter() {
+        match create_word(&connection, word, lang) {
+            Some(w) => assert_eq!((w.word.as_str(), w.lang.as_str()), (word, lang), "check creation"),
+            None => assert!(false, "failed to create word - '{}' ({})", word, lang),
+        }
+    }
+
+    match (get_word(&connection, "мир", "ru"), get_word(&connection, "мир", "uk")) {
+        (Some(ru), Some(uk)) => assert!(ru.id != uk.id, "check words are different"),
+        _ => assert!(false, "failed to get language words"),
+    }
+
+    assert_eq!(get_word(&connection, "peace", "ru"), None, "check other language");
+
+    match find_words(&connection, "мир", None) {
+        Some(list) => assert_eq!(
+            list.iter().map(|rec| rec.lang.as_str()).collect::<Vec<_>>(),
+            vec!["ru", "uk", ""],
+            "check all languages"
+        ),
+        None => assert!(false, "failed to find words"),
+    }
+
+    match find_words(&connection, "мир", Some("uk")) {
+        Some(list) => assert_eq!(list.len(), 1, "check filtered by language"),
+        None => assert!(false, "failed to find words by language"),
+    }
+
+    assert_eq!(complete_words(&connection, "мир", 10), Some(vec!["мир".to_string(), "мирный".to_string()]), "check completion");
+    assert_eq!(complete_words(&connection, "мир", 1), Some(vec!["мир".to_string()]), "check completion limit");
+    assert_eq!(complete_words(&connection, "pe", 10), Some(vec!["peace".to_string()]), "check latin completion");
+}
+
 #[test]
 fn test_vectors() {
     use std::collections::BTreeMap;
     use super::word::{Word, create_word};
     use super::kind::{Kind, create_kind};
-    use super::vector::{Vector, add_vectors, word_2_vector};
+    use super::vector::{Vector, add_vectors, kind_size, replace_vectors, word_2_vector};
 
     let connection = match prepare_connection() {
         Ok(connection) => connection,
@@ -133,7 +210,7 @@ fn test_vectors() {
     }).collect::<Vec<Kind>>();
 
     let stored_words = words.iter().filter_map(|&word| {
-        create_word(&connection, word)
+        create_word(&connection, word, "")
     }).collect::<Vec<Word>>();
 
     let mut data: BTreeMap<(String, String), Vec<f32>> = BTreeMap::new();
@@ -171,5 +248,165 @@ fn test_vectors() {
                 assert!(false, "data doesn't found in a test cache");
             }
         }
+
+        assert_eq!(kind_size(&connection, &kind), Some((words.len() as i64, vector_dim as i64)), "check kind size");
+    }
+
+    let (kind, word) = (&stored_kinds[0], &stored_words[0]);
+    let vec = random_vector(vector_dim);
+
+    assert_eq!(add_vectors(&connection, &Vector::from_vec(word, kind, &vec)), 0, "check duplicate vector");
+    assert_eq!(replace_vectors(&connection, word, kind, &vec), vector_dim, "check replace");
+    assert_eq!(word_2_vector(&connection, word, kind), Some(vec), "check replaced vec");
+}
+
+#[test]
+fn test_ranks() {
+    use super::word::{Word, create_word};
+    use super::kind::create_kind;
+    use super::rank::{Rank, add_rank, get_rank, ranked_words};
+
+    let connection = match prepare_connection() {
+        Ok(connection) => connection,
+        Err(err) => {
+            assert!(false, "failed to prepare connection with {:?}", err);
+            return;
+        }
+    };
+
+    let kind = match create_kind(&connection, "gensim") {
+        Some(kind) => kind,
+        None => {
+            assert!(false, "failed to create kind");
+            return;
+        }
+    };
+
+    let words = vec!["намело", "сугробы", "у", "нашего", "крыльца"];
+
+    let stored_words = words.iter().filter_map(|&word| {
+        create_word(&connection, word, "")
+    }).collect::<Vec<Word>>();
+
+    // store in reverse file order
+    for (rank, word) in stored_words.iter().enumerate().rev() {
+        let source_index = if rank % 2 == 0 { Some(25454 + rank as i64) } else { None };
+
+        assert_eq!(add_rank(&connection, &Rank::new(word, &kind, rank as i32, source_index)), 1, "check rank insert");
+    }
+
+    match get_rank(&connection, &stored_words[2], &kind) {
+        Some(rank) => assert_eq!((rank.rank, rank.source_index), (2, Some(25456)), "check rank"),
+        None => assert!(false, "failed to get rank"),
+    }
+
+    match ranked_words(&connection, &kind, None) {
+        Some(list) => assert_eq!(
+            list.iter().map(|&(ref word, _)| word.word.as_str()).collect::<Vec<_>>(),
+            words,
+            "check file order"
+        ),
+        None => assert!(false, "failed to get ranked words"),
+    }
+
+    match ranked_words(&connection, &kind, Some(2)) {
+        Some(list) => assert_eq!(
+            list.iter().map(|&(ref word, ref rank)| (word.word.as_str(), rank.rank)).collect::<Vec<_>>(),
+            vec![("намело", 0), ("сугробы", 1)],
+            "check top words"
+        ),
+        None => assert!(false, "failed to get top ranked words"),
+    }
+}
+
+#[test]
+fn test_indexes() {
+    use super::kind::create_kind;
+    use super::index::{Index, get_index, index_params, save_index};
+
+    let connection = match prepare_connection() {
+        Ok(connection) => connection,
+        Err(err) => {
+            assert!(false, "failed to prepare connection with {:?}", err);
+            return;
+        }
+    };
+
+    let kind = match create_kind(&connection, "fasttext") {
+        Some(kind) => kind,
+        None => {
+            assert!(false, "failed to create kind");
+            return;
+        }
+    };
+
+    assert_eq!(get_index(&connection, &kind), None, "check missing index");
+    assert_eq!(index_params(&connection, &kind), None, "check missing index params");
+
+    let mut index = Index {
+        kind_id: kind.id,
+        m: 16,
+        ef_construction: 200,
+        checksum: -42,
+        data: vec![1, 2, 3],
+    };
+
+    assert!(save_index(&connection, &index), "check index insert");
+
+    index.data = vec![4, 5];
+
+    assert!(save_index(&connection, &index), "check index replace");
+    assert_eq!(get_index(&connection, &kind), Some(index), "check stored index");
+    assert_eq!(index_params(&connection, &kind), Some((16, 200)), "check index params");
+}
+
+#[test]
+fn test_subwords() {
+    use super::kind::create_kind;
+    use super::property::{get_property, set_property};
+    use super::subword::{Subword, add_subwords, copy_subwords, get_subwords};
+
+    let connection = match prepare_connection() {
+        Ok(connection) => connection,
+        Err(err) => {
+            assert!(false, "failed to prepare connection with {:?}", err);
+            return;
+        }
+    };
+
+    let kind = match create_kind(&connection, "fasttext") {
+        Some(kind) => kind,
+        None => {
+            assert!(false, "failed to create kind");
+            return;
+        }
+    };
+
+    assert_eq!(get_property(&connection, &kind, "minn"), None, "check missing property");
+    assert!(set_property(&connection, &kind, "minn", "2"), "check property insert");
+    assert!(set_property(&connection, &kind, "minn", "3"), "check property replace");
+    assert_eq!(get_property(&connection, &kind, "minn"), Some("3".to_string()), "check stored property");
+
+    let subwords = vec![
+        Subword::new(&kind, 7, &[0.5, -1.0]),
+        Subword::new(&kind, 11, &[0.25, 2.0]),
+        Subword::new(&kind, 13, &[0.0, 0.0]),
+    ];
+
+    assert_eq!(add_subwords(&connection, &subwords), 3, "check subwords insert");
+
+    match get_subwords(&connection, &kind, &[11, 7, 42]) {
+        Some(mut found) => {
+            found.sort_by_key(|subword| subword.bucket);
+
+            assert_eq!(found.iter().map(|subword| (subword.bucket, subword.points())).collect::<Vec<_>>(),
+                       vec![(7, vec![0.5f32, -1.0]), (11, vec![0.25f32, 2.0])], "check stored subwords");
+        }
+        None => assert!(false, "failed to get subwords"),
     }
-}
\ No newline at end of file
+
+    let copy = create_kind(&connection, "fasttext_copy").unwrap_or_default();
+
+    assert_eq!(copy_subwords(&connection, &kind, &copy), 3, "check subwords copy");
+    assert_eq!(get_subwords(&connection, &copy, &[13]).map(|found| found.len()), Some(1), "check copied subwords");
+}
@@ -4,7 +4,11 @@ use diesel::prelude::*;
 use db::connection::DsjConnection;
 use db::schema::vectors;
 use db::models::kind::Kind;
-use db::models::word::Word;
+use db::models::packed::{get_packed, kind_packed, packed_sample};
+use db::models::pq::{codes_count, get_codes, kind_codes};
+use db::models::word::{Word, find_words};
+use pq::ProductQuantizer;
+use quantize::Quantizer;
 
 #[derive(Debug, Insertable, Queryable, PartialEq)]
 #[table_name = "vectors"]
@@ -48,15 +52,144 @@ pub fn add_vectors(conn: &DsjConnection, new_vectors: &[Vector]) -> usize {
     }
 }
 
+/// Replaces a vector of a word in a kind.
+pub fn replace_vectors(conn: &DsjConnection, word: &Word, kind: &Kind, points: &[f32]) -> usize {
+    use db::schema::vectors::dsl::{vectors, word_id, kind_id};
+
+    let result = conn.transaction::<_, diesel::result::Error, _>(|| {
+        diesel::delete(vectors.filter(word_id.eq(word.id)).filter(kind_id.eq(kind.id)))
+            .execute(conn)?;
+
+        diesel::insert_into(vectors)
+            .values(&Vector::from_vec(word, kind, points))
+            .execute(conn)
+    });
+
+    match result {
+        Ok(count) => count,
+        Err(err) => {
+            println!("failed to replace a vector of '{}' in '{}' {:?}", word.word, kind.name, err);
+            0
+        }
+    }
+}
+
+/// A vector of a word in a kind, packed vectors are unpacked and codes are decoded.
 pub fn word_2_vector(conn: &DsjConnection, word: &Word, kind: &Kind) -> Option<Vec<f32>> {
     use db::schema::vectors::dsl::{vectors, word_id, kind_id, position};
 
-    match vectors
+    let points = match vectors
         .filter(word_id.eq(word.id))
         .filter(kind_id.eq(kind.id))
         .order(position)
         .load::<Vector>(conn) {
-        Ok(stmt) => Some(stmt.iter().map(|vec| vec.point).collect()),
-        Err(_) => None,
+        Ok(stmt) => stmt.iter().map(|vec| vec.point).collect::<Vec<_>>(),
+        Err(_) => return None,
+    };
+
+    if !points.is_empty() {
+        return Some(points);
+    }
+
+    if let (Some(quantizer), Some(packed)) = (Quantizer::load(conn, kind), get_packed(conn, word, kind)) {
+        return Some(quantizer.decode(&packed));
+    }
+
+    match (ProductQuantizer::load(conn, kind), get_codes(conn, word, kind)) {
+        (Some(quantizer), Some(codes)) => Some(quantizer.decode(&codes)),
+        _ => Some(points),
+    }
+}
+
+/// Looks a word up in a kind, a word without `lang` matches any language.
+pub fn lookup_vector(conn: &DsjConnection, w: &str, lang: Option<&str>, kind: &Kind) -> Option<(Word, Vec<f32>)> {
+    find_words(conn, w, lang)?
+        .into_iter()
+        .filter_map(|word| {
+            let vec = word_2_vector(conn, &word, kind)?;
+
+            if vec.is_empty() {
+                None
+            } else {
+                Some((word, vec))
+            }
+        })
+        .next()
+}
+
+/// Count of words and a dimension of a kind.
+pub fn kind_size(conn: &DsjConnection, kind: &Kind) -> Option<(i64, i64)> {
+    use db::schema::vectors::dsl::{vectors, kind_id, position};
+
+    if let Some(quantizer) = Quantizer::load(conn, kind) {
+        let (words, first) = packed_sample(conn, kind)?;
+
+        return Some((words, first.map(|vec| quantizer.decode(&vec).len()).unwrap_or_default() as i64));
+    }
+
+    if let Some(quantizer) = ProductQuantizer::load(conn, kind) {
+        return Some((codes_count(conn, kind)?, quantizer.dim() as i64));
+    }
+
+    let words = vectors
+        .filter(kind_id.eq(kind.id))
+        .filter(position.eq(0))
+        .count()
+        .get_result::<i64>(conn)
+        .ok()?;
+
+    let points = vectors
+        .filter(kind_id.eq(kind.id))
+        .count()
+        .get_result::<i64>(conn)
+        .ok()?;
+
+    Some((words, if words > 0 { points / words } else { 0 }))
+}
+
+/// All vectors of a kind grouped by a word id.
+pub fn kind_vectors(conn: &DsjConnection, kind: &Kind) -> Option<Vec<(i32, Vec<f32>)>> {
+    use db::schema::vectors::dsl::{vectors, kind_id, word_id, position};
+
+    if let Some(quantizer) = Quantizer::load(conn, kind) {
+        return kind_packed(conn, kind).map(|packed| packed.into_iter()
+            .map(|(id, vec)| (id, quantizer.decode(&vec)))
+            .collect());
+    }
+
+    if let Some(quantizer) = ProductQuantizer::load(conn, kind) {
+        return kind_codes(conn, kind).map(|codes| codes.into_iter()
+            .map(|(id, codes)| (id, quantizer.decode(&codes)))
+            .collect());
+    }
+
+    let points = match vectors
+        .filter(kind_id.eq(kind.id))
+        .order((word_id, position))
+        .load::<Vector>(conn) {
+        Ok(points) => points,
+        Err(err) => {
+            println!("failed to get vectors of '{}' {:?}", kind.name, err);
+            return None;
+        }
+    };
+
+    let mut result: Vec<(i32, Vec<f32>)> = Vec::new();
+
+    for point in points.into_iter() {
+        let is_next = match result.last() {
+            Some(&(id, _)) => id != point.word_id,
+            None => true,
+        };
+
+        if is_next {
+            result.push((point.word_id, Vec::new()));
+        }
+
+        if let Some(&mut (_, ref mut vec)) = result.last_mut() {
+            vec.push(point.point);
+        }
     }
-}
\ No newline at end of file
+
+    Some(result)
+}
@@ -8,6 +8,7 @@ use db::schema::words;
 pub struct Word {
     pub id: i32,
     pub word: String,
+    pub lang: String,
 }
 
 impl Default for Word {
@@ -15,6 +16,7 @@ impl Default for Word {
         Word {
             id: 0,
             word: "".to_string(),
+            lang: "".to_string(),
         }
     }
 }
@@ -23,13 +25,15 @@ impl Default for Word {
 #[table_name = "words"]
 pub struct NewWord<'a> {
     pub word: &'a str,
+    pub lang: &'a str,
 }
 
-pub fn create_word<'a>(conn: &DsjConnection, w: &'a str) -> Option<Word> {
+pub fn create_word<'a>(conn: &DsjConnection, w: &'a str, l: &'a str) -> Option<Word> {
     use db::schema::words::dsl::words;
 
     let new_word = NewWord {
         word: w,
+        lang: l,
     };
 
     if let Err(err) = diesel::insert_into(words)
@@ -38,13 +42,14 @@ pub fn create_word<'a>(conn: &DsjConnection, w: &'a str) -> Option<Word> {
         println!("failed to insert {:?} with {:?}", new_word, err);
     }
 
-    get_word(&conn, w)
+    get_word(&conn, w, l)
 }
 
-pub fn get_word<'a>(conn: &DsjConnection, w: &'a str) -> Option<Word> {
-    use db::schema::words::dsl::{words, word};
+pub fn get_word<'a>(conn: &DsjConnection, w: &'a str, l: &'a str) -> Option<Word> {
+    use db::schema::words::dsl::{words, word, lang};
 
     match words.filter(word.eq(w))
+        .filter(lang.eq(l))
         .load::<Word>(conn) {
         Ok(res) => Some(res.first()?.clone()),
         Err(_) => {
@@ -53,6 +58,22 @@ pub fn get_word<'a>(conn: &DsjConnection, w: &'a str) -> Option<Word> {
     }
 }
 
+pub fn find_words<'a>(conn: &DsjConnection, w: &'a str, l: Option<&'a str>) -> Option<Vec<Word>> {
+    use db::schema::words::dsl::{words, word, lang, id};
+
+    let query = words.filter(word.eq(w)).order(id).into_boxed();
+
+    let query = match l {
+        Some(l) => query.filter(lang.eq(l)),
+        None => query,
+    };
+
+    match query.load::<Word>(conn) {
+        Ok(res) => Some(res),
+        Err(_) => None,
+    }
+}
+
 pub fn words_list(conn: &DsjConnection) -> Option<Vec<Word>> {
     use db::schema::words::dsl::words;
 
@@ -61,3 +82,22 @@ pub fn words_list(conn: &DsjConnection) -> Option<Vec<Word>> {
         Err(_) => None,
     }
 }
+
+/// Distinct words starting with a prefix in the byte order.
+pub fn complete_words(conn: &DsjConnection, prefix: &str, limit: i64) -> Option<Vec<String>> {
+    use db::schema::words::dsl::{words, word};
+
+    // every UTF-8 continuation of a prefix is less than the prefix with the last code point
+    let upper = format!("{}{}", prefix, ::std::char::MAX);
+
+    match words.select(word)
+        .filter(word.ge(prefix))
+        .filter(word.lt(upper))
+        .distinct()
+        .order(word)
+        .limit(limit)
+        .load::<String>(conn) {
+        Ok(res) => Some(res),
+        Err(_) => None,
+    }
+}
@@ -2,6 +2,7 @@ table! {
     words {
         id -> Integer,
         word -> VarChar,
+        lang -> VarChar,
     }
 }
 
@@ -19,4 +20,68 @@ table! {
         position -> Integer,
         point -> Float,
     }
-}
\ No newline at end of file
+}
+
+table! {
+    ranks (word_id, kind_id) {
+        word_id -> Integer,
+        kind_id -> Integer,
+        rank -> Integer,
+        source_index -> Nullable<BigInt>,
+    }
+}
+
+table! {
+    indexes (kind_id) {
+        kind_id -> Integer,
+        m -> Integer,
+        ef_construction -> Integer,
+        checksum -> BigInt,
+        data -> Binary,
+    }
+}
+
+table! {
+    properties (kind_id, name) {
+        kind_id -> Integer,
+        name -> VarChar,
+        value -> VarChar,
+    }
+}
+
+table! {
+    subwords (kind_id, bucket) {
+        kind_id -> Integer,
+        bucket -> Integer,
+        vector -> Binary,
+    }
+}
+
+table! {
+    packed_vectors (word_id, kind_id) {
+        word_id -> Integer,
+        kind_id -> Integer,
+        vector -> Binary,
+    }
+}
+
+table! {
+    pq_codebooks (kind_id, subspace) {
+        kind_id -> Integer,
+        subspace -> Integer,
+        dim -> Integer,
+        centroids -> Binary,
+    }
+}
+
+table! {
+    pq_codes (word_id, kind_id) {
+        word_id -> Integer,
+        kind_id -> Integer,
+        codes -> Binary,
+    }
+}
+
+joinable!(ranks -> words (word_id));
+
+allow_tables_to_appear_in_same_query!(words, ranks);
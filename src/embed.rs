@@ -0,0 +1,298 @@
+use std::collections::{HashMap, HashSet};
+
+use db::connection::DsjConnection;
+use db::models::rank::get_rank;
+use db::models::vector::kind_size;
+use lookup::{Found, Lookup};
+use space::{dot, norm};
+
+// Euler–Mascheroni constant of a harmonic number approximation
+const GAMMA: f64 = 0.577_215_664_901_532_9;
+const ITERATIONS: usize = 100;
+
+/// Ways to combine word vectors of a text.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Weighting {
+    Mean,
+    // term frequency in a text by inverse frequency of texts with a term
+    TfIdf,
+    // a / (a + p(word)) with the first principal component of all texts removed,
+    // "A Simple but Tough-to-Beat Baseline for Sentence Embeddings"
+    Sif(f32),
+}
+
+impl Weighting {
+    pub fn parse(name: &str, sif_a: f32) -> Option<Weighting> {
+        match name {
+            "mean" => Some(Weighting::Mean),
+            "tfidf" => Some(Weighting::TfIdf),
+            "sif" => Some(Weighting::Sif(sif_a)),
+            _ => None,
+        }
+    }
+}
+
+/// A vector of a text, zeros for a text without known words.
+#[derive(Debug, PartialEq)]
+pub struct Embedding {
+    pub vec: Vec<f32>,
+    pub tokens: usize,
+    pub missing: Vec<String>,
+}
+
+/// Words of a text split on everything except letters, digits, '-' and '_'.
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        .map(|token| token.trim_matches('-'))
+        .filter(|token| !token.is_empty())
+        .map(|token| token.to_string())
+        .collect()
+}
+
+// a probability of a word by Zipf's law over a rank in a frequency sorted source
+fn zipf(rank: i64, words: i64) -> f32 {
+    let words = words.max(1) as f64;
+    let harmonic = words.ln() + GAMMA + 0.5 / words;
+
+    (1.0 / ((rank + 1) as f64 * harmonic)) as f32
+}
+
+fn normalize(vec: &mut [f32]) -> bool {
+    let length = norm(vec);
+
+    if length == 0.0 {
+        return false;
+    }
+
+    for point in vec.iter_mut() {
+        *point /= length;
+    }
+
+    true
+}
+
+// the top right singular vector of rows by power iteration on rows' Gram matrix
+fn first_component(rows: &[Vec<f32>]) -> Option<Vec<f32>> {
+    let dim = rows.first()?.len();
+    let mut component = rows.iter().find(|row| dot(row, row) > 0.0)?.clone();
+
+    normalize(&mut component);
+
+    for _ in 0..ITERATIONS {
+        let mut next = vec![0f32; dim];
+
+        for row in rows.iter() {
+            let projection = dot(row, &component);
+
+            for (point, value) in next.iter_mut().zip(row.iter()) {
+                *point += projection * value;
+            }
+        }
+
+        if !normalize(&mut next) {
+            return None;
+        }
+
+        let delta = next.iter().zip(component.iter()).map(|(a, b)| (a - b).abs()).fold(0f32, f32::max);
+
+        component = next;
+
+        if delta < 1e-6 {
+            break;
+        }
+    }
+
+    Some(component)
+}
+
+/// Embeds texts by word vectors of a kind, every distinct token is looked up once.
+pub fn embed(conn: &DsjConnection, lookup: &Lookup, texts: &[String], lang: Option<&str>, weighting: Weighting) -> Vec<Embedding> {
+    let tokenized = texts.iter().map(|text| tokenize(text)).collect::<Vec<_>>();
+    let (words, dim) = kind_size(conn, lookup.kind()).unwrap_or_default();
+
+    let mut found: HashMap<&str, Option<Found>> = HashMap::new();
+
+    for token in tokenized.iter().flat_map(|tokens| tokens.iter()) {
+        if !found.contains_key(token.as_str()) {
+            found.insert(token, lookup.find(conn, token, lang));
+        }
+    }
+
+    // "Король" and "король" found as the same stored word are one term
+    let term = |token: &str| -> String {
+        match found.get(token) {
+            Some(&Some(ref found)) => found.stored().unwrap_or(token).to_string(),
+            _ => token.to_string(),
+        }
+    };
+
+    let mut documents: HashMap<String, usize> = HashMap::new();
+
+    for tokens in tokenized.iter() {
+        for term in tokens.iter().map(|token| term(token)).collect::<HashSet<_>>() {
+            *documents.entry(term).or_insert(0) += 1;
+        }
+    }
+
+    let weight = |token: &str, found: &Found| -> f32 {
+        match weighting {
+            Weighting::Mean => 1.0,
+            // smoothed as in scikit-learn, a term of all texts still counts
+            Weighting::TfIdf => {
+                let df = documents.get(&term(token)).cloned().unwrap_or(0);
+
+                ((1 + texts.len()) as f32 / (1 + df) as f32).ln() + 1.0
+            }
+            // a word without a rank, e.g. composed from subwords, is the rarest one
+            Weighting::Sif(a) => {
+                let rank = found.word.as_ref()
+                    .and_then(|word| get_rank(conn, word, lookup.kind()))
+                    .map(|rank| rank.rank as i64)
+                    .unwrap_or(words);
+
+                a / (a + zipf(rank, words))
+            }
+        }
+    };
+
+    let mut weights: HashMap<&str, f32> = HashMap::new();
+    let mut embeddings = Vec::new();
+
+    for tokens in tokenized.iter() {
+        let mut vec = vec![0f32; dim as usize];
+        let mut total = 0f32;
+        let mut count = 0;
+        let mut missing = Vec::new();
+
+        for token in tokens.iter() {
+            match found.get(token.as_str()) {
+                Some(&Some(ref found)) => {
+                    if !weights.contains_key(token.as_str()) {
+                        weights.insert(token.as_str(), weight(token, found));
+                    }
+
+                    let weight = weights[token.as_str()];
+
+                    for (point, value) in vec.iter_mut().zip(found.vec.iter()) {
+                        *point += weight * value;
+                    }
+
+                    total += weight;
+                    count += 1;
+                }
+                _ => missing.push(token.to_string()),
+            }
+        }
+
+        // SIF averages weighted vectors, others are weighted averages
+        let norm = match weighting {
+            Weighting::Sif(_) => count as f32,
+            _ => total,
+        };
+
+        if norm > 0.0 {
+            for point in vec.iter_mut() {
+                *point /= norm;
+            }
+        }
+
+        embeddings.push(Embedding {
+            vec,
+            tokens: tokens.len(),
+            missing,
+        });
+    }
+
+    if let Weighting::Sif(_) = weighting {
+        remove_component(&mut embeddings);
+    }
+
+    embeddings
+}
+
+// a single text would lose all of its own direction
+fn remove_component(embeddings: &mut [Embedding]) {
+    let rows = embeddings.iter()
+        .map(|embedding| embedding.vec.clone())
+        .filter(|vec| dot(vec, vec) > 0.0)
+        .collect::<Vec<_>>();
+
+    if rows.len() < 2 {
+        return;
+    }
+
+    let component = match first_component(&rows) {
+        Some(component) => component,
+        None => return,
+    };
+
+    for embedding in embeddings.iter_mut() {
+        let projection = dot(&embedding.vec, &component);
+
+        for (point, value) in embedding.vec.iter_mut().zip(component.iter()) {
+            *point -= projection * value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::testing::test_connection;
+    use db::models::kind::get_kind;
+    use db::models::rank::{Rank, add_rank};
+    use db::models::word::get_word;
+    use lookup::{STRATEGIES, Strategy};
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(tokenize("Король, королева — и «яблоко»!"), vec!["Король", "королева", "и", "яблоко"], "check punctuation");
+        assert_eq!(tokenize("  во-первых слово_NOUN 42 "), vec!["во-первых", "слово_NOUN", "42"], "check tokens");
+        assert_eq!(tokenize(" - "), Vec::<String>::new(), "check empty");
+    }
+
+    #[test]
+    fn test_first_component() {
+        let rows = vec![vec![2.0f32, 2.0], vec![-1.0, -1.1], vec![3.0, 2.9]];
+
+        match first_component(&rows) {
+            Some(component) => assert!((component[0].abs() - 0.5f32.sqrt()).abs() < 0.01 && component[0] * component[1] > 0.0, "check component {:?}", component),
+            None => assert!(false, "failed to find a component"),
+        }
+
+        assert_eq!(first_component(&[vec![0.0f32, 0.0]]), None, "check zeros");
+    }
+
+    #[test]
+    fn test_embed() {
+        let conn = test_connection();
+        let kind = get_kind(&conn, "fasttext").unwrap_or_default();
+        let lookup = Lookup::new(&conn, &kind, &Strategy::parse_chain(STRATEGIES).unwrap_or_default());
+        let texts = vec!["Король и королева".to_string(), "король".to_string(), "".to_string()];
+
+        let embeddings = embed(&conn, &lookup, &texts, None, Weighting::Mean);
+
+        assert_eq!((embeddings[0].tokens, &embeddings[0].missing), (3, &vec!["и".to_string()]), "check tokens");
+        assert!(embeddings[0].vec.iter().zip([0.9f32, 0.45, 0.45, 0.0].iter()).all(|(a, b)| (a - b).abs() < 1e-6), "check mean {:?}", embeddings[0].vec);
+        assert_eq!(embeddings[2].vec, vec![0f32; 4], "check empty text");
+
+        // "король" is in both texts and weights less than "королева"
+        let embeddings = embed(&conn, &lookup, &texts, None, Weighting::TfIdf);
+
+        assert!(embeddings[0].vec[2] > embeddings[0].vec[1], "check tfidf {:?}", embeddings[0].vec);
+
+        for (rank, &w) in ["королева", "король"].iter().enumerate() {
+            if let Some(word) = get_word(&conn, w, "ru") {
+                add_rank(&conn, &Rank::new(&word, &kind, rank as i32, None));
+            }
+        }
+
+        let embeddings = embed(&conn, &lookup, &texts, None, Weighting::Sif(1e-3));
+
+        let (a, b) = (&embeddings[0].vec, &embeddings[1].vec);
+
+        // without the first component both texts lie along the second one
+        assert!((dot(a, b).abs() / (dot(a, a) * dot(b, b)).sqrt() - 1.0).abs() < 1e-3, "check removed component {:?}", embeddings);
+        assert_eq!(embeddings[2].vec, vec![0f32; 4], "check empty text of sif");
+    }
+}
@@ -0,0 +1,223 @@
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+
+/// A pair of words of a similarity benchmark with a human score.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pair {
+    pub first: String,
+    pub second: String,
+    pub score: f64,
+}
+
+// SimLex-999 and WordSim-353 are tab separated, RuSim and some WordSim copies are comma separated
+fn fields(line: &str) -> Vec<&str> {
+    if line.contains('\t') {
+        line.split('\t').map(|field| field.trim()).collect()
+    } else if line.contains(',') {
+        line.split(',').map(|field| field.trim()).collect()
+    } else {
+        line.split_whitespace().collect()
+    }
+}
+
+/// Parses a line of two words followed by columns with a score as the first number after them,
+/// e.g. "old\tnew\tA\t1.58" of SimLex-999, a header or a comment gives none.
+pub fn parse_pair(line: &str) -> Option<Pair> {
+    if line.starts_with('#') {
+        return None;
+    }
+
+    let fields = fields(line);
+
+    if fields.len() < 3 || fields[0].is_empty() || fields[1].is_empty() {
+        return None;
+    }
+
+    let score = fields[2..].iter().filter_map(|field| field.parse::<f64>().ok()).next()?;
+
+    Some(Pair {
+        first: fields[0].to_string(),
+        second: fields[1].to_string(),
+        score,
+    })
+}
+
+pub fn read_pairs(path: &str) -> Result<Vec<Pair>, String> {
+    let file = File::open(path).map_err(|err| format!("failed to open '{}': {}", path, err))?;
+    let mut pairs = Vec::new();
+
+    for line in BufReader::new(file).lines() {
+        let line = line.map_err(|err| format!("failed to read '{}': {}", path, err))?;
+
+        pairs.extend(parse_pair(&line));
+    }
+
+    if pairs.is_empty() {
+        return Err(format!("no word pairs with scores in '{}'", path));
+    }
+
+    Ok(pairs)
+}
+
+pub fn pearson(x: &[f64], y: &[f64]) -> Option<f64> {
+    let n = x.len().min(y.len());
+
+    if n < 2 {
+        return None;
+    }
+
+    let (mean_x, mean_y) = (x.iter().sum::<f64>() / n as f64, y.iter().sum::<f64>() / n as f64);
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+
+    for (a, b) in x.iter().zip(y.iter()) {
+        covariance += (a - mean_x) * (b - mean_y);
+        variance_x += (a - mean_x) * (a - mean_x);
+        variance_y += (b - mean_y) * (b - mean_y);
+    }
+
+    if variance_x > 0.0 && variance_y > 0.0 {
+        Some(covariance / (variance_x * variance_y).sqrt())
+    } else {
+        None
+    }
+}
+
+// ranks from 1, tied values take a mean of their ranks
+fn ranks(values: &[f64]) -> Vec<f64> {
+    let mut order = (0..values.len()).collect::<Vec<_>>();
+
+    order.sort_by(|&i, &j| values[i].partial_cmp(&values[j]).unwrap_or(Ordering::Equal));
+
+    let mut ranks = vec![0.0; values.len()];
+    let mut start = 0;
+
+    while start < order.len() {
+        let mut end = start + 1;
+
+        while end < order.len() && values[order[end]] == values[order[start]] {
+            end += 1;
+        }
+
+        for &i in order[start..end].iter() {
+            ranks[i] = (start + end + 1) as f64 / 2.0;
+        }
+
+        start = end;
+    }
+
+    ranks
+}
+
+/// Pearson correlation of ranks.
+pub fn spearman(x: &[f64], y: &[f64]) -> Option<f64> {
+    pearson(&ranks(x), &ranks(y))
+}
+
+/// Questions "a b c d" of a section of an analogy benchmark, a is to b as c is to d.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub name: String,
+    pub questions: Vec<[String; 4]>,
+}
+
+/// Parses the questions-words format of word2vec: ": section" headers followed by lines of four words.
+pub fn parse_questions(text: &str) -> Result<Vec<Section>, String> {
+    let mut sections: Vec<Section> = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if line.starts_with(':') {
+            sections.push(Section {
+                name: line[1..].trim().to_string(),
+                questions: Vec::new(),
+            });
+            continue;
+        }
+
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        if words.len() != 4 {
+            return Err(format!("line {}: expected four words, got '{}'", i + 1, line));
+        }
+
+        if sections.is_empty() {
+            sections.push(Section {
+                name: String::new(),
+                questions: Vec::new(),
+            });
+        }
+
+        if let Some(section) = sections.last_mut() {
+            section.questions.push([words[0].to_string(), words[1].to_string(), words[2].to_string(), words[3].to_string()]);
+        }
+    }
+
+    Ok(sections)
+}
+
+pub fn read_questions(path: &str) -> Result<Vec<Section>, String> {
+    let mut text = String::new();
+
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|err| format!("failed to read '{}': {}", path, err))?;
+
+    parse_questions(&text).map_err(|err| format!("{}: {}", path, err))
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[test]
+    fn test_parse_pair() {
+        let pair = |first: &str, second: &str, score| Some(Pair {
+            first: first.to_string(),
+            second: second.to_string(),
+            score,
+        });
+
+        assert_eq!(parse_pair("word1\tword2\tPOS\tSimLex999\tconc(w1)"), None, "check simlex header");
+        assert_eq!(parse_pair("old\tnew\tA\t1.58\t2.72"), pair("old", "new", 1.58), "check simlex");
+        assert_eq!(parse_pair("love\tsex\t6.77"), pair("love", "sex", 6.77), "check wordsim");
+        assert_eq!(parse_pair("Word 1,Word 2,Human (mean)"), None, "check csv header");
+        assert_eq!(parse_pair("король, королева, 0.8"), pair("король", "королева", 0.8), "check csv");
+        assert_eq!(parse_pair("тигр кот 7.5"), pair("тигр", "кот", 7.5), "check spaces");
+        assert_eq!(parse_pair("# a comment 1 2"), None, "check comment");
+    }
+
+    #[test]
+    fn test_parse_questions() {
+        let text = ": capital-common-countries\nAthens Greece Baghdad Iraq\n\n: family\nboy girl brother sister\nboy girl dad mom\n";
+
+        match parse_questions(text) {
+            Ok(sections) => {
+                assert_eq!(sections.iter().map(|section| (section.name.as_str(), section.questions.len())).collect::<Vec<_>>(),
+                           vec![("capital-common-countries", 1), ("family", 2)], "check sections");
+                assert_eq!(sections[0].questions[0][3], "Iraq", "check question");
+            }
+            Err(err) => assert!(false, "failed to parse questions: {}", err),
+        }
+
+        assert_eq!(parse_questions("a b c d").map(|sections| sections[0].name.clone()), Ok(String::new()), "check no header");
+        assert!(parse_questions(": s\na b c").is_err(), "check three words");
+    }
+
+    #[test]
+    fn test_correlation() {
+        let x = [1.0, 2.0, 3.0, 4.0];
+
+        assert!((pearson(&x, &[2.0, 4.0, 6.0, 8.0]).unwrap_or_default() - 1.0).abs() < 1e-12, "check pearson");
+        assert!((pearson(&x, &[4.0, 3.0, 2.0, 1.0]).unwrap_or_default() + 1.0).abs() < 1e-12, "check negative pearson");
+        assert_eq!(pearson(&x, &[1.0, 1.0, 1.0, 1.0]), None, "check constant");
+        assert_eq!(ranks(&[10.0, 30.0, 20.0, 20.0]), vec![1.0, 4.0, 2.5, 2.5], "check ties");
+        assert!((spearman(&x, &[1.0, 10.0, 100.0, 1000.0]).unwrap_or_default() - 1.0).abs() < 1e-12, "check monotonic");
+        assert!((spearman(&x, &[1.0, 3.0, 2.0, 4.0]).unwrap_or_default() - 0.8).abs() < 1e-12, "check spearman");
+    }
+}
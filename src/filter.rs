@@ -0,0 +1,212 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use regex::Regex;
+
+/// A reason to drop an imported word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Reason {
+    Length,
+    Include,
+    Exclude,
+    Allowlist,
+    Denylist,
+    Digits,
+    Url,
+    // a vector of another dimension than a transform expects
+    Dimension,
+}
+
+impl Reason {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Reason::Length => "length",
+            Reason::Include => "include",
+            Reason::Exclude => "exclude",
+            Reason::Allowlist => "allowlist",
+            Reason::Denylist => "denylist",
+            Reason::Digits => "digits",
+            Reason::Url => "url",
+            Reason::Dimension => "dimension",
+        }
+    }
+}
+
+/// Words of a file, one per line.
+pub fn read_words(path: &str) -> Result<HashSet<String>, String> {
+    let file = File::open(path).map_err(|err| format!("failed to open '{}': {}", path, err))?;
+
+    BufReader::new(file).lines()
+        .map(|line| line.map(|line| line.trim().to_string()))
+        .filter(|line| line.as_ref().map(|line| !line.is_empty()).unwrap_or(true))
+        .collect::<Result<HashSet<_>, _>>()
+        .map_err(|err| format!("failed to read '{}': {}", path, err))
+}
+
+/// Import filters of words, the first `limit` records of a file are read at most.
+#[derive(Debug, Clone, Default)]
+pub struct Filter {
+    pub limit: Option<usize>,
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    pub include: Option<Regex>,
+    pub exclude: Option<Regex>,
+    pub allowlist: Option<HashSet<String>>,
+    pub denylist: Option<HashSet<String>>,
+    pub digits: bool,
+    pub urls: bool,
+}
+
+impl Filter {
+    pub fn is_empty(&self) -> bool {
+        self.limit.is_none() && self.min_length.is_none() && self.max_length.is_none() &&
+            self.include.is_none() && self.exclude.is_none() &&
+            self.allowlist.is_none() && self.denylist.is_none() &&
+            !self.digits && !self.urls
+    }
+
+    /// A reason to drop a word, none to keep it.
+    pub fn check(&self, word: &str) -> Option<Reason> {
+        let length = word.chars().count();
+
+        if self.min_length.map(|min| length < min).unwrap_or(false) ||
+            self.max_length.map(|max| length > max).unwrap_or(false) {
+            return Some(Reason::Length);
+        }
+
+        if self.include.as_ref().map(|include| !include.is_match(word)).unwrap_or(false) {
+            return Some(Reason::Include);
+        }
+
+        if self.exclude.as_ref().map(|exclude| exclude.is_match(word)).unwrap_or(false) {
+            return Some(Reason::Exclude);
+        }
+
+        if self.allowlist.as_ref().map(|allowlist| !allowlist.contains(word)).unwrap_or(false) {
+            return Some(Reason::Allowlist);
+        }
+
+        if self.denylist.as_ref().map(|denylist| denylist.contains(word)).unwrap_or(false) {
+            return Some(Reason::Denylist);
+        }
+
+        if self.digits && word.chars().any(char::is_numeric) {
+            return Some(Reason::Digits);
+        }
+
+        if self.urls && is_url(word) {
+            return Some(Reason::Url);
+        }
+
+        None
+    }
+}
+
+// "http://example.com/a", "www.example.com" and "example.com" of web crawl vocabularies
+fn is_url(word: &str) -> bool {
+    let word = word.to_lowercase();
+
+    word.contains("://") || word.starts_with("www.") ||
+        [".com", ".org", ".net", ".ru", ".html", ".php"].iter()
+            .any(|suffix| word.len() > suffix.len() && (word.ends_with(suffix) || word.contains(&format!("{}/", suffix))))
+}
+
+/// Counts of dropped words by a reason and the limit which cut a file short.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filtered {
+    counts: BTreeMap<Reason, u64>,
+    cut: Option<usize>,
+}
+
+impl Filtered {
+    pub fn add(&mut self, reason: Reason) {
+        *self.counts.entry(reason).or_insert(0) += 1;
+    }
+
+    /// Records beyond a limit aren't read, so they are reported without a count.
+    pub fn cut(&mut self, limit: usize) {
+        self.cut = Some(limit);
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty() && self.cut.is_none()
+    }
+
+    /// "12 filtered (length 3, digits 9), cut at 200000 records", empty without dropped words.
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+
+        if !self.counts.is_empty() {
+            let reasons = self.counts.iter()
+                .map(|(reason, count)| format!("{} {}", reason.name(), count))
+                .collect::<Vec<_>>();
+
+            parts.push(format!("{} filtered ({})", self.total(), reasons.join(", ")));
+        }
+
+        if let Some(limit) = self.cut {
+            parts.push(format!("cut at {} records", limit));
+        }
+
+        parts.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[test]
+    fn test_check() {
+        let filter = Filter {
+            min_length: Some(2),
+            max_length: Some(10),
+            include: Regex::new(r"^[\p{Cyrillic}\d-]+$").ok(),
+            exclude: Regex::new("^-|-$").ok(),
+            denylist: Some(["вы".to_string()].iter().cloned().collect()),
+            digits: true,
+            ..Filter::default()
+        };
+
+        assert!(!filter.is_empty() && Filter::default().is_empty(), "check empty");
+        assert_eq!(filter.check("король"), None, "check kept");
+        assert_eq!(filter.check("я"), Some(Reason::Length), "check min length");
+        assert_eq!(filter.check("достопримечательность"), Some(Reason::Length), "check max length");
+        assert_eq!(filter.check("king"), Some(Reason::Include), "check include");
+        assert_eq!(filter.check("-то"), Some(Reason::Exclude), "check exclude");
+        assert_eq!(filter.check("вы"), Some(Reason::Denylist), "check denylist");
+        assert_eq!(filter.check("2018"), Some(Reason::Digits), "check digits");
+
+        let filter = Filter {
+            allowlist: Some(["вы".to_string()].iter().cloned().collect()),
+            ..Filter::default()
+        };
+
+        assert_eq!((filter.check("вы"), filter.check("мы")), (None, Some(Reason::Allowlist)), "check allowlist");
+        assert_eq!(Filter { urls: true, ..Filter::default() }.check("почта.ru"), Some(Reason::Url), "check url");
+        assert_eq!(Filter { urls: true, ..Filter::default() }.check("http://example.com/a"), Some(Reason::Url), "check scheme");
+        assert_eq!(Filter { urls: true, ..Filter::default() }.check(".com"), None, "check a bare suffix");
+    }
+
+    #[test]
+    fn test_filtered() {
+        let mut filtered = Filtered::default();
+
+        assert!(filtered.is_empty() && filtered.summary() == "", "check nothing filtered");
+
+        filtered.add(Reason::Digits);
+        filtered.add(Reason::Length);
+        filtered.add(Reason::Digits);
+
+        assert_eq!(filtered.total(), 3, "check total");
+        assert_eq!(filtered.summary(), "3 filtered (length 1, digits 2)", "check summary");
+
+        filtered.cut(100);
+
+        assert_eq!(filtered.summary(), "3 filtered (length 1, digits 2), cut at 100 records", "check cut");
+    }
+}
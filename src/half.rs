@@ -0,0 +1,123 @@
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = ((bits & 0x8000) as u32) << 16;
+    let exponent = ((bits >> 10) & 0x1f) as u32;
+    let mantissa = (bits & 0x03ff) as u32;
+
+    let value = match (exponent, mantissa) {
+        (0, 0) => sign,
+        (0, _) => {
+            // subnormal, renormalize the mantissa
+            let mut exponent = 127 - 15 + 1;
+            let mut mantissa = mantissa;
+
+            while mantissa & 0x0400 == 0 {
+                mantissa <<= 1;
+                exponent -= 1;
+            }
+
+            sign | (exponent << 23) | ((mantissa & 0x03ff) << 13)
+        }
+        (0x1f, _) => sign | 0x7f80_0000 | (mantissa << 13),
+        (_, _) => sign | ((exponent + 127 - 15) << 23) | (mantissa << 13),
+    };
+
+    f32::from_bits(value)
+}
+
+/// Rounds to the nearest half precision value, ties to even.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x007f_ffff;
+
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x0200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+
+    // a subnormal keeps bits of the mantissa with the implicit one shifted by the exponent
+    let (value, rest, half) = if exponent <= 0 {
+        let shift = (14 - exponent) as u32;
+
+        if shift > 24 {
+            return sign;
+        }
+
+        let full = mantissa | 0x0080_0000;
+
+        (full >> shift, full & ((1 << shift) - 1), 1 << (shift - 1))
+    } else {
+        (((exponent as u32) << 10) | (mantissa >> 13), mantissa & 0x1fff, 0x1000)
+    };
+
+    // a carry to the exponent rounds up to the next binade or infinity
+    let value = if rest > half || (rest == half && value & 1 == 1) { value + 1 } else { value };
+
+    sign | value as u16
+}
+
+pub fn bf16_to_f32(bits: u16) -> f32 {
+    f32::from_bits((bits as u32) << 16)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[test]
+    fn test_f16_to_f32() {
+        let cases = [
+            (0x0000u16, 0.0f32),
+            (0x3c00, 1.0),
+            (0xc000, -2.0),
+            (0x3555, 0.33325195),
+            (0x7bff, 65504.0),
+            (0x0001, 0.000000059604645),
+        ];
+
+        for &(bits, expected) in cases.iter() {
+            assert_eq!(f16_to_f32(bits), expected, "check {:#x}", bits);
+        }
+
+        assert!(f16_to_f32(0x7c00).is_infinite(), "check infinity");
+        assert!(f16_to_f32(0x7e00).is_nan(), "check nan");
+    }
+
+    #[test]
+    fn test_f32_to_f16() {
+        let cases = [
+            (0.0f32, 0x0000u16),
+            (-2.0, 0xc000),
+            (0.33325195, 0x3555),
+            (65504.0, 0x7bff),
+            (0.000000059604645, 0x0001),
+            (1.0 + 1.0 / 2048.0, 0x3c00),
+            (1.0 + 3.0 / 2048.0, 0x3c02),
+            (65520.0, 0x7c00),
+            (1e-9, 0x0000),
+        ];
+
+        for &(value, expected) in cases.iter() {
+            assert_eq!(f32_to_f16(value), expected, "check {}", value);
+        }
+
+        assert!(f16_to_f32(f32_to_f16(::std::f32::NAN)).is_nan(), "check nan");
+
+        for bits in 0..0x7c00u16 {
+            assert_eq!(f32_to_f16(f16_to_f32(bits)), bits, "check round trip {:#x}", bits);
+        }
+    }
+
+    #[test]
+    fn test_bf16_to_f32() {
+        assert_eq!(bf16_to_f32(0x3f80), 1.0f32, "check one");
+        assert_eq!(bf16_to_f32(0xc040), -3.0f32, "check negative");
+        assert_eq!(bf16_to_f32(0x3e20), 0.15625f32, "check fraction");
+    }
+}
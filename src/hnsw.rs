@@ -0,0 +1,506 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use byteorder::{ByteOrder, LittleEndian};
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use db::connection::DsjConnection;
+use db::models::index::{Index, get_index, save_index};
+use db::models::kind::Kind;
+use space::{Neighbor, Space, norm};
+
+const MAGIC: u32 = 0x5753_4e48; // "HNSW"
+const VERSION: u32 = 1;
+const NONE: u32 = ::std::u32::MAX;
+const MAX_LEVEL: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Params {
+    pub m: usize,
+    pub ef_construction: usize,
+}
+
+impl Default for Params {
+    fn default() -> Params {
+        Params {
+            m: 16,
+            ef_construction: 200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Item {
+    distance: f32,
+    row: usize,
+}
+
+impl PartialEq for Item {
+    fn eq(&self, other: &Item) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Item {}
+
+impl PartialOrd for Item {
+    fn partial_cmp(&self, other: &Item) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Item {
+    fn cmp(&self, other: &Item) -> Ordering {
+        self.distance.partial_cmp(&other.distance)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.row.cmp(&other.row))
+    }
+}
+
+/// Hierarchical navigable small world graph over rows of a space
+/// (Malkov and Yashunin, 2016) with the cosine distance.
+#[derive(Debug, PartialEq)]
+pub struct Hnsw {
+    params: Params,
+    entry: Option<usize>,
+    max_level: usize,
+    links: Vec<Vec<Vec<u32>>>,
+}
+
+impl Hnsw {
+    pub fn build(space: &Space, params: Params) -> Hnsw {
+        let mut hnsw = Hnsw {
+            params,
+            entry: None,
+            max_level: 0,
+            links: vec![Vec::new(); space.len()],
+        };
+
+        let mut rng = XorShiftRng::from_seed([0x193a_6754, 0xa8a7_d469, 0x9783_0e05, 0x113b_a7bb]);
+        let level_mult = 1.0 / (params.m.max(2) as f64).ln();
+
+        for row in 0..space.len() {
+            let random: f64 = rng.gen_range(::std::f64::EPSILON, 1.0);
+            let level = ((-random.ln() * level_mult) as usize).min(MAX_LEVEL);
+
+            hnsw.insert(space, row, level);
+        }
+
+        hnsw
+    }
+
+    fn max_links(&self, level: usize) -> usize {
+        if level == 0 {
+            self.params.m * 2
+        } else {
+            self.params.m
+        }
+    }
+
+    fn distance(space: &Space, query: &[f32], query_norm: f32, row: usize) -> f32 {
+        1.0 - space.similarity(query, query_norm, row)
+    }
+
+    fn search_layer(&self, space: &Space, query: &[f32], query_norm: f32, entries: &[usize], ef: usize, level: usize) -> Vec<Item> {
+        let mut visited: HashSet<usize> = HashSet::new();
+        let mut candidates: BinaryHeap<Reverse<Item>> = BinaryHeap::new();
+        let mut result: BinaryHeap<Item> = BinaryHeap::new();
+
+        for &row in entries.iter() {
+            if visited.insert(row) {
+                let item = Item {
+                    distance: Hnsw::distance(space, query, query_norm, row),
+                    row,
+                };
+
+                candidates.push(Reverse(item));
+                result.push(item);
+            }
+        }
+
+        while let Some(Reverse(candidate)) = candidates.pop() {
+            let farthest = result.peek().map(|item| item.distance).unwrap_or(::std::f32::MAX);
+
+            if candidate.distance > farthest && result.len() >= ef {
+                break;
+            }
+
+            for &next in self.links[candidate.row][level].iter() {
+                let next = next as usize;
+
+                if !visited.insert(next) {
+                    continue;
+                }
+
+                let item = Item {
+                    distance: Hnsw::distance(space, query, query_norm, next),
+                    row: next,
+                };
+
+                let farthest = result.peek().map(|item| item.distance).unwrap_or(::std::f32::MAX);
+
+                if result.len() < ef || item.distance < farthest {
+                    candidates.push(Reverse(item));
+                    result.push(item);
+
+                    if result.len() > ef {
+                        result.pop();
+                    }
+                }
+            }
+        }
+
+        result.into_sorted_vec()
+    }
+
+    // keeps candidates closer to the query than to already selected ones
+    // and fills the rest with the nearest pruned candidates
+    fn select(space: &Space, candidates: &[Item], m: usize) -> Vec<u32> {
+        let mut selected: Vec<usize> = Vec::with_capacity(m);
+        let mut pruned: Vec<usize> = Vec::new();
+
+        for candidate in candidates.iter() {
+            if selected.len() >= m {
+                break;
+            }
+
+            let vec = space.vector(candidate.row);
+            let vec_norm = space.norm(candidate.row);
+
+            if selected.iter().all(|&row| Hnsw::distance(space, vec, vec_norm, row) > candidate.distance) {
+                selected.push(candidate.row);
+            } else {
+                pruned.push(candidate.row);
+            }
+        }
+
+        for row in pruned.into_iter() {
+            if selected.len() >= m {
+                break;
+            }
+
+            selected.push(row);
+        }
+
+        selected.into_iter().map(|row| row as u32).collect()
+    }
+
+    fn insert(&mut self, space: &Space, row: usize, level: usize) {
+        self.links[row] = vec![Vec::new(); level + 1];
+
+        let entry = match self.entry {
+            Some(entry) => entry,
+            None => {
+                self.entry = Some(row);
+                self.max_level = level;
+                return;
+            }
+        };
+
+        let query = space.vector(row);
+        let query_norm = space.norm(row);
+        let mut entries = vec![entry];
+
+        for current in (level + 1..self.max_level + 1).rev() {
+            entries = self.search_layer(space, query, query_norm, &entries, 1, current)
+                .into_iter()
+                .map(|item| item.row)
+                .take(1)
+                .collect();
+        }
+
+        for current in (0..level.min(self.max_level) + 1).rev() {
+            let found = self.search_layer(space, query, query_norm, &entries, self.params.ef_construction, current);
+            let max_links = self.max_links(current);
+            let neighbors = Hnsw::select(space, &found, self.params.m);
+
+            for &neighbor in neighbors.iter() {
+                let neighbor = neighbor as usize;
+
+                self.links[neighbor][current].push(row as u32);
+
+                if self.links[neighbor][current].len() > max_links {
+                    let vec = space.vector(neighbor);
+                    let vec_norm = space.norm(neighbor);
+
+                    let mut candidates = self.links[neighbor][current].iter()
+                        .map(|&link| Item {
+                            distance: Hnsw::distance(space, vec, vec_norm, link as usize),
+                            row: link as usize,
+                        })
+                        .collect::<Vec<_>>();
+
+                    candidates.sort();
+
+                    self.links[neighbor][current] = Hnsw::select(space, &candidates, max_links);
+                }
+            }
+
+            self.links[row][current] = neighbors;
+            entries = found.into_iter().map(|item| item.row).collect();
+        }
+
+        if level > self.max_level {
+            self.max_level = level;
+            self.entry = Some(row);
+        }
+    }
+
+    /// Approximate cosine similarity search, `ef` is a size of a dynamic candidate list.
+    pub fn search(&self, space: &Space, query: &[f32], top: usize, ef: usize, exclude: &[usize]) -> Vec<Neighbor> {
+        let entry = match self.entry {
+            Some(entry) => entry,
+            None => return Vec::new(),
+        };
+
+        let query_norm = norm(query);
+        let mut entries = vec![entry];
+
+        for current in (1..self.max_level + 1).rev() {
+            entries = self.search_layer(space, query, query_norm, &entries, 1, current)
+                .into_iter()
+                .map(|item| item.row)
+                .take(1)
+                .collect();
+        }
+
+        self.search_layer(space, query, query_norm, &entries, ef.max(top + exclude.len()), 0)
+            .into_iter()
+            .filter(|item| !exclude.contains(&item.row))
+            .take(top)
+            .map(|item| Neighbor {
+                row: item.row,
+                similarity: 1.0 - item.distance,
+            })
+            .collect()
+    }
+
+    /// Share of exact top neighbours found by the index for a sample of rows.
+    pub fn recall(&self, space: &Space, sample: usize, top: usize, ef: usize) -> f32 {
+        let step = (space.len() / sample.max(1)).max(1);
+        let mut expected = 0;
+        let mut found = 0;
+
+        for row in (0..space.len()).filter(|row| row % step == 0).take(sample) {
+            let query = space.vector(row);
+
+            let exact = space.neighbors(query, top, &[row])
+                .into_iter()
+                .map(|neighbor| neighbor.row)
+                .collect::<HashSet<_>>();
+
+            found += self.search(space, query, top, ef, &[row])
+                .iter()
+                .filter(|neighbor| exact.contains(&neighbor.row))
+                .count();
+
+            expected += exact.len();
+        }
+
+        if expected > 0 {
+            found as f32 / expected as f32
+        } else {
+            1.0
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut values: Vec<u32> = vec![
+            MAGIC,
+            VERSION,
+            self.params.m as u32,
+            self.params.ef_construction as u32,
+            self.entry.map(|entry| entry as u32).unwrap_or(NONE),
+            self.max_level as u32,
+            self.links.len() as u32,
+        ];
+
+        for levels in self.links.iter() {
+            values.push(levels.len() as u32);
+
+            for links in levels.iter() {
+                values.push(links.len() as u32);
+                values.extend_from_slice(links);
+            }
+        }
+
+        let mut buf = vec![0u8; values.len() * 4];
+
+        LittleEndian::write_u32_into(&values, &mut buf);
+
+        buf
+    }
+
+    pub fn from_bytes(buf: &[u8]) -> Result<Hnsw, String> {
+        let mut values = buf.chunks(4)
+            .filter(|value| value.len() == 4)
+            .map(LittleEndian::read_u32);
+
+        let mut next = || values.next().ok_or("unexpected end of an index".to_string());
+
+        if next()? != MAGIC || next()? != VERSION {
+            return Err("unsupported index format".to_string());
+        }
+
+        let params = Params {
+            m: next()? as usize,
+            ef_construction: next()? as usize,
+        };
+
+        let entry = match next()? {
+            NONE => None,
+            entry => Some(entry as usize),
+        };
+
+        let max_level = next()? as usize;
+        let count = next()? as usize;
+
+        if max_level > MAX_LEVEL {
+            return Err(format!("index level {} exceeds {}", max_level, MAX_LEVEL));
+        }
+
+        // counts are bounded by the buffer as a corrupt one may claim any size
+        let bound = buf.len() / 4;
+        let mut links = Vec::with_capacity(count.min(bound));
+
+        for _ in 0..count {
+            let level_count = next()? as usize;
+
+            if level_count == 0 || level_count > max_level + 1 {
+                return Err(format!("a row of an index has {} levels over a top level {}", level_count, max_level));
+            }
+
+            let mut levels = Vec::with_capacity(level_count);
+
+            for _ in 0..level_count {
+                let len = next()? as usize;
+                let mut level_links = Vec::with_capacity(len.min(bound));
+
+                for _ in 0..len {
+                    level_links.push(next()?);
+                }
+
+                levels.push(level_links);
+            }
+
+            links.push(levels);
+        }
+
+        match entry {
+            Some(entry) if entry >= count || links[entry].len() != max_level + 1 => {
+                return Err(format!("an entry {} of an index isn't a row of the top level", entry));
+            }
+            None if count > 0 => return Err("an index of rows has no entry".to_string()),
+            _ => {}
+        }
+
+        // a linked row should be present at the level of a link
+        for levels in links.iter() {
+            for (level, level_links) in levels.iter().enumerate() {
+                if level_links.iter().any(|&row| row as usize >= count || links[row as usize].len() <= level) {
+                    return Err(format!("an index links a missing row at level {}", level));
+                }
+            }
+        }
+
+        Ok(Hnsw {
+            params,
+            entry,
+            max_level,
+            links,
+        })
+    }
+
+    /// Loads an index of a kind if it was built for the same vectors.
+    pub fn load(conn: &DsjConnection, kind: &Kind, space: &Space) -> Option<Hnsw> {
+        let index = get_index(conn, kind)?;
+
+        if index.checksum != space.checksum() as i64 {
+            return None;
+        }
+
+        match Hnsw::from_bytes(&index.data) {
+            Ok(ref hnsw) if hnsw.links.len() != space.len() => None,
+            Ok(hnsw) => Some(hnsw),
+            Err(err) => {
+                println!("failed to read an index of '{}' {}", kind.name, err);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, conn: &DsjConnection, kind: &Kind, space: &Space) -> bool {
+        save_index(conn, &Index {
+            kind_id: kind.id,
+            m: self.params.m as i32,
+            ef_construction: self.params.ef_construction as i32,
+            checksum: space.checksum() as i64,
+            data: self.to_bytes(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::word::Word;
+
+    fn random_space(count: usize, dim: usize) -> Space {
+        let mut rng = XorShiftRng::from_seed([1, 2, 3, 4]);
+
+        Space::new(
+            (0..count).map(|id| Word { id: id as i32 + 1, ..Word::default() }).collect(),
+            (0..count).map(|_| (0..dim).map(|_| rng.gen_range(-1.0f32, 1.0)).collect()).collect(),
+        )
+    }
+
+    #[test]
+    fn test_search() {
+        let space = random_space(500, 16);
+        let hnsw = Hnsw::build(&space, Params { m: 8, ef_construction: 64 });
+
+        let recall = hnsw.recall(&space, 50, 10, 64);
+
+        assert!(recall > 0.9, "check recall {}", recall);
+
+        let query = space.vector(7).to_vec();
+        let found = hnsw.search(&space, &query, 5, 32, &[]);
+
+        assert_eq!(found.len(), 5, "check count");
+        assert_eq!(found[0].row, 7, "check the nearest is the query itself");
+        assert!(found.windows(2).all(|pair| pair[0].similarity >= pair[1].similarity), "check order");
+        assert!(hnsw.search(&space, &query, 5, 32, &[7]).iter().all(|neighbor| neighbor.row != 7), "check excluded");
+    }
+
+    #[test]
+    fn test_serialization() {
+        let space = random_space(100, 4);
+        let hnsw = Hnsw::build(&space, Params::default());
+
+        match Hnsw::from_bytes(&hnsw.to_bytes()) {
+            Ok(restored) => assert_eq!(restored, hnsw, "check restored index"),
+            Err(err) => assert!(false, "failed to restore an index with {:?}", err),
+        }
+
+        assert!(Hnsw::from_bytes(&[1u8, 2, 3, 4]).is_err(), "check wrong format");
+        assert!(Hnsw::from_bytes(&hnsw.to_bytes()[..40]).is_err(), "check truncated index");
+
+        // a corrupt value at a position of 32-bit values of a valid index
+        let corrupt = |position: usize, value: u32| {
+            let mut buf = hnsw.to_bytes();
+
+            LittleEndian::write_u32(&mut buf[position * 4..position * 4 + 4], value);
+            Hnsw::from_bytes(&buf)
+        };
+
+        assert!(corrupt(4, 100).is_err(), "check entry out of rows");
+        assert!(corrupt(5, 1000).is_err(), "check level out of bounds");
+        assert!(corrupt(6, u32::max_value()).is_err(), "check row count beyond a buffer");
+        assert!(corrupt(7, u32::max_value()).is_err(), "check level count beyond a buffer");
+        assert!(corrupt(8, u32::max_value()).is_err(), "check link count beyond a buffer");
+        assert!(corrupt(9, 100).is_err(), "check link out of rows");
+
+        let empty = Hnsw::build(&random_space(0, 4), Params::default());
+
+        assert_eq!(empty.search(&space, space.vector(0), 5, 10, &[]), vec![], "check empty index");
+    }
+}
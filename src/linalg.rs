@@ -0,0 +1,226 @@
+const SWEEPS: usize = 100;
+
+/// Sums and a sum of outer products of vectors to estimate their mean and covariance.
+pub struct Moments {
+    count: u64,
+    sum: Vec<f64>,
+    // the upper triangle of a sum of x * x^T, none without covariance
+    outer: Option<Vec<f64>>,
+}
+
+impl Moments {
+    pub fn new(dim: usize, covariance: bool) -> Moments {
+        Moments {
+            count: 0,
+            sum: vec![0.0; dim],
+            outer: if covariance { Some(vec![0.0; dim * (dim + 1) / 2]) } else { None },
+        }
+    }
+
+    pub fn dim(&self) -> usize {
+        self.sum.len()
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Adds a vector of the same dimension, returns false for another one.
+    pub fn add(&mut self, vec: &[f32]) -> bool {
+        if vec.len() != self.sum.len() {
+            return false;
+        }
+
+        for (sum, &value) in self.sum.iter_mut().zip(vec.iter()) {
+            *sum += value as f64;
+        }
+
+        if let Some(ref mut outer) = self.outer {
+            let mut index = 0;
+
+            for i in 0..vec.len() {
+                let x = vec[i] as f64;
+
+                for j in i..vec.len() {
+                    outer[index] += x * vec[j] as f64;
+                    index += 1;
+                }
+            }
+        }
+
+        self.count += 1;
+        true
+    }
+
+    pub fn mean(&self) -> Vec<f64> {
+        let count = self.count.max(1) as f64;
+
+        self.sum.iter().map(|sum| sum / count).collect()
+    }
+
+    /// A population covariance matrix.
+    pub fn covariance(&self) -> Option<Vec<Vec<f64>>> {
+        let outer = self.outer.as_ref()?;
+        let dim = self.dim();
+        let count = self.count.max(1) as f64;
+        let mean = self.mean();
+        let mut covariance = vec![vec![0.0; dim]; dim];
+        let mut index = 0;
+
+        for i in 0..dim {
+            for j in i..dim {
+                let value = outer[index] / count - mean[i] * mean[j];
+
+                covariance[i][j] = value;
+                covariance[j][i] = value;
+                index += 1;
+            }
+        }
+
+        Some(covariance)
+    }
+}
+
+/// Eigenvalues and unit eigenvectors of a symmetric matrix by cyclic Jacobi rotations,
+/// the largest eigenvalue first.
+pub fn symmetric_eigen(matrix: &[Vec<f64>]) -> (Vec<f64>, Vec<Vec<f64>>) {
+    let n = matrix.len();
+    let mut a = matrix.to_vec();
+    // columns are eigenvectors
+    let mut v = (0..n).map(|i| (0..n).map(|j| if i == j { 1.0 } else { 0.0 }).collect::<Vec<f64>>()).collect::<Vec<_>>();
+
+    let scale = a.iter().map(|row| row.iter().map(|x| x * x).sum::<f64>()).sum::<f64>().sqrt();
+
+    for _ in 0..SWEEPS {
+        let off = (0..n).map(|i| (0..n).filter(|&j| j != i).map(|j| a[i][j] * a[i][j]).sum::<f64>()).sum::<f64>().sqrt();
+
+        if off <= 1e-12 * scale.max(::std::f64::MIN_POSITIVE) {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for k in 0..n {
+                    let (akp, akq) = (a[k][p], a[k][q]);
+
+                    a[k][p] = c * akp - s * akq;
+                    a[k][q] = s * akp + c * akq;
+                }
+
+                for k in 0..n {
+                    let (apk, aqk) = (a[p][k], a[q][k]);
+
+                    a[p][k] = c * apk - s * aqk;
+                    a[q][k] = s * apk + c * aqk;
+                }
+
+                for k in 0..n {
+                    let (vkp, vkq) = (v[k][p], v[k][q]);
+
+                    v[k][p] = c * vkp - s * vkq;
+                    v[k][q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+
+    let mut order = (0..n).collect::<Vec<_>>();
+
+    order.sort_by(|&i, &j| a[j][j].partial_cmp(&a[i][i]).unwrap_or(::std::cmp::Ordering::Equal));
+
+    let values = order.iter().map(|&i| a[i][i]).collect();
+    let vectors = order.iter().map(|&i| (0..n).map(|k| v[k][i]).collect()).collect();
+
+    (values, vectors)
+}
+
+/// The closest orthogonal matrix to a square one, `U V^T` of its SVD `U S V^T`, as `M (M^T M)^(-1/2)`.
+/// A rank-deficient matrix has no unique one and gives None.
+pub fn orthogonal_polar(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let gram = (0..n).map(|i| (0..n).map(|j| (0..n).map(|k| matrix[k][i] * matrix[k][j]).sum()).collect()).collect::<Vec<Vec<f64>>>();
+    let (values, vectors) = symmetric_eigen(&gram);
+    let largest = values.first().cloned().unwrap_or_default();
+
+    if values.iter().any(|&value| value <= 1e-12 * largest) || largest <= 0.0 {
+        return None;
+    }
+
+    let mut inverse_sqrt = vec![vec![0.0; n]; n];
+
+    for (value, vector) in values.iter().zip(vectors.iter()) {
+        for i in 0..n {
+            for j in 0..n {
+                inverse_sqrt[i][j] += vector[i] * vector[j] / value.sqrt();
+            }
+        }
+    }
+
+    Some(matrix.iter()
+        .map(|row| (0..n).map(|j| row.iter().zip(inverse_sqrt.iter()).map(|(a, inverse)| a * inverse[j]).sum()).collect())
+        .collect())
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[test]
+    fn test_moments() {
+        let mut moments = Moments::new(2, true);
+
+        assert!(moments.add(&[1.0, 2.0]) && moments.add(&[3.0, 2.0]), "check add");
+        assert!(!moments.add(&[1.0]), "check dimension");
+        assert_eq!((moments.count(), moments.mean()), (2, vec![2.0, 2.0]), "check mean");
+        assert_eq!(moments.covariance(), Some(vec![vec![1.0, 0.0], vec![0.0, 0.0]]), "check covariance");
+        assert_eq!(Moments::new(2, false).covariance(), None, "check without covariance");
+    }
+
+    #[test]
+    fn test_symmetric_eigen() {
+        let matrix = vec![
+            vec![2.0, 1.0, 0.0],
+            vec![1.0, 2.0, 0.0],
+            vec![0.0, 0.0, 5.0],
+        ];
+
+        let (values, vectors) = symmetric_eigen(&matrix);
+
+        assert!(values.iter().zip([5.0, 3.0, 1.0].iter()).all(|(a, b)| (a - b).abs() < 1e-9), "check values {:?}", values);
+        assert!((vectors[0][2].abs() - 1.0).abs() < 1e-9, "check first vector {:?}", vectors[0]);
+        assert!((vectors[1][0] - vectors[1][1]).abs() < 1e-9 && (vectors[1][0].abs() - 0.5f64.sqrt()).abs() < 1e-9, "check second vector {:?}", vectors[1]);
+
+        for (value, vector) in values.iter().zip(vectors.iter()) {
+            let product = matrix.iter().map(|row| row.iter().zip(vector.iter()).map(|(a, b)| a * b).sum::<f64>()).collect::<Vec<_>>();
+
+            assert!(product.iter().zip(vector.iter()).all(|(a, b)| (a - value * b).abs() < 1e-9), "check eigenvector {:?}", vector);
+        }
+    }
+
+    #[test]
+    fn test_orthogonal_polar() {
+        // a rotation by 30 degrees scaled along axes
+        let (cos, sin) = (0.75f64.sqrt(), 0.5);
+        let matrix = vec![vec![cos * 2.0, -sin * 3.0], vec![sin * 2.0, cos * 3.0]];
+
+        let orthogonal = orthogonal_polar(&matrix).unwrap_or_default();
+        let expected = [[cos, -sin], [sin, cos]];
+
+        assert_eq!(orthogonal.len(), 2, "check size");
+
+        for i in 0..2 {
+            assert!(orthogonal[i].iter().zip(expected[i].iter()).all(|(a, b)| (a - b).abs() < 1e-9), "check rotation {:?}", orthogonal);
+        }
+
+        assert_eq!(orthogonal_polar(&[vec![1.0, 2.0], vec![2.0, 4.0]]), None, "check rank-deficient");
+    }
+}
@@ -0,0 +1,405 @@
+use std::collections::HashMap;
+use unicode_normalization::UnicodeNormalization;
+
+use db::connection::DsjConnection;
+use db::models::kind::Kind;
+use db::models::property::get_property;
+use db::models::subword::get_subwords;
+use db::models::vector::lookup_vector;
+use db::models::word::{Word, complete_words};
+use subword::{BUCKET, MAXN, MINN, NGrams};
+use transform::Pipeline;
+
+pub const STRATEGIES: &str = "exact,lowercase,nfkc,yo,strip,subwords";
+
+// candidates of a word with a part of speech suffix, "слово_NOUN" for "слово"
+const TAGGED: i64 = 20;
+
+// Universal Dependencies part of speech tags of RusVectores models
+const TAGS: [&str; 17] = [
+    "ADJ", "ADP", "ADV", "AUX", "CCONJ", "DET", "INTJ", "NOUN", "NUM",
+    "PART", "PRON", "PROPN", "PUNCT", "SCONJ", "SYM", "VERB", "X",
+];
+
+/// A way to find a word, strategies of a chain are tried in order and
+/// every one looks up the word as transformed by the previous ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    Exact,
+    // "Москва" as "москва" or "москва" as "Москва"
+    Lowercase,
+    Nfkc,
+    // "ёлка" as "елка"
+    Yo,
+    // trailing punctuation and part of speech suffixes, "слово_NOUN" as "слово" and back
+    Strip,
+    // composed from n-gram buckets of an unknown word
+    Subwords,
+}
+
+impl Strategy {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Strategy::Exact => "exact",
+            Strategy::Lowercase => "lowercase",
+            Strategy::Nfkc => "nfkc",
+            Strategy::Yo => "yo",
+            Strategy::Strip => "strip",
+            Strategy::Subwords => "subwords",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Strategy> {
+        match name {
+            "exact" => Some(Strategy::Exact),
+            "lowercase" => Some(Strategy::Lowercase),
+            "nfkc" => Some(Strategy::Nfkc),
+            "yo" => Some(Strategy::Yo),
+            "strip" => Some(Strategy::Strip),
+            "subwords" => Some(Strategy::Subwords),
+            _ => None,
+        }
+    }
+
+    /// A chain of comma separated names, "exact,lowercase".
+    pub fn parse_chain(names: &str) -> Result<Vec<Strategy>, String> {
+        let chain = names.split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(|name| Strategy::parse(name)
+                .ok_or_else(|| format!("unknown lookup strategy '{}', expected some of {}", name, STRATEGIES)))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if chain.is_empty() {
+            return Err("empty chain of lookup strategies".to_string());
+        }
+
+        Ok(chain)
+    }
+}
+
+fn capitalize(w: &str) -> String {
+    let mut chars = w.chars();
+
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+fn fold_yo(w: &str) -> String {
+    w.chars()
+        .map(|c| match c {
+            'ё' => 'е',
+            'Ё' => 'Е',
+            c => c,
+        })
+        .collect()
+}
+
+// a lowercase strategy runs before stripping and lowers a tag too
+fn is_tag(tag: &str) -> bool {
+    TAGS.contains(&tag.to_uppercase().as_str())
+}
+
+fn strip(w: &str) -> String {
+    let w = w.trim_matches(|c: char| !c.is_alphanumeric());
+
+    match w.rfind('_') {
+        Some(pos) if pos > 0 && is_tag(&w[pos + 1..]) => w[..pos].to_string(),
+        _ => w.to_string(),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Found {
+    pub word: Option<Word>,
+    pub vec: Vec<f32>,
+    pub matched: Strategy,
+}
+
+impl Found {
+    pub fn word_id(&self) -> Option<i32> {
+        self.word.as_ref().map(|word| word.id)
+    }
+
+    pub fn lang(&self) -> &str {
+        self.word.as_ref().map(|word| word.lang.as_str()).unwrap_or_default()
+    }
+
+    /// A stored word, none for a composed vector.
+    pub fn stored(&self) -> Option<&str> {
+        self.word.as_ref().map(|word| word.word.as_str())
+    }
+}
+
+/// Looks words up in a kind by a chain of strategies, unknown words of a kind
+/// with imported fastText n-gram buckets get an average of their n-gram rows
+/// passed through transforms of the kind.
+pub struct Lookup {
+    kind: Kind,
+    ngrams: Option<NGrams>,
+    strategies: Vec<Strategy>,
+    transform: Option<Pipeline>,
+}
+
+impl Lookup {
+    pub fn new(conn: &DsjConnection, kind: &Kind, strategies: &[Strategy]) -> Lookup {
+        let property = |name| get_property(conn, kind, name).and_then(|value| value.parse::<usize>().ok());
+
+        let ngrams = match (property(MINN), property(MAXN), property(BUCKET)) {
+            (Some(minn), Some(maxn), Some(bucket)) => Some(NGrams {
+                minn,
+                maxn,
+                bucket: bucket as u32,
+            }),
+            _ => None,
+        };
+
+        Lookup {
+            kind: kind.clone(),
+            ngrams,
+            strategies: strategies.to_vec(),
+            transform: Pipeline::load(conn, kind),
+        }
+    }
+
+    pub fn kind(&self) -> &Kind {
+        &self.kind
+    }
+
+    pub fn strategies(&self) -> &[Strategy] {
+        &self.strategies
+    }
+
+    pub fn find(&self, conn: &DsjConnection, w: &str, lang: Option<&str>) -> Option<Found> {
+        let mut query = w.to_string();
+
+        for &strategy in self.strategies.iter() {
+            let found = match strategy {
+                Strategy::Exact => self.exact(conn, &query, lang),
+                Strategy::Lowercase => {
+                    let lowercase = query.to_lowercase();
+                    let capitalized = capitalize(&lowercase);
+
+                    let found = self.changed(conn, &query, &lowercase, lang)
+                        .or_else(|| self.changed(conn, &query, &capitalized, lang));
+
+                    query = lowercase;
+                    found
+                }
+                Strategy::Nfkc => {
+                    let normalized = query.nfkc().collect();
+
+                    self.transform(conn, &mut query, normalized, lang)
+                }
+                Strategy::Yo => {
+                    let folded = fold_yo(&query);
+
+                    self.transform(conn, &mut query, folded, lang)
+                }
+                Strategy::Strip => {
+                    let stripped = strip(&query);
+
+                    self.transform(conn, &mut query, stripped, lang)
+                        .or_else(|| self.tagged(conn, &query, lang))
+                }
+                // fastText composes n-grams of a word as it is
+                Strategy::Subwords => self.compose(conn, w)
+                    .map(|vec| (None, vec)),
+            };
+
+            if let Some((word, vec)) = found {
+                return Some(Found {
+                    word,
+                    vec,
+                    matched: strategy,
+                });
+            }
+        }
+
+        None
+    }
+
+    fn exact(&self, conn: &DsjConnection, w: &str, lang: Option<&str>) -> Option<(Option<Word>, Vec<f32>)> {
+        lookup_vector(conn, w, lang, &self.kind)
+            .map(|(word, vec)| (Some(word), vec))
+    }
+
+    // a word unchanged by a strategy was already looked up
+    fn changed(&self, conn: &DsjConnection, query: &str, w: &str, lang: Option<&str>) -> Option<(Option<Word>, Vec<f32>)> {
+        if query == w {
+            None
+        } else {
+            self.exact(conn, w, lang)
+        }
+    }
+
+    fn transform(&self, conn: &DsjConnection, query: &mut String, w: String, lang: Option<&str>) -> Option<(Option<Word>, Vec<f32>)> {
+        let found = self.changed(conn, query, &w, lang);
+
+        *query = w;
+        found
+    }
+
+    fn tagged(&self, conn: &DsjConnection, w: &str, lang: Option<&str>) -> Option<(Option<Word>, Vec<f32>)> {
+        let prefix = format!("{}_", w);
+
+        complete_words(conn, &prefix, TAGGED)?
+            .into_iter()
+            .filter(|candidate| is_tag(&candidate[prefix.len()..]))
+            .filter_map(|candidate| self.exact(conn, &candidate, lang))
+            .next()
+    }
+
+    fn compose(&self, conn: &DsjConnection, w: &str) -> Option<Vec<f32>> {
+        let buckets = self.ngrams?.buckets(w)
+            .into_iter()
+            .map(|bucket| bucket as i32)
+            .collect::<Vec<_>>();
+
+        if buckets.is_empty() {
+            return None;
+        }
+
+        let rows = get_subwords(conn, &self.kind, &buckets)?
+            .into_iter()
+            .map(|subword| (subword.bucket, subword.points()))
+            .collect::<HashMap<_, _>>();
+
+        let mut vec: Vec<f32> = Vec::new();
+        let mut count = 0;
+
+        // a repeated n-gram counts every time as in fastText
+        for row in buckets.iter().filter_map(|bucket| rows.get(bucket)) {
+            if vec.is_empty() {
+                vec = vec![0f32; row.len()];
+            }
+
+            for (point, value) in vec.iter_mut().zip(row.iter()) {
+                *point += *value;
+            }
+
+            count += 1;
+        }
+
+        if count == 0 {
+            return None;
+        }
+
+        for point in vec.iter_mut() {
+            *point /= count as f32;
+        }
+
+        // stored words are transformed at import, n-gram rows are stored as is
+        if let Some(ref transform) = self.transform {
+            transform.apply(&mut vec).ok()?;
+        }
+
+        Some(vec)
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::testing::test_connection;
+    use db::models::kind::get_kind;
+    use db::models::property::set_property;
+    use db::models::subword::{Subword, add_subwords};
+    use space::norm;
+    use db::models::vector::{Vector, add_vectors};
+    use db::models::word::create_word;
+
+    #[test]
+    fn test_parse_chain() {
+        assert_eq!(Strategy::parse_chain("exact, lowercase,subwords"), Ok(vec![Strategy::Exact, Strategy::Lowercase, Strategy::Subwords]), "check chain");
+        assert_eq!(Strategy::parse_chain(STRATEGIES).map(|chain| chain.len()), Ok(6), "check all strategies");
+        assert!(Strategy::parse_chain("exact,stem").is_err(), "check unknown strategy");
+        assert!(Strategy::parse_chain(",").is_err(), "check empty chain");
+    }
+
+    #[test]
+    fn test_strategies() {
+        let conn = test_connection();
+        let kind = get_kind(&conn, "fasttext").unwrap_or_default();
+
+        for &w in ["елка", "йод", "слово_NOUN"].iter() {
+            if let Some(word) = create_word(&conn, w, "ru") {
+                add_vectors(&conn, &Vector::from_vec(&word, &kind, &[1.0, 0.0, 0.0, 0.0]));
+            }
+        }
+
+        let chain = Strategy::parse_chain(STRATEGIES).unwrap_or_default();
+        let lookup = Lookup::new(&conn, &kind, &chain);
+
+        let find = |w: &str| lookup.find(&conn, w, None)
+            .map(|found| (found.matched, found.stored().unwrap_or_default().to_string()));
+
+        assert_eq!(find("король"), Some((Strategy::Exact, "король".to_string())), "check exact");
+        assert_eq!(find("КОРОЛЬ"), Some((Strategy::Lowercase, "король".to_string())), "check lowercase");
+        assert_eq!(find("и\u{306}од"), Some((Strategy::Nfkc, "йод".to_string())), "check nfkc");
+        assert_eq!(find("Ёлка"), Some((Strategy::Yo, "елка".to_string())), "check lowercase and yo");
+        assert_eq!(find("«яблоко»,"), Some((Strategy::Strip, "яблоко".to_string())), "check punctuation");
+        assert_eq!(find("яблоко_NOUN"), Some((Strategy::Strip, "яблоко".to_string())), "check stripped tag");
+        assert_eq!(find("слово"), Some((Strategy::Strip, "слово_NOUN".to_string())), "check tagged");
+        assert_eq!(find("груша"), None, "check missing");
+
+        let lookup = Lookup::new(&conn, &kind, &[Strategy::Exact]);
+
+        assert_eq!(lookup.find(&conn, "КОРОЛЬ", None), None, "check exact only");
+    }
+
+    #[test]
+    fn test_subwords() {
+        let conn = test_connection();
+        let kind = get_kind(&conn, "fasttext").unwrap_or_default();
+        let chain = [Strategy::Exact, Strategy::Subwords];
+
+        assert_eq!(Lookup::new(&conn, &kind, &chain).find(&conn, "королём", None), None, "check closed vocabulary");
+
+        let ngrams = NGrams {
+            minn: 3,
+            maxn: 3,
+            bucket: 16,
+        };
+
+        set_property(&conn, &kind, MINN, "3");
+        set_property(&conn, &kind, MAXN, "3");
+        set_property(&conn, &kind, BUCKET, "16");
+
+        let subwords = (0..ngrams.bucket as i32)
+            .map(|bucket| Subword::new(&kind, bucket, &[bucket as f32, 1.0]))
+            .collect::<Vec<_>>();
+
+        add_subwords(&conn, &subwords);
+
+        let lookup = Lookup::new(&conn, &kind, &chain);
+
+        match lookup.find(&conn, "король", Some("ru")) {
+            Some(found) => assert_eq!((found.matched, found.lang(), &found.vec), (Strategy::Exact, "ru", &vec![0.9f32, 0.8, 0.1, 0.0]), "check exact"),
+            None => assert!(false, "failed to find an exact word"),
+        }
+
+        let buckets = ngrams.buckets("королём");
+        let expected = buckets.iter().map(|&bucket| bucket as f32).sum::<f32>() / buckets.len() as f32;
+
+        match lookup.find(&conn, "королём", Some("ru")) {
+            Some(found) => {
+                assert_eq!((found.matched, found.word_id()), (Strategy::Subwords, None), "check composed");
+                assert!((found.vec[0] - expected).abs() < 1e-5 && found.vec[1] == 1.0, "check average {:?}", found.vec);
+            }
+            None => assert!(false, "failed to compose a vector"),
+        }
+
+        match Pipeline::parse("l2") {
+            Ok(pipeline) => assert!(pipeline.save(&conn, &kind), "check save"),
+            Err(err) => assert!(false, "failed to parse a pipeline: {}", err),
+        }
+
+        match Lookup::new(&conn, &kind, &chain).find(&conn, "королём", Some("ru")) {
+            Some(found) => assert!((norm(&found.vec) - 1.0).abs() < 1e-5, "check transformed {:?}", found.vec),
+            None => assert!(false, "failed to compose a vector"),
+        }
+    }
+}
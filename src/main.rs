@@ -10,45 +10,190 @@ extern crate diesel_infer_schema;
 #[macro_use]
 extern crate diesel_migrations;
 
+extern crate byteorder;
+#[macro_use]
 extern crate clap;
 extern crate indicatif;
+extern crate libc;
+extern crate libsqlite3_sys;
+extern crate rayon;
+extern crate regex;
+extern crate rustyline;
+#[macro_use]
+extern crate serde_json;
 extern crate test;
+extern crate unicode_normalization;
 
-#[cfg(test)]
 extern crate rand;
 
+mod analogy;
 mod args;
+mod command;
 mod converter;
 mod db;
+mod embed;
+mod eval;
+mod filter;
+mod half;
+mod hnsw;
 mod io;
+mod linalg;
+mod lookup;
+mod npy;
+mod pq;
 mod progressbar;
+mod quantize;
+mod server;
+mod shell;
+mod space;
+mod subword;
+mod transform;
+mod translate;
+mod wmd;
 mod wordvector;
 
 #[cfg(feature = "dumb")]
 mod data;
 
+use std::process;
 use std::rc::Rc;
 use args::Args;
 use converter::Converter;
-use progressbar::Progress;
+use filter::Filter;
+use hnsw::Params;
+use progressbar::{Progress, ProgressSignal};
+use quantize::{Precision, Quantizer};
+use transform::Pipeline;
 use wordvector::dataiterator::DataIterator;
-use wordvector::VectorFile;
+use wordvector::Source;
+
+fn fit_process(pipeline: &mut Pipeline, signal: &Rc<ProgressSignal>, source: &Source, filter: &Option<Rc<Filter>>) -> Result<(), String> {
+    while pipeline.passes() > 0 {
+        let mut data_iter = match DataIterator::new(signal.clone(), source) {
+            Some(data_iter) => data_iter,
+            None => return Err("failed to reopen a source to fit transforms".to_string()),
+        };
+
+        if let Some(ref filter) = *filter {
+            data_iter.filter(filter.clone());
+        }
+
+        signal.start(&format!("{}: fitting {}", data_iter.kind(), pipeline.name()), data_iter.max());
+
+        let count = pipeline.fit(data_iter.iter().map(|record| record.vec))?;
+
+        signal.finish(&format!("{}: fitted by {} vectors", data_iter.kind(), count));
+    }
+
+    Ok(())
+}
+
+fn quantize_process(quantizer: &mut Quantizer, signal: &Rc<ProgressSignal>, source: &Source, filter: &Option<Rc<Filter>>, pipeline: &Option<Pipeline>) -> Result<(), String> {
+    let mut data_iter = match DataIterator::new(signal.clone(), source) {
+        Some(data_iter) => data_iter,
+        None => return Err("failed to reopen a source to fit quantization".to_string()),
+    };
+
+    if let Some(ref filter) = *filter {
+        data_iter.filter(filter.clone());
+    }
 
-fn convert_process(converter: Converter, vector_files: &[VectorFile]) {
+    if let Some(ref pipeline) = *pipeline {
+        data_iter.transform(pipeline.clone());
+    }
+
+    signal.start(&format!("{}: fitting {}", data_iter.kind(), quantizer.precision().name()), data_iter.max());
+
+    let count = quantizer.fit(data_iter.iter().map(|record| record.vec))?;
+
+    signal.finish(&format!("{}: fitted by {} vectors", data_iter.kind(), count));
+
+    Ok(())
+}
+
+fn convert_process(converter: Converter, arg: &Args) {
     converter.prepare();
 
+    let sources = arg.sources().unwrap_or_default();
+
+    let pipeline = match arg.transform().map(Pipeline::parse) {
+        Some(Ok(pipeline)) => Some(pipeline),
+        Some(Err(err)) => {
+            println!("{}", err);
+            return;
+        }
+        None => None,
+    };
+    let passes = pipeline.as_ref().map(|pipeline| pipeline.passes()).unwrap_or(0);
+    let quantizer = match arg.precision() {
+        Precision::F32 => None,
+        precision => Some(Quantizer::new(precision)),
+    };
+    let passes = passes + quantizer.as_ref().map(|quantizer| if quantizer.is_fitted() { 0 } else { 1 }).unwrap_or(0);
+    let filter = if arg.filter().is_empty() { None } else { Some(Rc::new(arg.filter().clone())) };
+
     let progress_signal = Rc::new(Progress::start());
-    let data_iterators = DataIterator::make_vec(progress_signal.clone(), vector_files);
+    let data_iterators = sources.iter()
+        .filter_map(|source| DataIterator::new(progress_signal.clone(), source).map(|data_iter| (source, data_iter)))
+        .collect::<Vec<_>>();
 
-    progress_signal.init(data_iterators.len() as u64);
+    progress_signal.init((data_iterators.len() * (passes + 1)) as u64);
 
     data_iterators.into_iter()
-        .for_each(|mut data_iter| {
-            progress_signal.start(data_iter.kind(), data_iter.max());
+        .for_each(|(source, mut data_iter)| {
+            if let Some(ref filter) = filter {
+                data_iter.filter(filter.clone());
+            }
+
+            // fitted for every source as kinds are transformed independently
+            let mut pipeline = pipeline.clone();
+
+            if let Some(ref mut pipeline) = pipeline {
+                if let Err(err) = fit_process(pipeline, &progress_signal, source, &filter) {
+                    println!("{}: {}", data_iter.kind(), err);
+                    return;
+                }
+            }
+
+            // ranges of quantization are fitted by transformed vectors
+            let mut quantizer = quantizer.clone();
+
+            if let Some(ref mut quantizer) = quantizer {
+                if !quantizer.is_fitted() {
+                    if let Err(err) = quantize_process(quantizer, &progress_signal, source, &filter, &pipeline) {
+                        println!("{}: {}", data_iter.kind(), err);
+                        return;
+                    }
+                }
+            }
+
+            if let Some(pipeline) = pipeline {
+                data_iter.transform(pipeline);
+            }
 
-            converter.convert(&mut data_iter);
+            progress_signal.start(data_iter.kind(), data_iter.max());
 
-            progress_signal.finish(&format!("{}: done", data_iter.kind()));
+            let converted = converter.convert(&mut data_iter, arg.duplicates(), quantizer.as_ref());
+            let summary = match converted {
+                Ok(ref imported) => {
+                    let filtered = data_iter.filtered();
+
+                    if !filtered.is_empty() {
+                        format!("{}: done, {}, {}", data_iter.kind(), imported.summary(), filtered.summary())
+                    } else {
+                        format!("{}: done, {}", data_iter.kind(), imported.summary())
+                    }
+                }
+                Err(ref err) => format!("{}: {}", data_iter.kind(), err),
+            };
+
+            if arg.index() && converted.is_ok() {
+                if let Err(err) = converter.index(data_iter.kind(), Params::default()) {
+                    println!("{}", err);
+                }
+            }
+
+            progress_signal.finish(&summary);
         });
 }
 
@@ -58,11 +203,15 @@ fn main() {
 
     let mut arg = Args::default();
 
+    if let Some((name, matches)) = arg.sub_command() {
+        process::exit(command::run(name, matches));
+    }
+
     if arg.is_incomplete() {
         arg.print_help();
     } else {
         match Converter::new(arg.database_uri().unwrap()) {
-            Ok(converter) => convert_process(converter, arg.file_path().unwrap()),
+            Ok(converter) => convert_process(converter, &arg),
             Err(err) => println!("failed to initialise a converter with {:?}", err),
         }
     }
@@ -0,0 +1,68 @@
+use std::io::{self, Write};
+use byteorder::{LittleEndian, WriteBytesExt};
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+// numpy aligns the data of a file to 64 bytes
+const ALIGN: usize = 64;
+
+fn header(rows: usize, cols: usize) -> Vec<u8> {
+    let mut dict = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': ({}, {}), }}", rows, cols);
+
+    // magic, a version 1.0 and a length of a header
+    let prefix = MAGIC.len() + 4;
+
+    while (prefix + dict.len() + 1) % ALIGN != 0 {
+        dict.push(' ');
+    }
+
+    dict.push('\n');
+
+    let mut header = Vec::with_capacity(prefix + dict.len());
+
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&[1, 0]);
+    header.extend_from_slice(&[(dict.len() & 0xff) as u8, (dict.len() >> 8) as u8]);
+    header.extend_from_slice(dict.as_bytes());
+    header
+}
+
+/// Writes rows of the same length as a C ordered float32 matrix in the NPY format.
+pub fn write_f32<W: Write>(out: &mut W, rows: &[Vec<f32>], cols: usize) -> io::Result<()> {
+    out.write_all(&header(rows.len(), cols))?;
+
+    for row in rows.iter() {
+        if row.len() != cols {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("expected a row of {} values, got {}", cols, row.len())));
+        }
+
+        for &value in row.iter() {
+            out.write_f32::<LittleEndian>(value)?;
+        }
+    }
+
+    out.flush()
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[test]
+    fn test_write() {
+        let mut out = Vec::new();
+
+        assert!(write_f32(&mut out, &[vec![1.0, 2.0, 3.0], vec![0.5, 0.0, -1.0]], 3).is_ok(), "check write");
+
+        let length = out[8] as usize + ((out[9] as usize) << 8);
+        let dict = String::from_utf8_lossy(&out[10..10 + length]).to_string();
+
+        assert_eq!(&out[..8], b"\x93NUMPY\x01\x00", "check magic");
+        assert_eq!((10 + length) % 64, 0, "check alignment");
+        assert!(dict.starts_with("{'descr': '<f4', 'fortran_order': False, 'shape': (2, 3), }") && dict.ends_with(" \n"), "check header {:?}", dict);
+        assert_eq!(out.len(), 10 + length + 2 * 3 * 4, "check data size");
+        assert_eq!(&out[10 + length..10 + length + 4], &[0, 0, 0x80, 0x3f], "check first value");
+
+        assert!(write_f32(&mut Vec::new(), &[vec![1.0]], 2).is_err(), "check row length");
+    }
+}
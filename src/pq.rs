@@ -0,0 +1,384 @@
+use std::collections::HashMap;
+use byteorder::{ByteOrder, LittleEndian};
+use rayon::prelude::*;
+
+use db::connection::DsjConnection;
+use db::models::kind::Kind;
+use db::models::pq::{Codebook, add_codebooks, kind_codebooks, kind_codes};
+use db::models::word::Word;
+use space::{Neighbor, dot, kind_words, norm, rank};
+
+// codes of a word are bytes
+pub const MAX_CENTROIDS: usize = 256;
+
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| (x - y) * (x - y)).sum()
+}
+
+// the closest of centroids of `dim` points
+fn nearest(centroids: &[f32], dim: usize, x: &[f32]) -> usize {
+    centroids.chunks(dim)
+        .map(|centroid| distance(centroid, x))
+        .enumerate()
+        .fold((0, ::std::f32::INFINITY), |best, (i, d)| if d < best.1 { (i, d) } else { best })
+        .0
+}
+
+// Lloyd's k-means started from evenly spaced points
+fn kmeans(points: &[&[f32]], count: usize, iterations: usize) -> Vec<f32> {
+    let dim = points[0].len();
+    let mut centroids = (0..count)
+        .flat_map(|i| points[i * points.len() / count].iter().cloned())
+        .collect::<Vec<_>>();
+    let mut assigned = vec![usize::max_value(); points.len()];
+
+    for _ in 0..iterations {
+        let mut changed = false;
+
+        for (i, point) in points.iter().enumerate() {
+            let cluster = nearest(&centroids, dim, point);
+
+            changed |= assigned[i] != cluster;
+            assigned[i] = cluster;
+        }
+
+        if !changed {
+            break;
+        }
+
+        let mut sums = vec![0f64; count * dim];
+        let mut sizes = vec![0usize; count];
+
+        for (point, &cluster) in points.iter().zip(assigned.iter()) {
+            for (sum, &value) in sums[cluster * dim..(cluster + 1) * dim].iter_mut().zip(point.iter()) {
+                *sum += value as f64;
+            }
+
+            sizes[cluster] += 1;
+        }
+
+        // an empty cluster keeps its centroid
+        for (cluster, &size) in sizes.iter().enumerate().filter(|&(_, &size)| size > 0) {
+            for j in 0..dim {
+                centroids[cluster * dim + j] = (sums[cluster * dim + j] / size as f64) as f32;
+            }
+        }
+    }
+
+    centroids
+}
+
+/// Splits vectors to subspaces and codes every part by the closest centroid of its subspace.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProductQuantizer {
+    // bounds of subspaces, the last one is a dimension of vectors
+    bounds: Vec<usize>,
+    // centroids of subspaces, rows of a subspace dimension
+    codebooks: Vec<Vec<f32>>,
+}
+
+impl ProductQuantizer {
+    /// Trains `centroids` centroids for each of `subspaces` nearly equal parts of vectors by k-means.
+    pub fn train(vectors: &[&[f32]], subspaces: usize, centroids: usize, iterations: usize) -> Result<ProductQuantizer, String> {
+        let dim = match vectors.first() {
+            Some(vec) => vec.len(),
+            None => return Err("no vectors to train codebooks".to_string()),
+        };
+
+        if subspaces == 0 || subspaces > dim {
+            return Err(format!("subspaces should be from 1 to {}", dim));
+        }
+
+        if centroids == 0 || centroids > MAX_CENTROIDS.min(vectors.len()) {
+            return Err(format!("centroids should be from 1 to {}", MAX_CENTROIDS.min(vectors.len())));
+        }
+
+        if vectors.iter().any(|vec| vec.len() != dim) {
+            return Err("vectors should have the same dimension".to_string());
+        }
+
+        let bounds = (0..subspaces + 1).map(|i| i * dim / subspaces).collect::<Vec<_>>();
+
+        let codebooks = (0..subspaces).into_par_iter()
+            .map(|m| {
+                let points = vectors.iter().map(|vec| &vec[bounds[m]..bounds[m + 1]]).collect::<Vec<_>>();
+
+                kmeans(&points, centroids, iterations)
+            })
+            .collect();
+
+        Ok(ProductQuantizer {
+            bounds,
+            codebooks,
+        })
+    }
+
+    pub fn dim(&self) -> usize {
+        self.bounds.last().cloned().unwrap_or_default()
+    }
+
+    pub fn subspaces(&self) -> usize {
+        self.codebooks.len()
+    }
+
+    fn part<'a>(&self, vec: &'a [f32], m: usize) -> &'a [f32] {
+        &vec[self.bounds[m]..self.bounds[m + 1]]
+    }
+
+    pub fn encode(&self, vec: &[f32]) -> Vec<u8> {
+        self.codebooks.iter().enumerate()
+            .map(|(m, codebook)| nearest(codebook, self.bounds[m + 1] - self.bounds[m], self.part(vec, m)) as u8)
+            .collect()
+    }
+
+    /// A vector of centroids of codes.
+    /// A vector of centroids, empty when a code is out of its codebook.
+    pub fn decode(&self, codes: &[u8]) -> Vec<f32> {
+        let mut vec = Vec::with_capacity(self.dim());
+
+        for (m, (&code, codebook)) in codes.iter().zip(self.codebooks.iter()).enumerate() {
+            let dim = self.bounds[m + 1] - self.bounds[m];
+
+            match codebook.get(code as usize * dim..(code as usize + 1) * dim) {
+                Some(centroid) => vec.extend_from_slice(centroid),
+                None => return Vec::new(),
+            }
+        }
+
+        vec
+    }
+
+    /// Dot products of parts of a query with centroids of their subspaces,
+    /// a dot product with a coded vector is a sum of them (asymmetric distance).
+    pub fn table(&self, query: &[f32]) -> Vec<Vec<f32>> {
+        self.codebooks.iter().enumerate()
+            .map(|(m, codebook)| {
+                let part = self.part(query, m);
+
+                codebook.chunks(part.len().max(1)).map(|centroid| dot(part, centroid)).collect()
+            })
+            .collect()
+    }
+
+    pub fn score(table: &[Vec<f32>], codes: &[u8]) -> f32 {
+        table.iter().zip(codes.iter()).map(|(products, &code)| products[code as usize]).sum()
+    }
+
+    pub fn save(&self, conn: &DsjConnection, kind: &Kind) -> bool {
+        let codebooks = self.codebooks.iter().enumerate()
+            .map(|(m, codebook)| {
+                let mut centroids = vec![0u8; codebook.len() * 4];
+
+                LittleEndian::write_f32_into(codebook, &mut centroids);
+
+                Codebook {
+                    kind_id: kind.id,
+                    subspace: m as i32,
+                    dim: (self.bounds[m + 1] - self.bounds[m]) as i32,
+                    centroids,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        add_codebooks(conn, &codebooks) == codebooks.len()
+    }
+
+    /// Codebooks of a product quantized kind.
+    pub fn load(conn: &DsjConnection, kind: &Kind) -> Option<ProductQuantizer> {
+        ProductQuantizer::from_codebooks(kind_codebooks(conn, kind)?)
+    }
+
+    /// A quantizer of codebooks in the order of subspaces, none without them
+    /// or when subspaces aren't numbered from 0 or centroids don't fit them.
+    pub fn from_codebooks(codebooks: Vec<Codebook>) -> Option<ProductQuantizer> {
+        if codebooks.is_empty() {
+            return None;
+        }
+
+        let mut bounds = vec![0];
+
+        for (m, codebook) in codebooks.iter().enumerate() {
+            let dim = codebook.dim as usize * 4;
+
+            if codebook.subspace != m as i32 || codebook.dim <= 0 || codebook.centroids.is_empty()
+                || codebook.centroids.len() % dim != 0 || codebook.centroids.len() / dim > MAX_CENTROIDS {
+                return None;
+            }
+
+            let last = bounds[bounds.len() - 1];
+
+            bounds.push(last + codebook.dim as usize);
+        }
+
+        let codebooks = codebooks.into_iter()
+            .map(|codebook| {
+                let mut centroids = vec![0f32; codebook.centroids.len() / 4];
+
+                LittleEndian::read_f32_into(&codebook.centroids, &mut centroids);
+                centroids
+            })
+            .collect();
+
+        Some(ProductQuantizer {
+            bounds,
+            codebooks,
+        })
+    }
+}
+
+/// Codes of a product quantized kind loaded to memory with norms of their vectors.
+pub struct CompressedSpace {
+    words: Vec<Word>,
+    rows: HashMap<i32, usize>,
+    quantizer: ProductQuantizer,
+    codes: Vec<u8>,
+    norms: Vec<f32>,
+}
+
+impl CompressedSpace {
+    pub fn new(words: Vec<Word>, quantizer: ProductQuantizer, codes: Vec<Vec<u8>>) -> CompressedSpace {
+        let subspaces = quantizer.subspaces();
+
+        let mut space = CompressedSpace {
+            words: Vec::with_capacity(words.len()),
+            rows: HashMap::with_capacity(words.len()),
+            quantizer,
+            codes: Vec::with_capacity(words.len() * subspaces),
+            norms: Vec::with_capacity(words.len()),
+        };
+
+        for (word, codes) in words.into_iter().zip(codes.into_iter()) {
+            if codes.len() != subspaces {
+                println!("skip '{}' with {} codes instead of {}", word.word, codes.len(), subspaces);
+                continue;
+            }
+
+            space.rows.insert(word.id, space.words.len());
+            space.norms.push(norm(&space.quantizer.decode(&codes)));
+            space.codes.extend_from_slice(&codes);
+            space.words.push(word);
+        }
+
+        space
+    }
+
+    /// Loads a kind in the file order, `limit` keeps only the top N ranked words.
+    pub fn load(conn: &DsjConnection, kind: &Kind, limit: Option<i64>) -> Result<CompressedSpace, String> {
+        let quantizer = match ProductQuantizer::load(conn, kind) {
+            Some(quantizer) => quantizer,
+            None => return Err(format!("kind '{}' isn't product quantized", kind.name)),
+        };
+
+        let mut codes: HashMap<i32, Vec<u8>> = match kind_codes(conn, kind) {
+            Some(codes) => codes.into_iter().collect(),
+            None => return Err(format!("failed to load codes of '{}'", kind.name)),
+        };
+
+        let words = kind_words(conn, kind, limit, &codes)?;
+
+        let rows = words.iter()
+            .map(|word| codes.remove(&word.id).unwrap_or_default())
+            .collect();
+
+        Ok(CompressedSpace::new(words, quantizer, rows))
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn word(&self, row: usize) -> &Word {
+        &self.words[row]
+    }
+
+    pub fn row(&self, word_id: i32) -> Option<usize> {
+        self.rows.get(&word_id).cloned()
+    }
+
+    pub fn codes(&self, row: usize) -> &[u8] {
+        let subspaces = self.quantizer.subspaces();
+
+        &self.codes[row * subspaces..(row + 1) * subspaces]
+    }
+
+    /// Cosine similarity search by asymmetric distances of a query to codes, rows of `exclude` are skipped.
+    pub fn neighbors(&self, query: &[f32], top: usize, exclude: &[usize]) -> Vec<Neighbor> {
+        let table = self.quantizer.table(query);
+        let query_norm = norm(query);
+
+        rank(self.len(), top, exclude, |row| {
+            let norms = query_norm * self.norms[row];
+
+            if norms > 0.0 {
+                ProductQuantizer::score(&table, self.codes(row)) / norms
+            } else {
+                0.0
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use space::testing::test_space;
+
+    #[test]
+    fn test_quantizer() {
+        let space = test_space();
+        let vectors = (0..space.len()).map(|row| space.vector(row)).collect::<Vec<_>>();
+
+        assert!(ProductQuantizer::train(&vectors, 5, 2, 10).is_err(), "check subspaces");
+        assert!(ProductQuantizer::train(&vectors, 2, 7, 10).is_err(), "check centroids");
+
+        // a centroid per vector codes vectors exactly
+        let quantizer = match ProductQuantizer::train(&vectors, 3, vectors.len(), 10) {
+            Ok(quantizer) => quantizer,
+            Err(err) => {
+                assert!(false, "failed to train: {}", err);
+                return;
+            }
+        };
+
+        assert_eq!((quantizer.dim(), quantizer.subspaces(), quantizer.codebooks[2].len()), (4, 3, 6 * 2), "check sizes");
+        assert_eq!(quantizer.bounds, vec![0, 1, 2, 4], "check bounds");
+
+        for vec in vectors.iter() {
+            let codes = quantizer.encode(vec);
+
+            assert_eq!(codes.len(), 3, "check codes");
+            assert_eq!(quantizer.decode(&codes), vec.to_vec(), "check decode");
+            assert!((ProductQuantizer::score(&quantizer.table(vec), &codes) - dot(vec, vec)).abs() < 1e-6, "check score");
+        }
+
+        assert!(quantizer.decode(&[0, 0, vectors.len() as u8]).is_empty(), "check decode out of range");
+
+        let codes = vectors.iter().map(|vec| quantizer.encode(vec)).collect();
+        let compressed = CompressedSpace::new((0..space.len()).map(|row| space.word(row).clone()).collect(), quantizer, codes);
+
+        let rows = |neighbors: Vec<Neighbor>| neighbors.into_iter().map(|neighbor| neighbor.row).collect::<Vec<_>>();
+
+        assert_eq!(rows(compressed.neighbors(space.vector(0), 3, &[0])), rows(space.neighbors(space.vector(0), 3, &[0])), "check neighbors");
+
+        // two centroids per subspace keep the closest pair of fruits
+        match ProductQuantizer::train(&vectors, 2, 2, 10) {
+            Ok(quantizer) => assert_eq!(quantizer.encode(vectors[4]), quantizer.encode(vectors[5]), "check clusters"),
+            Err(err) => assert!(false, "failed to train: {}", err),
+        }
+    }
+
+    #[test]
+    fn test_from_codebooks() {
+        let codebook = |subspace: i32, dim: i32, len: usize| Codebook {
+            kind_id: 1,
+            subspace,
+            dim,
+            centroids: vec![0u8; len],
+        };
+
+        assert!(ProductQuantizer::from_codebooks(vec![codebook(0, 2, 16), codebook(1, 1, 8)]).is_some(), "check valid");
+        assert!(ProductQuantizer::from_codebooks(vec![codebook(0, 2, 12)]).is_none(), "check partial centroid");
+        assert!(ProductQuantizer::from_codebooks(vec![codebook(0, 0, 0)]).is_none(), "check dimension");
+        assert!(ProductQuantizer::from_codebooks(vec![codebook(0, 2, 16), codebook(2, 1, 8)]).is_none(), "check subspaces");
+    }
+}
@@ -0,0 +1,259 @@
+use byteorder::{ByteOrder, LittleEndian};
+use serde_json::{self, Value};
+
+use db::connection::DsjConnection;
+use db::models::kind::Kind;
+use db::models::property::{get_property, set_property};
+use half::{f16_to_f32, f32_to_f16};
+
+// a kind property of packed vectors, kinds without it store f32 points
+pub const QUANTIZATION: &str = "quantization";
+
+pub const PRECISIONS: [&str; 4] = ["f32", "f16", "int8", "int8-vector"];
+
+const LEVELS: f32 = 255.0;
+
+/// Storage precision of vectors of a kind.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Precision {
+    F32,
+    F16,
+    // a scale and an offset per dimension fitted by a pass over vectors
+    Int8,
+    // a scale and an offset stored before codes of every vector
+    Int8Vector,
+}
+
+impl Precision {
+    pub fn parse(name: &str) -> Option<Precision> {
+        match name {
+            "f32" => Some(Precision::F32),
+            "f16" => Some(Precision::F16),
+            "int8" => Some(Precision::Int8),
+            "int8-vector" => Some(Precision::Int8Vector),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Precision::F32 => "f32",
+            Precision::F16 => "f16",
+            Precision::Int8 => "int8",
+            Precision::Int8Vector => "int8-vector",
+        }
+    }
+}
+
+fn range(min: f32, max: f32) -> (f32, f32) {
+    let scale = (max - min) / LEVELS;
+
+    (min, if scale > 0.0 { scale } else { 1.0 })
+}
+
+fn code(value: f32, offset: f32, scale: f32) -> u8 {
+    ((value - offset) / scale).round().max(0.0).min(LEVELS) as u8
+}
+
+/// Packs vectors to bytes of a precision and unpacks them back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Quantizer {
+    precision: Precision,
+    // offsets and scales of dimensions of int8
+    ranges: Option<(Vec<f32>, Vec<f32>)>,
+}
+
+impl Quantizer {
+    pub fn new(precision: Precision) -> Quantizer {
+        Quantizer {
+            precision,
+            ranges: None,
+        }
+    }
+
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    pub fn is_fitted(&self) -> bool {
+        self.precision != Precision::Int8 || self.ranges.is_some()
+    }
+
+    /// Fits ranges of dimensions of int8 by vectors of the same dimension, returns a number of vectors used.
+    pub fn fit(&mut self, vectors: impl Iterator<Item=Vec<f32>>) -> Result<u64, String> {
+        let mut bounds: Option<(Vec<f32>, Vec<f32>)> = None;
+        let mut count = 0;
+
+        for vec in vectors {
+            let &mut (ref mut min, ref mut max) = bounds.get_or_insert_with(|| (vec.clone(), vec.clone()));
+
+            if vec.len() != min.len() {
+                continue;
+            }
+
+            for (i, &value) in vec.iter().enumerate() {
+                min[i] = min[i].min(value);
+                max[i] = max[i].max(value);
+            }
+
+            count += 1;
+        }
+
+        let (min, max) = match bounds {
+            Some(bounds) => bounds,
+            None => return Err(format!("no vectors to fit {} quantization", self.precision.name())),
+        };
+
+        let (offsets, scales) = min.iter().zip(max.iter()).map(|(&min, &max)| range(min, max)).unzip();
+
+        self.ranges = Some((offsets, scales));
+
+        Ok(count)
+    }
+
+    pub fn encode(&self, vec: &[f32]) -> Vec<u8> {
+        match self.precision {
+            Precision::F32 => {
+                let mut bytes = vec![0u8; vec.len() * 4];
+
+                LittleEndian::write_f32_into(vec, &mut bytes);
+                bytes
+            }
+            Precision::F16 => {
+                let mut bytes = vec![0u8; vec.len() * 2];
+
+                for (i, &value) in vec.iter().enumerate() {
+                    LittleEndian::write_u16(&mut bytes[i * 2..], f32_to_f16(value));
+                }
+
+                bytes
+            }
+            Precision::Int8 => match self.ranges {
+                Some((ref offsets, ref scales)) => vec.iter().zip(offsets.iter().zip(scales.iter()))
+                    .map(|(&value, (&offset, &scale))| code(value, offset, scale))
+                    .collect(),
+                None => Vec::new(),
+            },
+            Precision::Int8Vector => {
+                let min = vec.iter().cloned().fold(::std::f32::INFINITY, f32::min);
+                let max = vec.iter().cloned().fold(::std::f32::NEG_INFINITY, f32::max);
+                let (offset, scale) = if vec.is_empty() { (0.0, 1.0) } else { range(min, max) };
+
+                let mut bytes = vec![0u8; 8];
+
+                LittleEndian::write_f32(&mut bytes[..4], offset);
+                LittleEndian::write_f32(&mut bytes[4..], scale);
+                bytes.extend(vec.iter().map(|&value| code(value, offset, scale)));
+                bytes
+            }
+        }
+    }
+
+    pub fn decode(&self, bytes: &[u8]) -> Vec<f32> {
+        match self.precision {
+            Precision::F32 => {
+                let mut vec = vec![0f32; bytes.len() / 4];
+
+                LittleEndian::read_f32_into(&bytes[..vec.len() * 4], &mut vec);
+                vec
+            }
+            Precision::F16 => bytes.chunks(2)
+                .filter(|chunk| chunk.len() == 2)
+                .map(|chunk| f16_to_f32(LittleEndian::read_u16(chunk)))
+                .collect(),
+            Precision::Int8 => match self.ranges {
+                Some((ref offsets, ref scales)) => bytes.iter().zip(offsets.iter().zip(scales.iter()))
+                    .map(|(&code, (&offset, &scale))| offset + code as f32 * scale)
+                    .collect(),
+                None => Vec::new(),
+            },
+            Precision::Int8Vector => {
+                if bytes.len() < 8 {
+                    return Vec::new();
+                }
+
+                let (offset, scale) = (LittleEndian::read_f32(&bytes[..4]), LittleEndian::read_f32(&bytes[4..8]));
+
+                bytes[8..].iter().map(|&code| offset + code as f32 * scale).collect()
+            }
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "precision": self.precision.name(),
+            "offsets": self.ranges.as_ref().map(|&(ref offsets, _)| offsets),
+            "scales": self.ranges.as_ref().map(|&(_, ref scales)| scales),
+        })
+    }
+
+    pub fn from_json(value: &Value) -> Option<Quantizer> {
+        let floats = |name| -> Option<Vec<f32>> {
+            value.get(name)?
+                .as_array()?
+                .iter()
+                .map(|value| value.as_f64().map(|value| value as f32))
+                .collect()
+        };
+
+        let precision = Precision::parse(value.get("precision")?.as_str()?)?;
+
+        let ranges = match (floats("offsets"), floats("scales")) {
+            (Some(offsets), Some(scales)) => Some((offsets, scales)),
+            _ => None,
+        };
+
+        Some(Quantizer {
+            precision,
+            ranges,
+        })
+    }
+
+    pub fn save(&self, conn: &DsjConnection, kind: &Kind) -> bool {
+        set_property(conn, kind, QUANTIZATION, &self.to_json().to_string())
+    }
+
+    /// A quantizer of a kind with packed vectors.
+    pub fn load(conn: &DsjConnection, kind: &Kind) -> Option<Quantizer> {
+        let value = get_property(conn, kind, QUANTIZATION)?;
+
+        Quantizer::from_json(&serde_json::from_str(&value).ok()?)
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    fn error(quantizer: &Quantizer, vec: &[f32]) -> f32 {
+        quantizer.decode(&quantizer.encode(vec)).iter()
+            .zip(vec.iter())
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_quantizer() {
+        let vectors = vec![vec![-1.0f32, 0.0, 10.0], vec![1.0, 0.0, 20.0], vec![0.5, 0.0, 15.0]];
+
+        let mut int8 = Quantizer::new(Precision::Int8);
+
+        assert!(!int8.is_fitted() && Quantizer::new(Precision::F16).is_fitted(), "check fitted");
+        assert_eq!(int8.fit(vectors.clone().into_iter()), Ok(3), "check fit");
+
+        for vec in vectors.iter() {
+            assert_eq!(int8.encode(vec).len(), 3, "check int8 size");
+            assert!(error(&int8, vec) <= 10.0 / 255.0 / 2.0 + 1e-6, "check int8 error {:?}", int8.decode(&int8.encode(vec)));
+        }
+
+        let vec = vec![0.1f32, -0.25, 3.0];
+        let int8_vector = Quantizer::new(Precision::Int8Vector);
+
+        assert_eq!(int8_vector.encode(&vec).len(), 8 + 3, "check int8-vector size");
+        assert!(error(&int8_vector, &vec) <= 3.25 / 255.0 / 2.0 + 1e-6, "check int8-vector error");
+        assert_eq!(Quantizer::new(Precision::F16).encode(&vec).len(), 6, "check f16 size");
+        assert!(error(&Quantizer::new(Precision::F16), &vec) < 1e-3, "check f16 error");
+        assert_eq!(Quantizer::new(Precision::F32).decode(&Quantizer::new(Precision::F32).encode(&vec)), vec, "check f32");
+        assert_eq!(Quantizer::from_json(&int8.to_json()), Some(int8), "check json");
+    }
+}
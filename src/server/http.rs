@@ -0,0 +1,155 @@
+use std::io::{BufRead, Write};
+use std::io;
+use serde_json::Value;
+
+const MAX_BODY: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, PartialEq)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub body: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    pub fn ok(body: Value) -> Response {
+        Response {
+            status: 200,
+            body,
+        }
+    }
+
+    pub fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            body: json!({ "error": message }),
+        }
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+fn read_line<R: BufRead>(reader: &mut R) -> Result<String, String> {
+    let mut line = String::new();
+
+    match reader.read_line(&mut line) {
+        Ok(_) => Ok(line.trim_end_matches(|c| c == '\r' || c == '\n').to_string()),
+        Err(err) => Err(format!("failed to read a request with {:?}", err)),
+    }
+}
+
+/// Reads a request line, headers and a body of Content-Length bytes.
+pub fn read_request<R: BufRead>(reader: &mut R) -> Result<Request, String> {
+    let line = read_line(reader)?;
+    let parts = line.split_whitespace().collect::<Vec<_>>();
+
+    if parts.len() != 3 || !parts[2].starts_with("HTTP/") {
+        return Err(format!("malformed request line '{}'", line));
+    }
+
+    let mut content_length = 0;
+
+    loop {
+        let header = read_line(reader)?;
+
+        if header.is_empty() {
+            break;
+        }
+
+        let mut header = header.splitn(2, ':');
+
+        if let (Some(name), Some(value)) = (header.next(), header.next()) {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = match value.trim().parse::<usize>() {
+                    Ok(len) if len <= MAX_BODY => len,
+                    _ => return Err(format!("unsupported content length '{}'", value.trim())),
+                };
+            }
+        }
+    }
+
+    let mut body = vec![0u8; content_length];
+
+    if let Err(err) = reader.read_exact(&mut body) {
+        return Err(format!("failed to read a request body with {:?}", err));
+    }
+
+    Ok(Request {
+        method: parts[0].to_string(),
+        path: parts[1].split('?').next().unwrap_or_default().to_string(),
+        body,
+    })
+}
+
+pub fn write_response<W: Write>(writer: &mut W, response: &Response) -> io::Result<()> {
+    let body = response.body.to_string();
+
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status, reason(response.status), body.len(), body,
+    )?;
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_read_request() {
+        let body = r#"{"kind":"fasttext","words":["мир"]}"#;
+        let data = format!("POST /vectors?pretty HTTP/1.1\r\nHost: localhost\r\ncontent-length: {}\r\n\r\n{}", body.len(), body);
+
+        match read_request(&mut Cursor::new(data)) {
+            Ok(request) => assert_eq!(request, Request {
+                method: "POST".to_string(),
+                path: "/vectors".to_string(),
+                body: body.as_bytes().to_vec(),
+            }, "check request"),
+            Err(err) => assert!(false, "failed to read a request with {:?}", err),
+        }
+
+        match read_request(&mut Cursor::new("GET /kinds HTTP/1.0\n\n")) {
+            Ok(request) => assert_eq!((request.method.as_str(), request.path.as_str(), request.body.len()), ("GET", "/kinds", 0), "check request without body"),
+            Err(err) => assert!(false, "failed to read a request with {:?}", err),
+        }
+
+        assert!(read_request(&mut Cursor::new("")).is_err(), "check empty request");
+        assert!(read_request(&mut Cursor::new("GET /kinds\r\n\r\n")).is_err(), "check request line");
+        assert!(read_request(&mut Cursor::new("POST / HTTP/1.1\r\nContent-Length: 10\r\n\r\n{}")).is_err(), "check short body");
+    }
+
+    #[test]
+    fn test_write_response() {
+        let mut buf = Vec::new();
+
+        if let Err(err) = write_response(&mut buf, &Response::error(404, "кто?")) {
+            assert!(false, "failed to write a response with {:?}", err);
+        }
+
+        let body = r#"{"error":"кто?"}"#;
+
+        assert_eq!(
+            String::from_utf8(buf).unwrap_or_default(),
+            format!("HTTP/1.1 404 Not Found\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body),
+            "check response"
+        );
+    }
+}
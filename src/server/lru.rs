@@ -0,0 +1,105 @@
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+
+/// Map of a limited size which evicts the least recently used entry.
+pub struct Lru<K, V> {
+    capacity: usize,
+    tick: u64,
+    entries: HashMap<K, (V, u64)>,
+    order: BTreeMap<u64, K>,
+}
+
+impl<K, V> Lru<K, V>
+    where K: Hash + Eq + Clone,
+          V: Clone
+{
+    pub fn new(capacity: usize) -> Lru<K, V> {
+        Lru {
+            capacity,
+            tick: 0,
+            entries: HashMap::with_capacity(capacity),
+            order: BTreeMap::new(),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn next_tick(&mut self) -> u64 {
+        self.tick += 1;
+        self.tick
+    }
+
+    pub fn get(&mut self, key: &K) -> Option<V> {
+        let tick = self.next_tick();
+
+        match self.entries.get_mut(key) {
+            Some(&mut (ref value, ref mut used)) => {
+                self.order.remove(used);
+                self.order.insert(tick, key.clone());
+                *used = tick;
+
+                Some(value.clone())
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Some((_, used)) = self.entries.remove(&key) {
+            self.order.remove(&used);
+        }
+
+        while self.entries.len() >= self.capacity {
+            let oldest = match self.order.keys().next() {
+                Some(&oldest) => oldest,
+                None => break,
+            };
+
+            if let Some(evicted) = self.order.remove(&oldest) {
+                self.entries.remove(&evicted);
+            }
+        }
+
+        let tick = self.next_tick();
+
+        self.order.insert(tick, key.clone());
+        self.entries.insert(key, (value, tick));
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[test]
+    fn test_lru() {
+        let mut lru = Lru::new(2);
+
+        lru.insert("намело", 1);
+        lru.insert("сугробы", 2);
+
+        assert_eq!(lru.get(&"намело"), Some(1), "check get");
+
+        lru.insert("крыльца", 3);
+
+        assert_eq!(lru.len(), 2, "check capacity");
+        assert_eq!(lru.get(&"сугробы"), None, "check evicted least recently used");
+        assert_eq!(lru.get(&"намело"), Some(1), "check kept recently used");
+
+        lru.insert("крыльца", 4);
+
+        assert_eq!((lru.len(), lru.get(&"крыльца")), (2, Some(4)), "check replace");
+
+        let mut empty = Lru::new(0);
+
+        empty.insert("у", 1);
+
+        assert_eq!(empty.get(&"у"), None, "check zero capacity");
+    }
+}
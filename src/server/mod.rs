@@ -0,0 +1,477 @@
+mod http;
+mod lru;
+mod signal;
+
+use std::io::{self, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+use serde_json::{self, Value};
+
+use analogy::{Expression, Method, solve};
+use command::{json_neighbor, json_f32, json_vec};
+use db::connection::DsjConnection;
+use db::models::index::index_params;
+use db::models::kind::{Kind, get_kind, kinds_list};
+use db::models::vector::kind_size;
+use hnsw::Hnsw;
+use lookup::{Found, Lookup, STRATEGIES, Strategy};
+use space::{Neighbor, Space, cosine};
+use self::http::{Request, Response, read_request, write_response};
+use self::lru::Lru;
+
+const PATHS: [&str; 7] = ["/health", "/kinds", "/vectors", "/similarity", "/neighbors", "/analogy", "/shutdown"];
+
+type Reply = Result<Value, Response>;
+
+// a kind id, lookup strategies, a language and a word
+type CacheKey = (i32, Vec<Strategy>, Option<String>, String);
+
+// loaded spaces kept in memory, each one holds a full copy of a kind
+const SPACES: usize = 2;
+
+struct Loaded {
+    space: Space,
+    hnsw: Option<Hnsw>,
+}
+
+/// JSON service over a database, requests are handled one by one
+/// on a single connection.
+pub struct Server {
+    conn: DsjConnection,
+    cache: Lru<CacheKey, Option<Rc<Found>>>,
+    spaces: Lru<(i32, Option<i64>), Rc<Loaded>>,
+    stopping: bool,
+}
+
+fn bad_request(message: String) -> Response {
+    Response::error(400, &message)
+}
+
+fn string<'a>(body: &'a Value, name: &str) -> Result<&'a str, Response> {
+    body[name].as_str()
+        .ok_or_else(|| bad_request(format!("'{}' should be a string", name)))
+}
+
+fn strings(value: &Value, name: &str) -> Result<Vec<String>, Response> {
+    value.as_array()
+        .and_then(|values| values.iter().map(|value| value.as_str().map(|value| value.to_string())).collect())
+        .ok_or_else(|| bad_request(format!("'{}' should be an array of strings", name)))
+}
+
+fn number(body: &Value, name: &str, default: u64) -> Result<u64, Response> {
+    match body[name] {
+        Value::Null => Ok(default),
+        ref value => value.as_u64().ok_or_else(|| bad_request(format!("'{}' should be a positive integer", name))),
+    }
+}
+
+fn limit(body: &Value) -> Result<Option<i64>, Response> {
+    match body["limit"] {
+        Value::Null => Ok(None),
+        _ => number(body, "limit", 0).map(|limit| Some(limit as i64)),
+    }
+}
+
+fn neighbors_json(space: &Space, neighbors: &[Neighbor]) -> Value {
+    Value::Array(neighbors.iter().map(|neighbor| json_neighbor(space, neighbor)).collect())
+}
+
+impl Server {
+    pub fn new(conn: DsjConnection, cache_size: usize) -> Server {
+        Server {
+            conn,
+            cache: Lru::new(cache_size),
+            spaces: Lru::new(SPACES),
+            stopping: false,
+        }
+    }
+
+    /// Accepts connections until SIGINT, SIGTERM or POST /shutdown,
+    /// a request in progress is answered before exit.
+    pub fn serve(&mut self, bind: &str) -> Result<(), String> {
+        let listener = match TcpListener::bind(bind) {
+            Ok(listener) => listener,
+            Err(err) => return Err(format!("failed to bind {} with {:?}", bind, err)),
+        };
+
+        if let Err(err) = listener.set_nonblocking(true) {
+            return Err(format!("failed to set up a listener with {:?}", err));
+        }
+
+        signal::listen();
+
+        println!("listening on http://{}", bind);
+
+        while !self.stopping && !signal::is_stopped() {
+            match listener.accept() {
+                Ok((stream, _)) => if let Err(err) = self.respond(stream) {
+                    println!("{}", err);
+                },
+                Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => thread::sleep(Duration::from_millis(50)),
+                Err(err) => return Err(format!("failed to accept a connection with {:?}", err)),
+            }
+        }
+
+        println!("stopped");
+
+        Ok(())
+    }
+
+    fn respond(&mut self, mut stream: TcpStream) -> Result<(), String> {
+        let setup = stream.set_nonblocking(false)
+            .and_then(|_| stream.set_read_timeout(Some(Duration::from_secs(10))));
+
+        if let Err(err) = setup {
+            return Err(format!("failed to set up a connection with {:?}", err));
+        }
+
+        let response = match read_request(&mut BufReader::new(&stream)) {
+            Ok(request) => self.handle(&request),
+            Err(err) => bad_request(err),
+        };
+
+        write_response(&mut stream, &response)
+            .map_err(|err| format!("failed to write a response with {:?}", err))
+    }
+
+    pub fn handle(&mut self, request: &Request) -> Response {
+        let body = if request.body.is_empty() {
+            Value::Null
+        } else {
+            match serde_json::from_slice(&request.body) {
+                Ok(body) => body,
+                Err(err) => return bad_request(format!("invalid JSON body: {}", err)),
+            }
+        };
+
+        let reply = match (request.method.as_str(), request.path.as_str()) {
+            ("GET", "/health") => Ok(json!({
+                "status": "ok",
+                "cached_vectors": self.cache.len(),
+            })),
+            ("GET", "/kinds") => self.kinds(),
+            ("POST", "/vectors") => self.vectors(&body),
+            ("POST", "/similarity") => self.similarity(&body),
+            ("POST", "/neighbors") => self.neighbors(&body),
+            ("POST", "/analogy") => self.analogy(&body),
+            ("POST", "/shutdown") => {
+                self.stopping = true;
+                Ok(json!({ "status": "stopping" }))
+            }
+            (_, path) if PATHS.contains(&path) =>
+                Err(Response::error(405, &format!("method {} isn't allowed for {}", request.method, path))),
+            (_, path) => Err(Response::error(404, &format!("unknown path {}", path))),
+        };
+
+        match reply {
+            Ok(body) => Response::ok(body),
+            Err(response) => response,
+        }
+    }
+
+    fn kind(&self, body: &Value) -> Result<Kind, Response> {
+        let name = string(body, "kind")?;
+
+        get_kind(&self.conn, name)
+            .ok_or_else(|| Response::error(404, &format!("kind '{}' doesn't found", name)))
+    }
+
+    fn lookup(&self, body: &Value) -> Result<Lookup, Response> {
+        let kind = self.kind(body)?;
+
+        let strategies = match body["strategies"] {
+            Value::Null => Strategy::parse_chain(STRATEGIES),
+            ref value => Strategy::parse_chain(&strings(value, "strategies")?.join(",")),
+        }.map_err(bad_request)?;
+
+        Ok(Lookup::new(&self.conn, &kind, &strategies))
+    }
+
+    fn vector(&mut self, lookup: &Lookup, w: &str, lang: Option<&str>) -> Option<Rc<Found>> {
+        let key = (lookup.kind().id, lookup.strategies().to_vec(), lang.map(|lang| lang.to_string()), w.to_string());
+
+        if let Some(found) = self.cache.get(&key) {
+            return found;
+        }
+
+        let found = lookup.find(&self.conn, w, lang).map(Rc::new);
+
+        self.cache.insert(key, found.clone());
+
+        found
+    }
+
+    fn space(&mut self, kind: &Kind, limit: Option<i64>) -> Result<Rc<Loaded>, Response> {
+        if let Some(loaded) = self.spaces.get(&(kind.id, limit)) {
+            return Ok(loaded);
+        }
+
+        let space = Space::load(&self.conn, kind, limit)
+            .map_err(|err| Response::error(500, &err))?;
+        let hnsw = Hnsw::load(&self.conn, kind, &space);
+
+        let loaded = Rc::new(Loaded {
+            space,
+            hnsw,
+        });
+
+        self.spaces.insert((kind.id, limit), loaded.clone());
+
+        Ok(loaded)
+    }
+
+    fn kinds(&self) -> Reply {
+        let kinds = kinds_list(&self.conn)
+            .ok_or_else(|| Response::error(500, "failed to list kinds"))?;
+
+        Ok(json!({
+            "kinds": kinds.iter().map(|kind| {
+                let (words, dim) = kind_size(&self.conn, kind).unwrap_or_default();
+
+                json!({
+                    "name": kind.name,
+                    "words": words,
+                    "dim": dim,
+                    "index": index_params(&self.conn, kind).map(|(m, ef_construction)| json!({
+                        "m": m,
+                        "ef_construction": ef_construction,
+                    })),
+                })
+            }).collect::<Vec<_>>(),
+        }))
+    }
+
+    fn vectors(&mut self, body: &Value) -> Reply {
+        let lookup = self.lookup(body)?;
+        let lang = body["lang"].as_str();
+        let words = strings(&body["words"], "words")?;
+
+        let mut vectors = Vec::new();
+        let mut missing = Vec::new();
+
+        for w in words.iter() {
+            match self.vector(&lookup, w, lang) {
+                Some(found) => vectors.push(json!({
+                    "word": w,
+                    "lang": found.lang(),
+                    "match": found.matched.name(),
+                    "found": found.stored(),
+                    "vector": json_vec(&found.vec),
+                })),
+                None => missing.push(w.to_string()),
+            }
+        }
+
+        Ok(json!({
+            "vectors": vectors,
+            "missing": missing,
+        }))
+    }
+
+    fn similarity(&mut self, body: &Value) -> Reply {
+        let lookup = self.lookup(body)?;
+        let lang = body["lang"].as_str();
+        let pairs = body["pairs"].as_array()
+            .ok_or_else(|| bad_request("'pairs' should be an array of word pairs".to_string()))?;
+
+        let mut similarities = Vec::with_capacity(pairs.len());
+
+        for pair in pairs.iter() {
+            let pair = strings(pair, "pairs")?;
+
+            if pair.len() != 2 {
+                return Err(bad_request(format!("expected a pair of words, got {:?}", pair)));
+            }
+
+            let similarity = match (self.vector(&lookup, &pair[0], lang), self.vector(&lookup, &pair[1], lang)) {
+                (Some(a), Some(b)) => Some(json_f32(cosine(&a.vec, &b.vec))),
+                _ => None,
+            };
+
+            similarities.push(json!(similarity));
+        }
+
+        Ok(json!({ "similarities": similarities }))
+    }
+
+    fn neighbors(&mut self, body: &Value) -> Reply {
+        let lookup = self.lookup(body)?;
+        let lang = body["lang"].as_str();
+        let words = strings(&body["words"], "words")?;
+        let top = number(body, "top", 10)? as usize;
+        let ef = number(body, "ef", 64)? as usize;
+        let exact = body["exact"].as_bool().unwrap_or(false);
+        let loaded = self.space(lookup.kind(), limit(body)?)?;
+
+        let mut results = Vec::with_capacity(words.len());
+
+        for w in words.iter() {
+            let found = match self.vector(&lookup, w, lang) {
+                Some(found) => found,
+                None => {
+                    results.push(json!({ "word": w, "neighbors": null }));
+                    continue;
+                }
+            };
+
+            let exclude = found.word_id().and_then(|id| loaded.space.row(id)).into_iter().collect::<Vec<_>>();
+            let neighbors = match loaded.hnsw {
+                Some(ref hnsw) if !exact => hnsw.search(&loaded.space, &found.vec, top, ef, &exclude),
+                _ => loaded.space.neighbors(&found.vec, top, &exclude),
+            };
+
+            results.push(json!({
+                "word": w,
+                "match": found.matched.name(),
+                "neighbors": neighbors_json(&loaded.space, &neighbors),
+            }));
+        }
+
+        Ok(json!({ "results": results }))
+    }
+
+    fn analogy(&mut self, body: &Value) -> Reply {
+        let lookup = self.lookup(body)?;
+        let lang = body["lang"].as_str();
+        let queries = strings(&body["queries"], "queries")?;
+        let top = number(body, "top", 10)? as usize;
+        let method = match body["method"].as_str() {
+            Some(method) => Method::parse(method)
+                .ok_or_else(|| bad_request(format!("unknown method '{}'", method)))?,
+            None => Method::CosAdd,
+        };
+        let loaded = self.space(lookup.kind(), limit(body)?)?;
+
+        let mut results = Vec::with_capacity(queries.len());
+
+        for query in queries.iter() {
+            let expression = Expression::query(query).map_err(bad_request)?;
+
+            let mut missing = Vec::new();
+            let mut exclude = Vec::new();
+            let mut positive = Vec::new();
+            let mut negative = Vec::new();
+
+            for (words, vectors) in vec![(&expression.positive, &mut positive), (&expression.negative, &mut negative)] {
+                for w in words.iter() {
+                    match self.vector(&lookup, w, lang) {
+                        Some(found) => {
+                            exclude.extend(found.word_id().and_then(|id| loaded.space.row(id)));
+                            vectors.push(found.vec.clone());
+                        }
+                        None => missing.push(w.to_string()),
+                    }
+                }
+            }
+
+            if missing.is_empty() {
+                let answers = solve(&loaded.space, &positive, &negative, method, top, &exclude);
+
+                results.push(json!({
+                    "query": query,
+                    "answers": neighbors_json(&loaded.space, &answers),
+                }));
+            } else {
+                results.push(json!({
+                    "query": query,
+                    "missing": missing,
+                }));
+            }
+        }
+
+        Ok(json!({ "results": results }))
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::testing::test_connection;
+
+    fn test_server() -> Server {
+        Server::new(test_connection(), 2)
+    }
+
+    fn request(server: &mut Server, method: &str, path: &str, body: Value) -> Response {
+        server.handle(&Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            body: if body.is_null() { Vec::new() } else { body.to_string().into_bytes() },
+        })
+    }
+
+    #[test]
+    fn test_routes() {
+        let mut server = test_server();
+
+        assert_eq!(request(&mut server, "GET", "/health", Value::Null), Response::ok(json!({"status": "ok", "cached_vectors": 0})), "check health");
+        assert_eq!(request(&mut server, "GET", "/kinds", Value::Null), Response::ok(json!({
+            "kinds": [{"name": "fasttext", "words": 5, "dim": 4, "index": null}],
+        })), "check kinds");
+
+        assert_eq!(request(&mut server, "GET", "/vectors", Value::Null).status, 405, "check method");
+        assert_eq!(request(&mut server, "GET", "/missing", Value::Null).status, 404, "check path");
+        assert_eq!(request(&mut server, "POST", "/vectors", json!({"kind": "glove", "words": []})).status, 404, "check kind");
+        assert_eq!(request(&mut server, "POST", "/vectors", json!({"kind": "fasttext", "words": "мир"})).status, 400, "check words");
+
+        let response = server.handle(&Request {
+            method: "POST".to_string(),
+            path: "/vectors".to_string(),
+            body: b"{".to_vec(),
+        });
+
+        assert_eq!(response.status, 400, "check malformed body");
+
+        assert!(!server.stopping, "check running");
+        assert_eq!(request(&mut server, "POST", "/shutdown", Value::Null).status, 200, "check shutdown");
+        assert!(server.stopping, "check stopping");
+    }
+
+    #[test]
+    fn test_queries() {
+        let mut server = test_server();
+
+        assert_eq!(request(&mut server, "POST", "/vectors", json!({"kind": "fasttext", "words": ["яблоко", "груша"]})), Response::ok(json!({
+            "vectors": [{"word": "яблоко", "lang": "ru", "match": "exact", "found": "яблоко", "vector": [0.0, 0.0, 0.1, 0.9]}],
+            "missing": ["груша"],
+        })), "check vectors");
+
+        assert_eq!(server.cache.len(), 2, "check cache size");
+
+        let response = request(&mut server, "POST", "/vectors", json!({"kind": "fasttext", "words": ["Яблоко"], "strategies": ["exact"]}));
+
+        assert_eq!(response.body["missing"], json!(["Яблоко"]), "check strategies");
+        assert_eq!(request(&mut server, "POST", "/vectors", json!({"kind": "fasttext", "words": ["Яблоко"]})).body["vectors"][0]["match"], json!("lowercase"), "check default strategies");
+        assert_eq!(request(&mut server, "POST", "/vectors", json!({"kind": "fasttext", "words": [], "strategies": ["stem"]})).status, 400, "check unknown strategy");
+
+        let response = request(&mut server, "POST", "/similarity", json!({"kind": "fasttext", "pairs": [["король", "король"], ["король", "груша"]]}));
+
+        assert_eq!(response.body["similarities"][1], Value::Null, "check missing similarity");
+        assert!((response.body["similarities"][0].as_f64().unwrap_or_default() - 1.0).abs() < 1e-6, "check similarity");
+
+        let response = request(&mut server, "POST", "/neighbors", json!({"kind": "fasttext", "words": ["яблоко", "груша"], "top": 2}));
+
+        assert_eq!(response.body["results"][0]["neighbors"].as_array().map(|neighbors| neighbors.len()), Some(2), "check top");
+        assert_eq!(response.body["results"][0]["neighbors"][0]["word"], json!("женщина"), "check neighbour");
+        assert_eq!(response.body["results"][1]["neighbors"], Value::Null, "check missing word");
+
+        let response = request(&mut server, "POST", "/analogy", json!({
+            "kind": "fasttext",
+            "queries": ["король мужчина женщина", "король - груша"],
+            "method": "mul",
+            "top": 1,
+        }));
+
+        assert_eq!(response.body["results"][0]["answers"][0]["word"], json!("королева"), "check analogy");
+        assert_eq!(response.body["results"][1]["missing"], json!(["груша"]), "check missing analogy word");
+        assert_eq!(request(&mut server, "POST", "/analogy", json!({"kind": "fasttext", "queries": ["король"]})).status, 400, "check query");
+
+        for limit in 1..5 {
+            request(&mut server, "POST", "/neighbors", json!({"kind": "fasttext", "words": ["яблоко"], "limit": limit}));
+        }
+
+        assert_eq!(server.spaces.len(), SPACES, "check spaces evicted");
+    }
+}
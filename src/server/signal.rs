@@ -0,0 +1,25 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static STOP: AtomicBool = AtomicBool::new(false);
+
+/// Turns SIGINT and SIGTERM into a stop request checked between connections.
+#[cfg(unix)]
+pub fn listen() {
+    use libc;
+
+    extern "C" fn handler(_: libc::c_int) {
+        STOP.store(true, Ordering::SeqCst);
+    }
+
+    unsafe {
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+        libc::signal(libc::SIGTERM, handler as libc::sighandler_t);
+    }
+}
+
+#[cfg(not(unix))]
+pub fn listen() {}
+
+pub fn is_stopped() -> bool {
+    STOP.load(Ordering::SeqCst)
+}
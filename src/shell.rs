@@ -0,0 +1,338 @@
+use rustyline;
+use rustyline::completion::Completer;
+
+use analogy::{Expression, Method, solve};
+use db::connection::DsjConnection;
+use db::models::index::index_params;
+use db::models::kind::{Kind, get_kind, kinds_list};
+use db::models::vector::kind_size;
+use db::models::word::complete_words;
+use hnsw::Hnsw;
+use lookup::{Found, Lookup, STRATEGIES, Strategy};
+use space::{Neighbor, Space, cosine};
+
+pub const COMMANDS: [&str; 12] = ["use", "lang", "strategy", "vec", "sim", "nn", "analogy", "kinds", "stats", "help", "quit", "exit"];
+
+const HELP: &str = "\
+use <kind>          select a kind of vectors
+lang [<lang>]       look words up in a language, any language without an argument
+strategy [<chain>]  print or set lookup strategies, e.g. 'exact,lowercase,subwords'
+vec <word>          print a vector
+sim <a> <b>         cosine similarity of two words
+nn <word> [<n>]     nearest words
+analogy <a> <b> <c> a - b + c, or an expression 'a - b + c - d'
+kinds               list kinds
+stats               words, dimension and index of the selected kind
+quit                exit, as Ctrl-D";
+
+const COMPLETIONS: i64 = 50;
+
+struct Loaded {
+    space: Space,
+    hnsw: Option<Hnsw>,
+}
+
+/// State of an interactive session: a selected kind, a language
+/// and vectors of the kind loaded by the first search.
+pub struct Session {
+    conn: DsjConnection,
+    kind: Option<Kind>,
+    lang: Option<String>,
+    strategies: Vec<Strategy>,
+    top: usize,
+    loaded: Option<Loaded>,
+}
+
+fn format_neighbors(space: &Space, neighbors: &[Neighbor]) -> String {
+    neighbors.iter()
+        .map(|neighbor| format!("{}\t{:.6}", space.word(neighbor.row).word, neighbor.similarity))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl Session {
+    pub fn new(conn: DsjConnection, top: usize) -> Session {
+        Session {
+            conn,
+            kind: None,
+            lang: None,
+            strategies: Strategy::parse_chain(STRATEGIES).unwrap_or_default(),
+            top,
+            loaded: None,
+        }
+    }
+
+    pub fn prompt(&self) -> String {
+        match (&self.kind, &self.lang) {
+            (&Some(ref kind), &Some(ref lang)) => format!("dsj:{}@{}> ", kind.name, lang),
+            (&Some(ref kind), &None) => format!("dsj:{}> ", kind.name),
+            _ => "dsj> ".to_string(),
+        }
+    }
+
+    /// Runs a line of a command and its arguments, returns a text to print.
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let tokens = line.split_whitespace().collect::<Vec<_>>();
+        let top = self.top;
+
+        let (command, args) = match tokens.split_first() {
+            Some((&command, args)) => (command, args),
+            None => return Ok(String::new()),
+        };
+
+        match (command, args.len()) {
+            ("use", 1) => self.use_kind(args[0]),
+            ("lang", 0) => {
+                self.lang = None;
+                Ok("any language".to_string())
+            }
+            ("lang", 1) => {
+                self.lang = Some(args[0].to_string());
+                Ok(format!("language {}", args[0]))
+            }
+            ("strategy", 0) => Ok(self.strategies.iter().map(|strategy| strategy.name()).collect::<Vec<_>>().join(",")),
+            ("strategy", 1) => {
+                self.strategies = Strategy::parse_chain(args[0])?;
+                Ok(format!("strategies {}", args[0]))
+            }
+            ("vec", 1) => {
+                let found = self.lookup(args[0])?;
+                let points = found.vec.iter().map(|point| point.to_string()).collect::<Vec<_>>().join(" ");
+
+                match (found.matched, found.stored()) {
+                    (Strategy::Exact, _) => Ok(format!("{} {}", args[0], points)),
+                    (matched, Some(stored)) => Ok(format!("{} {}\n({} match '{}')", args[0], points, matched.name(), stored)),
+                    (matched, None) => Ok(format!("{} {}\n({} match)", args[0], points, matched.name())),
+                }
+            }
+            ("sim", 2) => {
+                let a = self.lookup(args[0])?;
+                let b = self.lookup(args[1])?;
+
+                Ok(format!("{:.6}", cosine(&a.vec, &b.vec)))
+            }
+            ("nn", 1) => self.neighbors(args[0], top),
+            ("nn", 2) => match args[1].parse::<usize>() {
+                Ok(top) => self.neighbors(args[0], top),
+                Err(_) => Err(format!("expected a count of words, got '{}'", args[1])),
+            },
+            ("analogy", count) if count > 0 => self.analogy(&args.join(" ")),
+            ("kinds", 0) => self.kinds(),
+            ("stats", 0) => self.stats(),
+            ("help", _) => Ok(HELP.to_string()),
+            (command, _) if COMMANDS.contains(&command) => Err(format!("wrong arguments of '{}', see 'help'", command)),
+            (command, _) => Err(format!("unknown command '{}', see 'help'", command)),
+        }
+    }
+
+    fn kind(&self) -> Result<Kind, String> {
+        match self.kind {
+            Some(ref kind) => Ok(kind.clone()),
+            None => Err("select a kind with 'use <kind>' first".to_string()),
+        }
+    }
+
+    fn use_kind(&mut self, name: &str) -> Result<String, String> {
+        let kind = match get_kind(&self.conn, name) {
+            Some(kind) => kind,
+            None => return Err(format!("kind '{}' doesn't found", name)),
+        };
+
+        let (words, dim) = kind_size(&self.conn, &kind).unwrap_or_default();
+
+        self.kind = Some(kind);
+        self.loaded = None;
+
+        Ok(format!("{}: {} words, dimension {}", name, words, dim))
+    }
+
+    fn lookup(&self, w: &str) -> Result<Found, String> {
+        let kind = self.kind()?;
+        let lang = self.lang.as_ref().map(|lang| lang.as_str());
+
+        Lookup::new(&self.conn, &kind, &self.strategies).find(&self.conn, w, lang)
+            .ok_or_else(|| format!("missing: {}", w))
+    }
+
+    fn load(&mut self) -> Result<&Loaded, String> {
+        if self.loaded.is_none() {
+            let kind = self.kind()?;
+            let space = Space::load(&self.conn, &kind, None)?;
+            let hnsw = Hnsw::load(&self.conn, &kind, &space);
+
+            self.loaded = Some(Loaded {
+                space,
+                hnsw,
+            });
+        }
+
+        match self.loaded {
+            Some(ref loaded) => Ok(loaded),
+            None => Err("failed to load vectors".to_string()),
+        }
+    }
+
+    fn neighbors(&mut self, w: &str, top: usize) -> Result<String, String> {
+        let found = self.lookup(w)?;
+        let loaded = self.load()?;
+        let exclude = found.word_id().and_then(|id| loaded.space.row(id)).into_iter().collect::<Vec<_>>();
+
+        let neighbors = match loaded.hnsw {
+            Some(ref hnsw) => hnsw.search(&loaded.space, &found.vec, top, top.max(64), &exclude),
+            None => loaded.space.neighbors(&found.vec, top, &exclude),
+        };
+
+        Ok(format_neighbors(&loaded.space, &neighbors))
+    }
+
+    fn analogy(&mut self, query: &str) -> Result<String, String> {
+        let expression = Expression::query(query)?;
+
+        let mut word_ids = Vec::new();
+        let mut positive = Vec::new();
+        let mut negative = Vec::new();
+
+        for w in expression.positive.iter() {
+            let found = self.lookup(w)?;
+
+            word_ids.extend(found.word_id());
+            positive.push(found.vec);
+        }
+
+        for w in expression.negative.iter() {
+            let found = self.lookup(w)?;
+
+            word_ids.extend(found.word_id());
+            negative.push(found.vec);
+        }
+
+        let top = self.top;
+        let loaded = self.load()?;
+        let exclude = word_ids.iter()
+            .filter_map(|&id| loaded.space.row(id))
+            .collect::<Vec<_>>();
+
+        Ok(format_neighbors(&loaded.space, &solve(&loaded.space, &positive, &negative, Method::CosAdd, top, &exclude)))
+    }
+
+    fn kinds(&self) -> Result<String, String> {
+        let kinds = kinds_list(&self.conn)
+            .ok_or_else(|| "failed to list kinds".to_string())?;
+
+        Ok(kinds.iter()
+            .map(|kind| {
+                let (words, dim) = kind_size(&self.conn, kind).unwrap_or_default();
+
+                format!("{}\t{} words\tdimension {}", kind.name, words, dim)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+
+    fn stats(&self) -> Result<String, String> {
+        let kind = self.kind()?;
+        let (words, dim) = kind_size(&self.conn, &kind).unwrap_or_default();
+
+        let index = match index_params(&self.conn, &kind) {
+            Some((m, ef_construction)) => format!("HNSW, m = {}, ef construction = {}", m, ef_construction),
+            None => "none".to_string(),
+        };
+
+        let loaded = match self.loaded {
+            Some(ref loaded) => format!("{} vectors", loaded.space.len()),
+            None => "not yet".to_string(),
+        };
+
+        Ok(format!("kind\t{}\nwords\t{}\ndimension\t{}\nindex\t{}\nloaded\t{}", kind.name, words, dim, index, loaded))
+    }
+}
+
+/// Completes commands, kinds after 'use' and words of the `words` table.
+pub struct WordCompleter {
+    conn: DsjConnection,
+}
+
+impl WordCompleter {
+    pub fn new(conn: DsjConnection) -> WordCompleter {
+        WordCompleter {
+            conn,
+        }
+    }
+
+    fn candidates(&self, line: &str, pos: usize) -> (usize, Vec<String>) {
+        let start = line[..pos].rfind(' ').map(|space| space + 1).unwrap_or(0);
+        let prefix = &line[start..pos];
+        let command = line.split_whitespace().next().unwrap_or_default();
+
+        let candidates = if start == 0 {
+            COMMANDS.iter()
+                .filter(|command| command.starts_with(prefix))
+                .map(|command| command.to_string())
+                .collect()
+        } else if command == "use" {
+            kinds_list(&self.conn).unwrap_or_default()
+                .into_iter()
+                .map(|kind| kind.name)
+                .filter(|name| name.starts_with(prefix))
+                .collect()
+        } else if prefix.is_empty() {
+            Vec::new()
+        } else {
+            complete_words(&self.conn, prefix, COMPLETIONS).unwrap_or_default()
+        };
+
+        (start, candidates)
+    }
+}
+
+impl Completer for WordCompleter {
+    fn complete(&self, line: &str, pos: usize) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(self.candidates(line, pos))
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::testing::test_connection;
+
+    #[test]
+    fn test_session() {
+        let mut session = Session::new(test_connection(), 2);
+
+        assert_eq!(session.prompt(), "dsj> ", "check prompt");
+        assert!(session.execute("vec король").is_err(), "check without kind");
+        assert!(session.execute("use glove").is_err(), "check missing kind");
+        assert_eq!(session.execute("use fasttext"), Ok("fasttext: 5 words, dimension 4".to_string()), "check use");
+        assert_eq!(session.execute("lang ru").map(|_| session.prompt()), Ok("dsj:fasttext@ru> ".to_string()), "check lang");
+
+        assert_eq!(session.execute("vec яблоко"), Ok("яблоко 0 0 0.1 0.9".to_string()), "check vec");
+        assert_eq!(session.execute("vec груша"), Err("missing: груша".to_string()), "check missing word");
+        assert_eq!(session.execute("vec Яблоко"), Ok("Яблоко 0 0 0.1 0.9\n(lowercase match 'яблоко')".to_string()), "check lowercase");
+        assert_eq!(session.execute("strategy exact"), Ok("strategies exact".to_string()), "check strategy");
+        assert_eq!(session.execute("vec Яблоко"), Err("missing: Яблоко".to_string()), "check exact strategy");
+        assert!(session.execute("strategy stem").is_err(), "check unknown strategy");
+        assert_eq!(session.execute("strategy"), Ok("exact".to_string()), "check current strategy");
+        assert_eq!(session.execute("sim король король"), Ok("1.000000".to_string()), "check sim");
+        assert_eq!(session.execute("nn яблоко 1").map(|out| out.starts_with("женщина\t")), Ok(true), "check nn");
+        assert_eq!(session.execute("nn яблоко").map(|out| out.lines().count()), Ok(2), "check default top");
+        assert_eq!(session.execute("analogy король мужчина женщина").map(|out| out.starts_with("королева\t")), Ok(true), "check analogy");
+        assert_eq!(session.execute("kinds"), Ok("fasttext\t5 words\tdimension 4".to_string()), "check kinds");
+        assert_eq!(session.execute("stats").map(|out| out.contains("loaded\t5 vectors")), Ok(true), "check stats");
+
+        assert!(session.execute("sim король").is_err(), "check arguments");
+        assert!(session.execute("nn король many").is_err(), "check count");
+        assert!(session.execute("whatever").is_err(), "check unknown command");
+        assert_eq!(session.execute(""), Ok(String::new()), "check empty line");
+    }
+
+    #[test]
+    fn test_completion() {
+        let completer = WordCompleter::new(test_connection());
+
+        assert_eq!(completer.candidates("an", 2), (0, vec!["analogy".to_string()]), "check command");
+        assert_eq!(completer.candidates("use fa", 6), (4, vec!["fasttext".to_string()]), "check kind");
+        assert_eq!(completer.candidates("sim король коро", 25), (17, vec!["королева".to_string(), "король".to_string()]), "check words");
+        assert_eq!(completer.candidates("nn ", 3), (3, vec![]), "check empty prefix");
+    }
+}
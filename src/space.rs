@@ -0,0 +1,326 @@
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use rayon::prelude::*;
+
+use db::connection::DsjConnection;
+use db::models::kind::Kind;
+use db::models::rank::ranked_words;
+use db::models::vector::kind_vectors;
+use db::models::word::{Word, words_list};
+
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+pub fn norm(a: &[f32]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let norms = norm(a) * norm(b);
+
+    if norms > 0.0 {
+        dot(a, b) / norms
+    } else {
+        0.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Neighbor {
+    pub row: usize,
+    pub similarity: f32,
+}
+
+// reversed order keeps the least similar candidate on top of a heap
+struct Candidate(Neighbor);
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Candidate) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Candidate) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Candidate) -> Ordering {
+        other.0.similarity.partial_cmp(&self.0.similarity)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| self.0.row.cmp(&other.0.row))
+    }
+}
+
+struct TopK {
+    top: usize,
+    heap: BinaryHeap<Candidate>,
+}
+
+impl TopK {
+    fn new(top: usize) -> TopK {
+        TopK {
+            top,
+            heap: BinaryHeap::with_capacity(top + 1),
+        }
+    }
+
+    fn push(mut self, neighbor: Neighbor) -> TopK {
+        if neighbor.similarity.is_nan() || self.top == 0 {
+            return self;
+        }
+
+        self.heap.push(Candidate(neighbor));
+
+        if self.heap.len() > self.top {
+            self.heap.pop();
+        }
+
+        self
+    }
+
+    fn merge(self, other: TopK) -> TopK {
+        other.heap.into_iter().fold(self, |top, candidate| top.push(candidate.0))
+    }
+
+    fn into_vec(self) -> Vec<Neighbor> {
+        self.heap.into_sorted_vec().into_iter().map(|candidate| candidate.0).collect()
+    }
+}
+
+/// Vectors of a kind loaded to memory with precomputed norms.
+pub struct Space {
+    words: Vec<Word>,
+    rows: HashMap<i32, usize>,
+    dim: usize,
+    points: Vec<f32>,
+    norms: Vec<f32>,
+}
+
+impl Space {
+    pub fn new(words: Vec<Word>, vectors: Vec<Vec<f32>>) -> Space {
+        let dim = vectors.first().map(|vec| vec.len()).unwrap_or_default();
+
+        let mut space = Space {
+            words: Vec::with_capacity(words.len()),
+            rows: HashMap::with_capacity(words.len()),
+            dim,
+            points: Vec::with_capacity(words.len() * dim),
+            norms: Vec::with_capacity(words.len()),
+        };
+
+        for (word, vec) in words.into_iter().zip(vectors.into_iter()) {
+            if vec.len() != dim {
+                println!("skip '{}' with dimension {} instead of {}", word.word, vec.len(), dim);
+                continue;
+            }
+
+            space.rows.insert(word.id, space.words.len());
+            space.norms.push(norm(&vec));
+            space.points.extend_from_slice(&vec);
+            space.words.push(word);
+        }
+
+        space
+    }
+
+    /// Loads a kind in the file order, `limit` keeps only the top N ranked words.
+    pub fn load(conn: &DsjConnection, kind: &Kind, limit: Option<i64>) -> Result<Space, String> {
+        let mut vectors: HashMap<i32, Vec<f32>> = match kind_vectors(conn, kind) {
+            Some(vectors) => vectors.into_iter().collect(),
+            None => return Err(format!("failed to load vectors of '{}'", kind.name)),
+        };
+
+        let words = kind_words(conn, kind, limit, &vectors)?;
+
+        let rows = words.iter()
+            .map(|word| vectors.remove(&word.id).unwrap_or_default())
+            .collect();
+
+        Ok(Space::new(words, rows))
+    }
+
+    pub fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    pub fn dim(&self) -> usize {
+        self.dim
+    }
+
+    pub fn word(&self, row: usize) -> &Word {
+        &self.words[row]
+    }
+
+    pub fn row(&self, word_id: i32) -> Option<usize> {
+        self.rows.get(&word_id).cloned()
+    }
+
+    pub fn vector(&self, row: usize) -> &[f32] {
+        &self.points[row * self.dim..(row + 1) * self.dim]
+    }
+
+    pub fn norm(&self, row: usize) -> f32 {
+        self.norms[row]
+    }
+
+    /// FNV-1a hash of word ids and points, it ties derived data to vectors.
+    pub fn checksum(&self) -> u64 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+
+        {
+            let mut update = |value: u32| for shift in [0u32, 8, 16, 24].iter() {
+                hash ^= ((value >> shift) & 0xff) as u64;
+                hash = hash.wrapping_mul(0x0100_0000_01b3);
+            };
+
+            for (row, word) in self.words.iter().enumerate() {
+                update(word.id as u32);
+
+                for point in self.vector(row).iter() {
+                    update(point.to_bits());
+                }
+            }
+        }
+
+        hash
+    }
+
+    pub fn similarity(&self, query: &[f32], query_norm: f32, row: usize) -> f32 {
+        let norms = query_norm * self.norms[row];
+
+        if norms > 0.0 {
+            dot(query, self.vector(row)) / norms
+        } else {
+            0.0
+        }
+    }
+
+    /// Exact cosine similarity search, rows of `exclude` are skipped.
+    pub fn neighbors(&self, query: &[f32], top: usize, exclude: &[usize]) -> Vec<Neighbor> {
+        let query_norm = norm(query);
+
+        self.rank_by(top, exclude, |row| self.similarity(query, query_norm, row))
+    }
+
+    /// Ranks all rows by a score in parallel and keeps the `top` best.
+    pub fn rank_by<F>(&self, top: usize, exclude: &[usize], score: F) -> Vec<Neighbor>
+        where F: Fn(usize) -> f32 + Sync
+    {
+        rank(self.len(), top, exclude, score)
+    }
+}
+
+/// Ranks rows from 0 to `len` by a score in parallel and keeps the `top` best.
+pub fn rank<F>(len: usize, top: usize, exclude: &[usize], score: F) -> Vec<Neighbor>
+    where F: Fn(usize) -> f32 + Sync
+{
+    (0..len).into_par_iter()
+        .filter(|row| !exclude.contains(row))
+        .map(|row| Neighbor {
+            row,
+            similarity: score(row),
+        })
+        .fold(|| TopK::new(top), |top, neighbor| top.push(neighbor))
+        .reduce(|| TopK::new(top), |a, b| a.merge(b))
+        .into_vec()
+}
+
+/// Words of a kind in the file order which have rows, `limit` keeps only the top N ranked words.
+pub fn kind_words<T>(conn: &DsjConnection, kind: &Kind, limit: Option<i64>, rows: &HashMap<i32, T>) -> Result<Vec<Word>, String> {
+    let words = match ranked_words(conn, kind, limit) {
+        Some(ranked) => ranked.into_iter().map(|(word, _)| word).collect::<Vec<_>>(),
+        None => return Err(format!("failed to load words of '{}'", kind.name)),
+    };
+
+    if !words.is_empty() {
+        return Ok(words);
+    }
+
+    // kinds imported without ranks keep the order of word ids
+    Ok(words_list(conn).unwrap_or_default()
+        .into_iter()
+        .filter(|word| rows.contains_key(&word.id))
+        .take(limit.unwrap_or(i64::max_value()) as usize)
+        .collect())
+}
+
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+
+    pub fn test_space() -> Space {
+        let words = vec!["король", "королева", "мужчина", "женщина", "яблоко", "груша"];
+        let vectors = vec![
+            vec![0.9f32, 0.8, 0.1, 0.0],
+            vec![0.9f32, 0.1, 0.8, 0.0],
+            vec![0.1f32, 0.9, 0.0, 0.1],
+            vec![0.1f32, 0.1, 0.9, 0.1],
+            vec![0.0f32, 0.0, 0.1, 0.9],
+            vec![0.0f32, 0.1, 0.0, 0.8],
+        ];
+
+        Space::new(
+            words.iter().enumerate().map(|(id, &word)| Word {
+                id: id as i32 + 1,
+                word: word.to_string(),
+                lang: "ru".to_string(),
+            }).collect(),
+            vectors,
+        )
+    }
+
+    #[test]
+    fn test_vector_math() {
+        assert_eq!(dot(&[1.0f32, 2.0, 3.0], &[4.0f32, -5.0, 6.0]), 12.0, "check dot");
+        assert_eq!(norm(&[3.0f32, 4.0]), 5.0, "check norm");
+        assert_eq!(cosine(&[1.0f32, 0.0], &[2.0f32, 0.0]), 1.0, "check cosine");
+        assert_eq!(cosine(&[1.0f32, 0.0], &[0.0f32, 0.0]), 0.0, "check cosine of zero vector");
+    }
+
+    #[test]
+    fn test_space_rows() {
+        let space = test_space();
+
+        assert_eq!((space.len(), space.vector(0).len()), (6, 4), "check size");
+        assert_eq!(space.row(3), Some(2), "check row by word id");
+        assert_eq!(space.vector(4), &[0.0f32, 0.0, 0.1, 0.9], "check vector");
+        assert_eq!(space.norm(4), norm(&[0.0f32, 0.0, 0.1, 0.9]), "check norm");
+        assert_eq!(space.checksum(), test_space().checksum(), "check stable checksum");
+
+        let space = Space::new(
+            vec![Word::default(), Word { id: 1, ..Word::default() }],
+            vec![vec![1.0f32, 2.0], vec![1.0f32]],
+        );
+
+        assert_eq!(space.len(), 1, "check inconsistent dimension");
+        assert!(space.checksum() != test_space().checksum(), "check checksum of other vectors");
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let space = test_space();
+        let query = space.vector(4).to_vec();
+
+        assert!((space.similarity(&query, norm(&query), 4) - 1.0).abs() < 1e-6, "check self similarity");
+        assert_eq!(space.similarity(&query, 0.0, 5), 0.0, "check zero norm");
+
+        let neighbors = space.neighbors(&query, 3, &[4]);
+
+        assert_eq!(
+            neighbors.iter().map(|neighbor| space.word(neighbor.row).word.as_str()).collect::<Vec<_>>(),
+            vec!["груша", "женщина", "мужчина"],
+            "check order"
+        );
+
+        assert!(neighbors.windows(2).all(|pair| pair[0].similarity >= pair[1].similarity), "check similarity order");
+        assert_eq!(space.neighbors(&query, 10, &[]).len(), 6, "check top larger than space");
+        assert_eq!(space.neighbors(&query, 0, &[]).len(), 0, "check empty top");
+        assert_eq!(space.neighbors(&query, 1, &[])[0].row, 4, "check the word itself");
+    }
+}
@@ -0,0 +1,90 @@
+// kind properties of imported n-gram buckets
+pub const MINN: &str = "minn";
+pub const MAXN: &str = "maxn";
+pub const BUCKET: &str = "bucket";
+
+/// Character n-grams of fastText: a word is wrapped with '<' and '>',
+/// n-grams of `minn..maxn` code points are hashed to `bucket` rows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NGrams {
+    pub minn: usize,
+    pub maxn: usize,
+    pub bucket: u32,
+}
+
+/// FNV-1a over bytes sign extended as in fastText, where `char` is signed.
+pub fn hash(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(2_166_136_261u32, |hash, &byte| {
+        (hash ^ (byte as i8 as i32 as u32)).wrapping_mul(16_777_619)
+    })
+}
+
+impl NGrams {
+    pub fn buckets(&self, word: &str) -> Vec<u32> {
+        if self.bucket == 0 || self.maxn == 0 {
+            return Vec::new();
+        }
+
+        let word = format!("<{}>", word);
+        let starts = word.char_indices().map(|(start, _)| start).collect::<Vec<_>>();
+        let mut buckets = Vec::new();
+
+        for i in 0..starts.len() {
+            for n in self.minn.max(1)..self.maxn + 1 {
+                let j = i + n;
+
+                if j > starts.len() {
+                    break;
+                }
+
+                // a single '<' or '>' isn't an n-gram
+                if n == 1 && (i == 0 || j == starts.len()) {
+                    continue;
+                }
+
+                let end = starts.get(j).cloned().unwrap_or(word.len());
+
+                buckets.push(hash(word[starts[i]..end].as_bytes()) % self.bucket);
+            }
+        }
+
+        buckets
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+
+    #[test]
+    fn test_hash() {
+        assert_eq!(hash(b""), 2_166_136_261, "check offset basis");
+        assert_eq!(hash(b"a"), 0xe40c_292c, "check ascii");
+        // bytes above 0x7f are sign extended, the plain FNV-1a of "ё" is 0x3ac0a717
+        assert_eq!(hash("ё".as_bytes()), 0x44d7_e317, "check sign extension");
+    }
+
+    #[test]
+    fn test_buckets() {
+        let ngrams = NGrams {
+            minn: 3,
+            maxn: 4,
+            bucket: 2_000_000,
+        };
+
+        let expected = ["<ми", "<мир", "мир", "мир>", "ир>"].iter()
+            .map(|ngram| hash(ngram.as_bytes()) % 2_000_000)
+            .collect::<Vec<_>>();
+
+        assert_eq!(ngrams.buckets("мир"), expected, "check n-grams");
+
+        let unigrams = NGrams {
+            minn: 1,
+            maxn: 1,
+            bucket: 10,
+        };
+
+        assert_eq!(unigrams.buckets("ab").len(), 2, "check skipped '<' and '>'");
+        assert_eq!(NGrams { bucket: 0, ..ngrams }.buckets("мир"), Vec::<u32>::new(), "check without buckets");
+    }
+}
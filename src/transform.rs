@@ -0,0 +1,471 @@
+use std::fs::File;
+use std::io::Read;
+use serde_json::{self, Value};
+
+use db::connection::DsjConnection;
+use db::models::kind::Kind;
+use db::models::property::{get_property, set_property};
+use linalg::{Moments, symmetric_eigen};
+use space::{dot, norm};
+
+// a kind property of transforms applied at import
+pub const TRANSFORM: &str = "transform";
+
+/// A transform of imported vectors, centering and all-but-the-top are fitted by a pass over vectors,
+/// a linear map is learned by `align`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    Normalize,
+    Center(Option<Vec<f32>>),
+    // "All-but-the-Top: Simple and Effective Postprocessing for Word Representations",
+    // a mean and top principal components to remove
+    AllButTheTop {
+        components: usize,
+        fitted: Option<(Vec<f32>, Vec<Vec<f32>>)>,
+    },
+    // a mean, principal components to project onto and their shares of the total variance
+    Pca {
+        dims: usize,
+        fitted: Option<(Vec<f32>, Vec<Vec<f32>>, Vec<f32>)>,
+    },
+    // rows of a matrix multiplied by a vector
+    Map(Vec<Vec<f32>>),
+}
+
+fn read_mean(path: &str) -> Result<Vec<f32>, String> {
+    let mut text = String::new();
+
+    File::open(path)
+        .and_then(|mut file| file.read_to_string(&mut text))
+        .map_err(|err| format!("failed to read a mean vector '{}': {}", path, err))?;
+
+    let mean = text.split_whitespace()
+        .map(|value| value.parse::<f32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| format!("failed to parse a mean vector '{}': {}", path, err))?;
+
+    if mean.is_empty() {
+        return Err(format!("empty mean vector '{}'", path));
+    }
+
+    Ok(mean)
+}
+
+fn subtract(vec: &mut [f32], mean: &[f32]) -> Result<(), String> {
+    if vec.len() != mean.len() {
+        return Err(format!("dimension {} doesn't match a mean of dimension {}", vec.len(), mean.len()));
+    }
+
+    for (value, mean) in vec.iter_mut().zip(mean.iter()) {
+        *value -= *mean;
+    }
+
+    Ok(())
+}
+
+fn floats(value: &Value) -> Option<Vec<f32>> {
+    value.as_array()?
+        .iter()
+        .map(|value| value.as_f64().map(|value| value as f32))
+        .collect()
+}
+
+impl Transform {
+    pub fn parse(name: &str) -> Result<Transform, String> {
+        let (name, param) = match name.find(':') {
+            Some(pos) => (&name[..pos], Some(&name[pos + 1..])),
+            None => (name, None),
+        };
+
+        match (name, param) {
+            ("l2", None) | ("normalize", None) => Ok(Transform::Normalize),
+            ("center", None) => Ok(Transform::Center(None)),
+            ("center", Some(path)) => Ok(Transform::Center(Some(read_mean(path)?))),
+            ("abtt", Some(components)) => match components.parse::<usize>() {
+                Ok(components) if components > 0 => Ok(Transform::AllButTheTop {
+                    components,
+                    fitted: None,
+                }),
+                _ => Err(format!("'abtt' expects a positive number of components, got '{}'", components)),
+            },
+            ("abtt", None) => Err("'abtt' expects a number of components as 'abtt:D'".to_string()),
+            ("pca", Some(dims)) => match dims.parse::<usize>() {
+                Ok(dims) if dims > 0 => Ok(Transform::Pca {
+                    dims,
+                    fitted: None,
+                }),
+                _ => Err(format!("'pca' expects a positive number of dimensions, got '{}'", dims)),
+            },
+            ("pca", None) => Err("'pca' expects a number of dimensions as 'pca:D'".to_string()),
+            _ => Err(format!("unknown transform '{}', expected l2, center, center:FILE, abtt:D or pca:D", name)),
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match *self {
+            Transform::Normalize => "l2".to_string(),
+            Transform::Center(_) => "center".to_string(),
+            Transform::AllButTheTop { components, .. } => format!("abtt:{}", components),
+            Transform::Pca { dims, .. } => format!("pca:{}", dims),
+            Transform::Map(ref rows) => format!("map:{}", rows.len()),
+        }
+    }
+
+    pub fn is_fitted(&self) -> bool {
+        match *self {
+            Transform::Normalize => true,
+            Transform::Center(ref mean) => mean.is_some(),
+            Transform::AllButTheTop { ref fitted, .. } => fitted.is_some(),
+            Transform::Pca { ref fitted, .. } => fitted.is_some(),
+            Transform::Map(_) => true,
+        }
+    }
+
+    /// A share of the total variance kept by a fitted PCA.
+    pub fn explained(&self) -> Option<f32> {
+        match *self {
+            Transform::Pca { fitted: Some((_, _, ref explained)), .. } => Some(explained.iter().sum()),
+            _ => None,
+        }
+    }
+
+    fn apply(&self, vec: &mut Vec<f32>) -> Result<(), String> {
+        match *self {
+            Transform::Normalize => {
+                let length = norm(vec);
+
+                if length > 0.0 {
+                    vec.iter_mut().for_each(|value| *value /= length);
+                }
+            }
+            Transform::Center(Some(ref mean)) => subtract(vec, mean)?,
+            Transform::AllButTheTop { fitted: Some((ref mean, ref components)), .. } => {
+                subtract(vec, mean)?;
+
+                for component in components.iter() {
+                    let projection = dot(vec, component);
+
+                    for (value, &direction) in vec.iter_mut().zip(component.iter()) {
+                        *value -= projection * direction;
+                    }
+                }
+            }
+            Transform::Pca { fitted: Some((ref mean, ref components, _)), .. } => {
+                subtract(vec, mean)?;
+
+                *vec = components.iter().map(|component| dot(vec, component)).collect();
+            }
+            Transform::Map(ref rows) => {
+                if rows.first().map(|row| row.len() != vec.len()).unwrap_or(true) {
+                    return Err(format!("dimension {} doesn't match a map of dimension {}", vec.len(), rows.first().map(|row| row.len()).unwrap_or_default()));
+                }
+
+                *vec = rows.iter().map(|row| dot(row, vec)).collect();
+            }
+            _ => return Err(format!("transform '{}' isn't fitted", self.name())),
+        }
+
+        Ok(())
+    }
+
+    fn fit(&mut self, moments: &Moments) {
+        let mean = moments.mean().into_iter().map(|value| value as f32).collect::<Vec<_>>();
+
+        match *self {
+            Transform::Center(ref mut fitted) => *fitted = Some(mean),
+            Transform::AllButTheTop { components, ref mut fitted } => {
+                let (_, vectors) = symmetric_eigen(&moments.covariance().unwrap_or_default());
+
+                let top = vectors.into_iter()
+                    .take(components)
+                    .map(|vector| vector.into_iter().map(|value| value as f32).collect())
+                    .collect();
+
+                *fitted = Some((mean, top));
+            }
+            Transform::Pca { dims, ref mut fitted } => {
+                let (values, vectors) = symmetric_eigen(&moments.covariance().unwrap_or_default());
+                let total = values.iter().map(|value| value.max(0.0)).sum::<f64>().max(::std::f64::MIN_POSITIVE);
+
+                let top = vectors.into_iter()
+                    .take(dims)
+                    .map(|vector| vector.into_iter().map(|value| value as f32).collect())
+                    .collect();
+
+                let explained = values.into_iter()
+                    .take(dims)
+                    .map(|value| (value.max(0.0) / total) as f32)
+                    .collect();
+
+                *fitted = Some((mean, top, explained));
+            }
+            Transform::Normalize | Transform::Map(_) => {}
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match *self {
+            Transform::Normalize => json!({"name": "l2"}),
+            Transform::Center(ref mean) => json!({"name": "center", "mean": mean}),
+            Transform::AllButTheTop { components, ref fitted } => json!({
+                "name": "abtt",
+                "mean": fitted.as_ref().map(|&(ref mean, _)| mean),
+                "components": fitted.as_ref().map(|&(_, ref top)| top),
+                "count": components,
+            }),
+            Transform::Pca { dims, ref fitted } => json!({
+                "name": "pca",
+                "mean": fitted.as_ref().map(|&(ref mean, _, _)| mean),
+                "components": fitted.as_ref().map(|&(_, ref top, _)| top),
+                "explained": fitted.as_ref().map(|&(_, _, ref explained)| explained),
+                "dims": dims,
+            }),
+            Transform::Map(ref rows) => json!({"name": "map", "rows": rows}),
+        }
+    }
+
+    fn from_json(value: &Value) -> Option<Transform> {
+        match value.get("name")?.as_str()? {
+            "l2" => Some(Transform::Normalize),
+            "center" => Some(Transform::Center(Some(floats(value.get("mean")?)?))),
+            "abtt" => {
+                let top = value.get("components")?
+                    .as_array()?
+                    .iter()
+                    .map(floats)
+                    .collect::<Option<Vec<_>>>()?;
+
+                Some(Transform::AllButTheTop {
+                    components: value.get("count")?.as_u64()? as usize,
+                    fitted: Some((floats(value.get("mean")?)?, top)),
+                })
+            }
+            "pca" => {
+                let top = value.get("components")?
+                    .as_array()?
+                    .iter()
+                    .map(floats)
+                    .collect::<Option<Vec<_>>>()?;
+
+                Some(Transform::Pca {
+                    dims: value.get("dims")?.as_u64()? as usize,
+                    fitted: Some((floats(value.get("mean")?)?, top, floats(value.get("explained")?)?)),
+                })
+            }
+            "map" => value.get("rows")?
+                .as_array()?
+                .iter()
+                .map(floats)
+                .collect::<Option<Vec<_>>>()
+                .map(Transform::Map),
+            _ => None,
+        }
+    }
+}
+
+/// Transforms applied in order to every imported vector, e.g. "center,abtt:2,l2".
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Pipeline {
+    transforms: Vec<Transform>,
+}
+
+impl Pipeline {
+    pub fn parse(chain: &str) -> Result<Pipeline, String> {
+        let transforms = chain.split(',')
+            .map(|name| name.trim())
+            .filter(|name| !name.is_empty())
+            .map(Transform::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if transforms.is_empty() {
+            return Err("no transforms".to_string());
+        }
+
+        Ok(Pipeline {
+            transforms,
+        })
+    }
+
+    pub fn new(transforms: Vec<Transform>) -> Pipeline {
+        Pipeline {
+            transforms,
+        }
+    }
+
+    pub fn name(&self) -> String {
+        self.transforms.iter().map(|transform| transform.name()).collect::<Vec<_>>().join(",")
+    }
+
+    /// Passes over vectors to fit the pipeline.
+    pub fn passes(&self) -> usize {
+        self.transforms.iter().filter(|transform| !transform.is_fitted()).count()
+    }
+
+    /// Fits the first unfitted transform by vectors passed through transforms before it,
+    /// returns a number of vectors used.
+    pub fn fit(&mut self, vectors: impl Iterator<Item=Vec<f32>>) -> Result<u64, String> {
+        let position = match self.transforms.iter().position(|transform| !transform.is_fitted()) {
+            Some(position) => position,
+            None => return Ok(0),
+        };
+
+        let covariance = match self.transforms[position] {
+            Transform::AllButTheTop { .. } | Transform::Pca { .. } => true,
+            _ => false,
+        };
+
+        let mut moments: Option<Moments> = None;
+
+        for mut vec in vectors {
+            if self.transforms[..position].iter().any(|transform| transform.apply(&mut vec).is_err()) {
+                continue;
+            }
+
+            moments.get_or_insert_with(|| Moments::new(vec.len(), covariance)).add(&vec);
+        }
+
+        match moments {
+            Some(ref moments) if moments.count() > 0 => {
+                self.transforms[position].fit(moments);
+                Ok(moments.count())
+            }
+            _ => Err(format!("no vectors to fit '{}'", self.transforms[position].name())),
+        }
+    }
+
+    pub fn apply(&self, vec: &mut Vec<f32>) -> Result<(), String> {
+        self.transforms.iter().map(|transform| transform.apply(vec)).collect()
+    }
+
+    /// Transforms of another pipeline applied after ones of this pipeline.
+    pub fn append(&mut self, other: Pipeline) {
+        self.transforms.extend(other.transforms);
+    }
+
+    pub fn transforms(&self) -> &[Transform] {
+        &self.transforms
+    }
+
+    pub fn to_json(&self) -> Value {
+        Value::Array(self.transforms.iter().map(|transform| transform.to_json()).collect())
+    }
+
+    pub fn from_json(value: &Value) -> Option<Pipeline> {
+        let transforms = value.as_array()?
+            .iter()
+            .map(Transform::from_json)
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Pipeline {
+            transforms,
+        })
+    }
+
+    pub fn save(&self, conn: &DsjConnection, kind: &Kind) -> bool {
+        set_property(conn, kind, TRANSFORM, &self.to_json().to_string())
+    }
+
+    /// Transforms applied to vectors of a kind at import.
+    pub fn load(conn: &DsjConnection, kind: &Kind) -> Option<Pipeline> {
+        let value = get_property(conn, kind, TRANSFORM)?;
+
+        Pipeline::from_json(&serde_json::from_str(&value).ok()?)
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::testing::test_connection;
+    use db::models::kind::get_kind;
+
+    fn parse(chain: &str) -> Pipeline {
+        match Pipeline::parse(chain) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                assert!(false, "failed to parse '{}': {}", chain, err);
+                Pipeline::default()
+            }
+        }
+    }
+
+    fn close(a: &[f32], b: &[f32]) -> bool {
+        a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| (a - b).abs() < 1e-5)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Pipeline::parse("center, abtt:2,normalize").map(|pipeline| pipeline.name()), Ok("center,abtt:2,l2".to_string()), "check chain");
+        assert_eq!(Pipeline::parse("center,abtt:2,l2").map(|pipeline| pipeline.passes()), Ok(2), "check passes");
+        assert!(Pipeline::parse("abtt").is_err(), "check abtt without components");
+        assert!(Pipeline::parse("abtt:0").is_err(), "check abtt with no components");
+        assert!(Pipeline::parse("pca").is_err(), "check pca without dimensions");
+        assert!(Pipeline::parse("whiten").is_err(), "check unknown transform");
+        assert!(Pipeline::parse("").is_err(), "check empty chain");
+        assert!(Pipeline::parse("center:/nonexistent/mean.txt").is_err(), "check missing mean file");
+    }
+
+    #[test]
+    fn test_fit() {
+        let vectors = vec![vec![1.0, 2.0], vec![3.0, 2.0], vec![2.0, 5.0]];
+        let mut pipeline = parse("center,l2");
+
+        assert_eq!(pipeline.fit(vectors.clone().into_iter()), Ok(3), "check fit");
+        assert_eq!(pipeline.passes(), 0, "check fitted");
+
+        let mut vec = vec![5.0, 3.0];
+
+        assert!(pipeline.apply(&mut vec).is_ok() && close(&vec, &[1.0, 0.0]), "check apply {:?}", vec);
+        assert!(pipeline.apply(&mut vec![1.0]).is_err(), "check dimension");
+
+        // all points on a line lose everything but the mean after removing the top component
+        let line = vec![vec![1.0, 1.0], vec![2.0, 2.0], vec![3.0, 3.0]];
+        let mut pipeline = parse("abtt:1");
+
+        assert_eq!(pipeline.fit(line.into_iter()), Ok(3), "check abtt fit");
+
+        let mut vec = vec![5.0, 5.0];
+        let mut other = vec![3.0, 1.0];
+
+        assert!(pipeline.apply(&mut vec).is_ok() && close(&vec, &[0.0, 0.0]), "check abtt on the line {:?}", vec);
+        assert!(pipeline.apply(&mut other).is_ok() && close(&other, &[1.0, -1.0]), "check abtt off the line {:?}", other);
+
+        // points on a line keep all of their variance in one dimension
+        let line = vec![vec![1.0, 1.0, 0.0], vec![2.0, 2.0, 0.0], vec![3.0, 3.0, 0.0]];
+        let mut pipeline = parse("pca:1");
+
+        assert_eq!(pipeline.fit(line.into_iter()), Ok(3), "check pca fit");
+        assert!((pipeline.transforms()[0].explained().unwrap_or_default() - 1.0).abs() < 1e-5, "check explained variance");
+
+        let mut vec = vec![4.0, 4.0, 0.0];
+
+        assert!(pipeline.apply(&mut vec).is_ok() && vec.len() == 1 && (vec[0].abs() - 8f32.sqrt()).abs() < 1e-5, "check pca {:?}", vec);
+
+        assert!(parse("center").fit(Vec::new().into_iter()).is_err(), "check no vectors");
+    }
+
+    #[test]
+    fn test_save() {
+        let conn = test_connection();
+        let kind = get_kind(&conn, "fasttext").unwrap_or_default();
+        let mut pipeline = parse("center,abtt:1,l2");
+
+        let vectors = vec![vec![1.0, 0.0], vec![0.0, 1.0], vec![2.0, 1.0]];
+
+        while pipeline.passes() > 0 {
+            assert!(pipeline.fit(vectors.clone().into_iter()).is_ok(), "check fit");
+        }
+
+        let swap = Pipeline::new(vec![Transform::Map(vec![vec![0.0, 1.0], vec![1.0, 0.0]])]);
+        let mut vec = vec![3.0, 1.0];
+
+        assert!(swap.apply(&mut vec).is_ok() && vec == vec![1.0, 3.0], "check map {:?}", vec);
+        assert!(swap.apply(&mut vec![1.0]).is_err(), "check map dimension");
+
+        pipeline.append(swap);
+
+        assert_eq!(Pipeline::load(&conn, &kind), None, "check missing");
+        assert!(pipeline.save(&conn, &kind), "check save");
+        assert_eq!(Pipeline::load(&conn, &kind), Some(pipeline), "check load");
+    }
+}
@@ -0,0 +1,98 @@
+use std::cmp::Ordering;
+use rayon::prelude::*;
+
+use space::{Neighbor, Space};
+
+pub const METHODS: [&str; 2] = ["csls", "cosine"];
+
+/// Scoring of translations of a mapped source vector among target words.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Cosine,
+    // "Word Translation Without Parallel Data": 2 cos(x, y) - r_T(x) - r_S(y),
+    // neighbourhood densities r penalize hubs similar to many words
+    Csls,
+}
+
+impl Method {
+    pub fn parse(name: &str) -> Option<Method> {
+        match name {
+            "cosine" => Some(Method::Cosine),
+            "csls" => Some(Method::Csls),
+            _ => None,
+        }
+    }
+}
+
+/// A mean similarity of a vector to its `k` nearest rows of a space.
+pub fn density(space: &Space, query: &[f32], k: usize) -> f32 {
+    let neighbors = space.neighbors(query, k, &[]);
+
+    if neighbors.is_empty() {
+        return 0.0;
+    }
+
+    neighbors.iter().map(|neighbor| neighbor.similarity).sum::<f32>() / neighbors.len() as f32
+}
+
+/// The `top` target words for a query of the source space, CSLS reranks `candidates` nearest ones by cosine
+/// with densities of `k` neighbours.
+pub fn translate(query: &[f32], source: &Space, target: &Space, method: Method, top: usize, k: usize, candidates: usize) -> Vec<Neighbor> {
+    if method == Method::Cosine {
+        return target.neighbors(query, top, &[]);
+    }
+
+    let query_density = density(target, query, k);
+
+    let mut neighbors = target.neighbors(query, candidates.max(top), &[])
+        .into_par_iter()
+        .map(|neighbor| Neighbor {
+            row: neighbor.row,
+            similarity: 2.0 * neighbor.similarity - query_density - density(source, target.vector(neighbor.row), k),
+        })
+        .collect::<Vec<_>>();
+
+    neighbors.sort_by(|a, b| b.similarity.partial_cmp(&a.similarity).unwrap_or(Ordering::Equal).then_with(|| a.row.cmp(&b.row)));
+    neighbors.truncate(top);
+    neighbors
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use db::models::word::Word;
+
+    // unit vectors at angles in degrees
+    fn space(angles: &[f32]) -> Space {
+        Space::new(
+            angles.iter().enumerate().map(|(id, angle)| Word {
+                id: id as i32 + 1,
+                word: angle.to_string(),
+                lang: String::new(),
+            }).collect(),
+            angles.iter().map(|angle| vec![angle.to_radians().cos(), angle.to_radians().sin()]).collect(),
+        )
+    }
+
+    #[test]
+    fn test_translate() {
+        let source = space(&[25.0, 40.0, 45.0, 50.0]);
+        let target = space(&[0.0, 45.0, 90.0]);
+        let query = source.vector(0);
+
+        assert_eq!(Method::parse("csls"), Some(Method::Csls), "check parse");
+
+        // the target word at 45 degrees is a hub close to most source words
+        let cosine = translate(query, &source, &target, Method::Cosine, 3, 2, 10);
+        let csls = translate(query, &source, &target, Method::Csls, 3, 2, 10);
+
+        assert_eq!(cosine.iter().map(|neighbor| neighbor.row).collect::<Vec<_>>(), vec![1, 0, 2], "check cosine");
+        assert_eq!(csls.iter().map(|neighbor| neighbor.row).collect::<Vec<_>>(), vec![0, 1, 2], "check csls");
+
+        let expected = 2.0 * 25f32.to_radians().cos() - (20f32.to_radians().cos() + 25f32.to_radians().cos()) / 2.0 -
+            (25f32.to_radians().cos() + 40f32.to_radians().cos()) / 2.0;
+
+        assert!((csls[0].similarity - expected).abs() < 1e-5, "check csls score {}", csls[0].similarity);
+        assert_eq!(translate(query, &source, &target, Method::Csls, 1, 2, 0).len(), 1, "check top");
+    }
+}
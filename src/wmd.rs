@@ -0,0 +1,292 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use db::connection::DsjConnection;
+use embed::tokenize;
+use lookup::Lookup;
+
+/// A distinct word of a text with its count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub word: String,
+    pub vec: Vec<f32>,
+    pub count: i64,
+}
+
+/// A normalized bag of words of a text, a weight of a term is its share of all counted tokens.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Document {
+    pub terms: Vec<Term>,
+    pub missing: Vec<String>,
+}
+
+impl Document {
+    /// Tokens found as the same stored word are one term, stop words are compared in lowercase.
+    pub fn new(conn: &DsjConnection, lookup: &Lookup, text: &str, lang: Option<&str>, stop_words: &HashSet<String>) -> Document {
+        let mut terms: Vec<Term> = Vec::new();
+        let mut positions: HashMap<String, usize> = HashMap::new();
+        let mut missing = Vec::new();
+
+        for token in tokenize(text).into_iter().filter(|token| !stop_words.contains(&token.to_lowercase())) {
+            let found = match lookup.find(conn, &token, lang) {
+                Some(found) => found,
+                None => {
+                    missing.push(token);
+                    continue;
+                }
+            };
+
+            let word = found.stored().unwrap_or(&token).to_string();
+
+            if let Some(&position) = positions.get(&word) {
+                terms[position].count += 1;
+                continue;
+            }
+
+            positions.insert(word.clone(), terms.len());
+            terms.push(Term {
+                word,
+                vec: found.vec,
+                count: 1,
+            });
+        }
+
+        Document {
+            terms,
+            missing,
+        }
+    }
+
+    pub fn total(&self) -> i64 {
+        self.terms.iter().map(|term| term.count).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
+
+fn euclidean(a: &[f32], b: &[f32]) -> f64 {
+    a.iter().zip(b.iter()).map(|(a, b)| ((a - b) * (a - b)) as f64).sum::<f64>().sqrt()
+}
+
+fn costs(a: &Document, b: &Document) -> Vec<Vec<f64>> {
+    a.terms.iter()
+        .map(|x| b.terms.iter().map(|y| euclidean(&x.vec, &y.vec)).collect())
+        .collect()
+}
+
+/// The relaxed WMD lower bound, every word moves to the nearest word of the other text only.
+pub fn relaxed(a: &Document, b: &Document) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+
+    let costs = costs(a, b);
+
+    let forward = a.terms.iter().enumerate()
+        .map(|(i, term)| term.count as f64 * costs[i].iter().cloned().fold(::std::f64::INFINITY, f64::min))
+        .sum::<f64>() / a.total() as f64;
+
+    let backward = b.terms.iter().enumerate()
+        .map(|(j, term)| term.count as f64 * costs.iter().map(|row| row[j]).fold(::std::f64::INFINITY, f64::min))
+        .sum::<f64>() / b.total() as f64;
+
+    Some(forward.max(backward))
+}
+
+struct Edge {
+    to: usize,
+    rev: usize,
+    cap: i64,
+    cost: f64,
+}
+
+// a minimum cost flow by successive shortest paths, Bellman-Ford handles negative residual costs
+struct Network {
+    edges: Vec<Vec<Edge>>,
+}
+
+impl Network {
+    fn new(nodes: usize) -> Network {
+        Network {
+            edges: (0..nodes).map(|_| Vec::new()).collect(),
+        }
+    }
+
+    fn add(&mut self, from: usize, to: usize, cap: i64, cost: f64) {
+        let (forward, backward) = (self.edges[to].len(), self.edges[from].len());
+
+        self.edges[from].push(Edge { to, rev: forward, cap, cost });
+        self.edges[to].push(Edge { to: from, rev: backward, cap: 0, cost: -cost });
+    }
+
+    fn shortest_path(&self, source: usize, sink: usize) -> Option<Vec<(usize, usize)>> {
+        let nodes = self.edges.len();
+        let mut dist = vec![::std::f64::INFINITY; nodes];
+        let mut prev: Vec<Option<(usize, usize)>> = vec![None; nodes];
+        let mut queued = vec![false; nodes];
+        let mut queue = VecDeque::new();
+
+        dist[source] = 0.0;
+        queue.push_back(source);
+
+        while let Some(node) = queue.pop_front() {
+            queued[node] = false;
+
+            for (index, edge) in self.edges[node].iter().enumerate() {
+                if edge.cap > 0 && dist[node] + edge.cost < dist[edge.to] - 1e-12 {
+                    dist[edge.to] = dist[node] + edge.cost;
+                    prev[edge.to] = Some((node, index));
+
+                    if !queued[edge.to] {
+                        queued[edge.to] = true;
+                        queue.push_back(edge.to);
+                    }
+                }
+            }
+        }
+
+        let mut path = Vec::new();
+        let mut node = sink;
+
+        while node != source {
+            let (from, index) = prev[node]?;
+
+            path.push((from, index));
+            node = from;
+        }
+
+        Some(path)
+    }
+
+    fn min_cost(&mut self, source: usize, sink: usize, flow: i64) -> Option<f64> {
+        let mut left = flow;
+        let mut cost = 0.0;
+
+        while left > 0 {
+            let path = self.shortest_path(source, sink)?;
+            let push = path.iter().map(|&(from, index)| self.edges[from][index].cap).fold(left, i64::min);
+
+            for &(from, index) in path.iter() {
+                let (to, rev) = (self.edges[from][index].to, self.edges[from][index].rev);
+
+                self.edges[from][index].cap -= push;
+                self.edges[to][rev].cap += push;
+                cost += push as f64 * self.edges[from][index].cost;
+            }
+
+            left -= push;
+        }
+
+        Some(cost)
+    }
+}
+
+/// Word Mover's Distance, the minimal cost of moving words of one text to words of another
+/// one by Euclidean distances of their vectors, "From Word Embeddings To Document Distances".
+pub fn distance(a: &Document, b: &Document) -> Option<f64> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+
+    let costs = costs(a, b);
+    let (n, m) = (a.terms.len(), b.terms.len());
+    let (source, sink) = (0, n + m + 1);
+
+    // shares of terms scaled by totals of both texts are integers
+    let (total_a, total_b) = (a.total(), b.total());
+    let mut network = Network::new(n + m + 2);
+
+    for (i, term) in a.terms.iter().enumerate() {
+        network.add(source, 1 + i, term.count * total_b, 0.0);
+    }
+
+    for (j, term) in b.terms.iter().enumerate() {
+        network.add(1 + n + j, sink, term.count * total_a, 0.0);
+    }
+
+    for i in 0..n {
+        for j in 0..m {
+            network.add(1 + i, 1 + n + j, total_a * total_b, costs[i][j]);
+        }
+    }
+
+    network.min_cost(source, sink, total_a * total_b)
+        .map(|cost| cost / (total_a * total_b) as f64)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use rand::{self, Rng};
+    use db::models::testing::test_connection;
+    use db::models::kind::get_kind;
+    use lookup::{STRATEGIES, Strategy};
+
+    fn document(terms: &[(&str, f32, i64)]) -> Document {
+        Document {
+            terms: terms.iter()
+                .map(|&(word, x, count)| Term {
+                    word: word.to_string(),
+                    vec: vec![x, 0.0],
+                    count,
+                })
+                .collect(),
+            missing: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_distance() {
+        let a = document(&[("a", 0.0, 1), ("b", 10.0, 1)]);
+        let b = document(&[("c", 1.0, 1), ("d", 9.0, 1)]);
+
+        assert_eq!(distance(&a, &a), Some(0.0), "check same text");
+        assert_eq!(distance(&a, &b).map(|d| (d * 1e6).round() / 1e6), Some(1.0), "check distance");
+        assert_eq!(relaxed(&a, &b).map(|d| (d * 1e6).round() / 1e6), Some(1.0), "check relaxed");
+        assert_eq!(distance(&a, &document(&[])), None, "check empty text");
+
+        // a word at 0 of count 2 and a word at 3 of count 1 move to words at 1 and 2
+        let a = document(&[("a", 0.0, 2), ("b", 3.0, 1)]);
+        let b = document(&[("c", 1.0, 1), ("d", 2.0, 1)]);
+
+        assert!((distance(&a, &b).unwrap_or_default() - 7.0 / 6.0).abs() < 1e-9, "check counts {:?}", distance(&a, &b));
+    }
+
+    #[test]
+    fn test_relaxed_bound() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..20 {
+            let a = (0..rng.gen_range(1, 6)).map(|_| ("a", rng.gen_range(-5.0, 5.0), rng.gen_range(1, 4))).collect::<Vec<_>>();
+            let b = (0..rng.gen_range(1, 6)).map(|_| ("b", rng.gen_range(-5.0, 5.0), rng.gen_range(1, 4))).collect::<Vec<_>>();
+            let (a, b) = (document(&a), document(&b));
+
+            match (relaxed(&a, &b), distance(&a, &b)) {
+                (Some(lower), Some(exact)) => assert!(lower <= exact + 1e-6, "check lower bound {} {} of {:?} {:?}", lower, exact, a, b),
+                _ => assert!(false, "failed to compute distances"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_document() {
+        let conn = test_connection();
+        let kind = get_kind(&conn, "fasttext").unwrap_or_default();
+        let lookup = Lookup::new(&conn, &kind, &Strategy::parse_chain(STRATEGIES).unwrap_or_default());
+        let stop_words = ["и".to_string()].iter().cloned().collect();
+
+        let document = Document::new(&conn, &lookup, "Король и король, и груша", None, &stop_words);
+
+        assert_eq!(document.terms.iter().map(|term| (term.word.as_str(), term.count)).collect::<Vec<_>>(), vec![("король", 2)], "check terms");
+        assert_eq!(document.missing, vec!["груша".to_string()], "check missing");
+
+        let other = Document::new(&conn, &lookup, "королева", None, &stop_words);
+        let apple = Document::new(&conn, &lookup, "яблоко", None, &stop_words);
+
+        match (distance(&document, &other), distance(&document, &apple)) {
+            (Some(queen), Some(apple)) => assert!(queen < apple, "check distances {} {}", queen, apple),
+            _ => assert!(false, "failed to compute distances"),
+        }
+    }
+}
@@ -1,60 +1,397 @@
+use std::cell::{Cell, RefCell};
 use std::fs::File;
 use std::io::BufReader;
+use std::{iter, mem};
 use std::rc::Rc;
-use super::{VectorFile, Iter};
+use super::{Source, Subwords, Record, VectorFile, Iter};
 use super::fasttext::FastText;
+use super::fasttextbin::{FastTextBin, SubwordRows};
 use super::gensim::Gensim;
 use super::glove::Glove;
+use super::numberbatch::Numberbatch;
+use super::safetensors::SafeTensors;
+use super::vocab::read_vocab;
 use super::word2vec::Word2Vec;
+use filter::{Filter, Filtered, Reason};
 use progressbar::IncSignal;
+use transform::Pipeline;
 
 macro_rules! file_iterator {
     ($kind: expr, $typ: ident, $file_path: ident, $signal: expr) => { match File::open(&$file_path) {
         Ok(file) => Some((
             $kind.to_string(),
             file.metadata().unwrap().len(),
-            $typ::new(BufReader::new(file), $signal).into_iter()
+            $typ::new(BufReader::new(file), $signal).into_iter(),
+            None,
         )),
         Err(_) => None
     }}
 }
 
+fn fasttext_bin_iterator(file_path: &str, signal: Rc<IncSignal>) -> Option<(String, u64, Iter, Option<Subwords>)> {
+    let file = File::open(file_path).ok()?;
+
+    let words = match FastTextBin::new(BufReader::new(file), Some(signal.clone())) {
+        Ok(words) => words,
+        Err(err) => {
+            println!("failed to read '{}': {}", file_path, err);
+            return None;
+        }
+    };
+
+    let max = words.header().matrix_end();
+    let ngrams = words.header().ngrams;
+
+    // n-gram buckets are read by another reader after words
+    let subwords = if ngrams.bucket > 0 && ngrams.maxn > 0 {
+        let file = File::open(file_path).ok()?;
+
+        match SubwordRows::new(BufReader::new(file), words.header(), Some(signal)) {
+            Ok(rows) => Some(Subwords {
+                ngrams,
+                rows: Box::new(rows),
+            }),
+            Err(err) => {
+                println!("failed to read '{}': {}", file_path, err);
+                return None;
+            }
+        }
+    } else {
+        None
+    };
+
+    Some(("fasttext".to_string(), max, words.into_iter(), subwords))
+}
+
+fn safetensors_iterator(file_path: &str, vocab_path: &str, tensor: &str, signal: Rc<IncSignal>) -> Option<(String, u64, Iter, Option<Subwords>)> {
+    let vocab = match read_vocab(vocab_path) {
+        Ok(vocab) => vocab,
+        Err(err) => {
+            println!("{}", err);
+            return None;
+        }
+    };
+
+    let file = File::open(file_path).ok()?;
+    let max = file.metadata().ok()?.len();
+
+    match SafeTensors::new(BufReader::new(file), vocab, tensor, Some(signal)) {
+        Ok(safetensors) => Some(("safetensors".to_string(), max, safetensors.into_iter(), None)),
+        Err(err) => {
+            println!("failed to read '{}': {}", file_path, err);
+            None
+        }
+    }
+}
+
+// progress of bytes read, or of records read when a limit may stop a file early,
+// bytes of a header are held back until records are iterated after a progress bar starts
+struct Progress {
+    signal: Rc<IncSignal>,
+    records: Cell<bool>,
+    pending: Cell<Option<u64>>,
+}
+
+impl Progress {
+    fn record(&self) {
+        if self.records.get() {
+            self.signal.inc(1);
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(delta) = self.pending.take() {
+            if !self.records.get() {
+                self.signal.inc(delta);
+            }
+        }
+    }
+}
+
+impl IncSignal for Progress {
+    fn inc(&self, delta: u64) {
+        if self.records.get() {
+            return;
+        }
+
+        match self.pending.get() {
+            Some(pending) => self.pending.set(Some(pending + delta)),
+            None => self.signal.inc(delta),
+        }
+    }
+}
+
+// records up to a limit, a record beyond it marks a file cut short
+struct Limited {
+    records: Iter,
+    limit: usize,
+    read: usize,
+    done: bool,
+    progress: Rc<Progress>,
+    filtered: Rc<RefCell<Filtered>>,
+}
+
+impl Iterator for Limited {
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Record> {
+        if self.done {
+            return None;
+        }
+
+        if self.read >= self.limit {
+            self.done = true;
+
+            if self.records.next().is_some() {
+                self.filtered.borrow_mut().cut(self.limit);
+            }
+
+            return None;
+        }
+
+        let record = self.records.next();
+
+        match record {
+            Some(_) => {
+                self.read += 1;
+                self.progress.record();
+            }
+            None => self.done = true,
+        }
+
+        record
+    }
+}
+
 pub struct DataIterator {
     kind: String,
+    lang: String,
     max: u64,
     iterator: Iter,
+    subwords: Option<Subwords>,
+    transform: Option<Pipeline>,
+    filtered: Rc<RefCell<Filtered>>,
+    progress: Rc<Progress>,
 }
 
 impl DataIterator {
-    pub fn make_vec(signal: Rc<IncSignal>, vec_files: &[VectorFile]) -> Vec<DataIterator> {
-        vec_files.iter().filter_map(|file_name| {
-            let result = match file_name {
-                &VectorFile::FastText(ref file_path) => file_iterator!("fasttext", FastText, file_path, Some(signal.clone())),
-                &VectorFile::Glove(ref file_path) => file_iterator!("glove", Glove, file_path, Some(signal.clone())),
-                &VectorFile::Word2Vec(ref file_path) => file_iterator!("word2vec", Word2Vec, file_path, Some(signal.clone())),
-                &VectorFile::Gensim(ref file_path) => file_iterator!("gensim", Gensim, file_path, Some(signal.clone())),
-                &VectorFile::Unknown => None,
-            };
+    pub fn new(signal: Rc<IncSignal>, source: &Source) -> Option<DataIterator> {
+        let progress = Rc::new(Progress {
+            signal,
+            records: Cell::new(false),
+            pending: Cell::new(Some(0)),
+        });
+        let signal: Rc<IncSignal> = progress.clone();
+
+        let result = match &source.file {
+            &VectorFile::FastText(ref file_path) => file_iterator!("fasttext", FastText, file_path, Some(signal.clone())),
+            &VectorFile::FastTextBin(ref file_path) => fasttext_bin_iterator(file_path, signal.clone()),
+            &VectorFile::Glove(ref file_path) => file_iterator!("glove", Glove, file_path, Some(signal.clone())),
+            &VectorFile::Word2Vec(ref file_path) => file_iterator!("word2vec", Word2Vec, file_path, Some(signal.clone())),
+            &VectorFile::Gensim(ref file_path) => file_iterator!("gensim", Gensim, file_path, Some(signal.clone())),
+            &VectorFile::Numberbatch(ref file_path) => file_iterator!("numberbatch", Numberbatch, file_path, Some(signal.clone())),
+            &VectorFile::SafeTensors { ref file_path, ref vocab_path, ref tensor } =>
+                safetensors_iterator(file_path, vocab_path, tensor, signal.clone()),
+            &VectorFile::Unknown => None,
+        };
+
+        let (kind, max, iterator, subwords) = result?;
 
-            let (kind, max, iterator) = result?;
+        // a language makes the kind of one-per-language files distinct
+        let (kind, lang) = match source.lang {
+            Some(ref lang) => (format!("{}_{}", kind, lang), lang.to_string()),
+            None => (kind, "".to_string()),
+        };
 
-            Some(DataIterator {
-                kind,
-                max,
-                iterator,
-            })
-        }).collect()
+        Some(DataIterator {
+            kind,
+            lang,
+            max,
+            iterator,
+            subwords,
+            transform: None,
+            filtered: Rc::new(RefCell::new(Filtered::default())),
+            progress,
+        })
+    }
+
+    /// Drops filtered records and counts them by a reason, a limit switches progress from bytes
+    /// to records as a file may end long before its size.
+    pub fn filter(&mut self, filter: Rc<Filter>) {
+        let records = mem::replace(&mut self.iterator, Iter { iter: Rc::new(iter::empty()) });
+        let filtered = self.filtered.clone();
+
+        if let Some(limit) = filter.limit {
+            self.progress.records.set(true);
+            self.max = limit as u64;
+        }
+
+        let limited = Limited {
+            records,
+            limit: filter.limit.unwrap_or(usize::max_value()),
+            read: 0,
+            done: false,
+            progress: self.progress.clone(),
+            filtered: filtered.clone(),
+        };
+
+        self.iterator = Iter {
+            iter: Rc::new(limited.filter(move |record| match filter.check(&record.word) {
+                Some(reason) => {
+                    filtered.borrow_mut().add(reason);
+                    false
+                }
+                None => true,
+            })),
+        };
+    }
+
+    /// Applies a fitted pipeline to every record, a record of another dimension is skipped and counted.
+    pub fn transform(&mut self, pipeline: Pipeline) {
+        let records = mem::replace(&mut self.iterator, Iter { iter: Rc::new(iter::empty()) });
+        let filtered = self.filtered.clone();
+        let transform = pipeline.clone();
+        let mut reported = false;
+
+        self.iterator = Iter {
+            iter: Rc::new(records.filter_map(move |mut record| match transform.apply(&mut record.vec) {
+                Ok(_) => Some(record),
+                Err(err) => {
+                    // one message is enough when all records mismatch a supplied mean
+                    if !reported {
+                        println!("skipped '{}': {}", record.word, err);
+                        reported = true;
+                    }
+
+                    filtered.borrow_mut().add(Reason::Dimension);
+                    None
+                }
+            })),
+        };
+        self.transform = Some(pipeline);
     }
 
     pub fn kind(&self) -> &str {
         &self.kind
     }
 
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
     pub fn max(&self) -> u64 {
         self.max
     }
 
     pub fn iter(&mut self) -> &mut Iter {
+        self.progress.flush();
+
         &mut self.iterator
     }
+
+    pub fn subwords(&mut self) -> Option<&mut Subwords> {
+        self.subwords.as_mut()
+    }
+
+    pub fn filtered(&self) -> Filtered {
+        self.filtered.borrow().clone()
+    }
+
+    pub fn pipeline(&self) -> Option<&Pipeline> {
+        self.transform.as_ref()
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use rand;
+    use wordvector::testing::TestIncCounter;
+
+    #[test]
+    fn test_limit() {
+        let path = env::temp_dir().join(format!("dsj-limit-{}.vec", rand::random::<u32>()));
+
+        if let Err(err) = File::create(&path).and_then(|mut file| file.write_all("4 1\nда 1\n1 2\nнет 3\nмир 4\n".as_bytes())) {
+            assert!(false, "failed to write {:?}: {}", path, err);
+            return;
+        }
+
+        let source = Source {
+            file: VectorFile::FastText(path.to_string_lossy().to_string()),
+            lang: None,
+        };
+
+        for &(limit, expected, summary) in [(3, 2, "digits 1"), (4, 3, "digits 1"), (2, 1, "digits 1")].iter() {
+            let counter = Rc::new(TestIncCounter::default());
+
+            let mut data_iter = match DataIterator::new(counter.clone(), &source) {
+                Some(data_iter) => data_iter,
+                None => {
+                    assert!(false, "failed to open {:?}", path);
+                    return;
+                }
+            };
+
+            data_iter.filter(Rc::new(Filter {
+                limit: Some(limit),
+                digits: true,
+                ..Filter::default()
+            }));
+
+            assert_eq!(data_iter.max(), limit as u64, "check max of limit {}", limit);
+            assert_eq!(data_iter.iter().count(), expected, "check records of limit {}", limit);
+            assert_eq!(counter.value(), limit as u64, "check progress of limit {}", limit);
+
+            let summary = if limit < 4 { format!("1 filtered ({}), cut at {} records", summary, limit) } else { format!("1 filtered ({})", summary) };
+
+            assert_eq!(data_iter.filtered().summary(), summary, "check summary of limit {}", limit);
+        }
+
+        fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_transform_dimension() {
+        let path = env::temp_dir().join(format!("dsj-dimension-{}.vec", rand::random::<u32>()));
+        let mean_path = env::temp_dir().join(format!("dsj-dimension-{}.mean", rand::random::<u32>()));
+
+        let written = File::create(&path).and_then(|mut file| file.write_all("2 1\nда 1\nнет 3\n".as_bytes()))
+            .and_then(|_| File::create(&mean_path).and_then(|mut file| file.write_all(b"0 0\n")));
+
+        if let Err(err) = written {
+            assert!(false, "failed to write {:?}: {}", path, err);
+            return;
+        }
+
+        let source = Source {
+            file: VectorFile::FastText(path.to_string_lossy().to_string()),
+            lang: None,
+        };
+
+        let mut data_iter = match DataIterator::new(Rc::new(TestIncCounter::default()), &source) {
+            Some(data_iter) => data_iter,
+            None => {
+                assert!(false, "failed to open {:?}", path);
+                return;
+            }
+        };
+
+        match Pipeline::parse(&format!("center:{}", mean_path.to_string_lossy())) {
+            Ok(pipeline) => data_iter.transform(pipeline),
+            Err(err) => {
+                assert!(false, "failed to parse a pipeline: {}", err);
+                return;
+            }
+        }
+
+        assert_eq!(data_iter.iter().count(), 0, "check records");
+        assert_eq!(data_iter.filtered().summary(), "2 filtered (dimension 2)", "check summary");
+
+        fs::remove_file(&path).ok();
+        fs::remove_file(&mean_path).ok();
+    }
 }
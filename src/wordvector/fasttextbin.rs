@@ -0,0 +1,439 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::rc::Rc;
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt};
+
+use subword::NGrams;
+use wordvector::{Record, Iter};
+use progressbar::IncSignal;
+
+const MAGIC: i32 = 793_712_314;
+const EOS: &str = "</s>";
+const SUPERVISED: i32 = 3;
+
+/// Arguments and a dictionary of a fastText model (*.bin).
+#[derive(Debug, PartialEq)]
+pub struct Header {
+    pub dim: usize,
+    pub ngrams: NGrams,
+    pub words: Vec<String>,
+    // the first row of the input matrix
+    pub offset: u64,
+}
+
+impl Header {
+    pub fn read<R: Read + Seek>(reader: &mut R) -> Result<Header, String> {
+        Header::parse(reader).map_err(|err| format!("failed to read a fastText model header with {:?}", err))?
+    }
+
+    fn parse<R: Read + Seek>(reader: &mut R) -> ::std::io::Result<Result<Header, String>> {
+        let magic = reader.read_i32::<LittleEndian>()?;
+        let version = reader.read_i32::<LittleEndian>()?;
+
+        if magic != MAGIC || version < 11 || version > 12 {
+            return Ok(Err(format!("unsupported fastText model, magic {} version {}", magic, version)));
+        }
+
+        // dim, ws, epoch, minCount, neg, wordNgrams, loss, model, bucket, minn, maxn, lrUpdateRate
+        let mut args = [0i32; 12];
+
+        reader.read_i32_into::<LittleEndian>(&mut args)?;
+        reader.read_f64::<LittleEndian>()?;
+
+        let (dim, model, bucket, minn, mut maxn) = (args[0], args[7], args[8], args[9], args[10]);
+
+        if version == 11 && model == SUPERVISED {
+            maxn = 0;
+        }
+
+        let size = reader.read_i32::<LittleEndian>()?;
+        let nwords = reader.read_i32::<LittleEndian>()?;
+        reader.read_i32::<LittleEndian>()?;
+        reader.read_i64::<LittleEndian>()?;
+        let pruneidx_size = reader.read_i64::<LittleEndian>()?;
+
+        let mut words = Vec::new();
+
+        for index in 0..size {
+            let mut word = Vec::new();
+
+            loop {
+                match reader.read_u8()? {
+                    0 => break,
+                    byte => word.push(byte),
+                }
+            }
+
+            // a count and a type of an entry
+            reader.read_i64::<LittleEndian>()?;
+            reader.read_i8()?;
+
+            // labels of supervised models follow words
+            if index < nwords {
+                words.push(String::from_utf8_lossy(&word).into_owned());
+            }
+        }
+
+        if pruneidx_size >= 0 {
+            return Ok(Err("pruned fastText models aren't supported".to_string()));
+        }
+
+        if reader.read_u8()? != 0 {
+            return Ok(Err("quantized fastText models (*.ftz) aren't supported".to_string()));
+        }
+
+        let rows = reader.read_i64::<LittleEndian>()?;
+        let cols = reader.read_i64::<LittleEndian>()?;
+
+        if cols != dim as i64 || rows != nwords as i64 + bucket as i64 {
+            return Ok(Err(format!("input matrix {}x{} doesn't match {} words, {} buckets and dimension {}", rows, cols, nwords, bucket, dim)));
+        }
+
+        Ok(Ok(Header {
+            dim: dim as usize,
+            ngrams: NGrams {
+                minn: minn as usize,
+                maxn: maxn as usize,
+                bucket: bucket as u32,
+            },
+            words,
+            offset: reader.seek(SeekFrom::Current(0))?,
+        }))
+    }
+
+    fn row_offset(&self, row: usize) -> u64 {
+        self.offset + (row * self.dim * 4) as u64
+    }
+
+    /// The first row of n-gram buckets.
+    pub fn subwords_offset(&self) -> u64 {
+        self.row_offset(self.words.len())
+    }
+
+    pub fn matrix_end(&self) -> u64 {
+        self.row_offset(self.words.len() + self.ngrams.bucket as usize)
+    }
+}
+
+/// Words of a fastText model, a vector of a word is an average of its row
+/// and rows of its n-grams as in `print-word-vectors`.
+pub struct FastTextBin<T>
+    where T: Read + Seek + Sized
+{
+    reader: T,
+    header: Header,
+    row: usize,
+    // n-gram bucket rows, read once before words
+    buckets: Vec<f32>,
+    signal: Option<Rc<IncSignal>>,
+}
+
+impl<T: 'static> FastTextBin<T>
+    where T: Read + Seek + Sized
+{
+    pub fn new(mut reader: T, signal: Option<Rc<IncSignal>>) -> Result<FastTextBin<T>, String> {
+        let header = Header::read(&mut reader)?;
+        let buckets = FastTextBin::read_buckets(&mut reader, &header)?;
+
+        let fasttext = FastTextBin {
+            reader,
+            header,
+            row: 0,
+            buckets,
+            signal,
+        };
+
+        // bytes of buckets are counted by subword rows read after words
+        fasttext.inc(fasttext.header.offset as usize);
+
+        Ok(fasttext)
+    }
+
+    /// Reads n-gram buckets and seeks back to the first word row.
+    fn read_buckets(reader: &mut T, header: &Header) -> Result<Vec<f32>, String> {
+        let len = match reader.seek(SeekFrom::End(0)) {
+            Ok(len) => len,
+            Err(err) => return Err(format!("failed to seek to the end of a fastText model with {:?}", err)),
+        };
+
+        if len < header.matrix_end() {
+            return Err(format!("truncated fastText model, {} bytes of an input matrix ending at {}", len, header.matrix_end()));
+        }
+
+        let mut buckets = if header.ngrams.maxn > 0 {
+            vec![0f32; header.ngrams.bucket as usize * header.dim]
+        } else {
+            Vec::new()
+        };
+
+        let read = reader.seek(SeekFrom::Start(header.subwords_offset()))
+            .and_then(|_| reader.read_f32_into::<LittleEndian>(&mut buckets))
+            .and_then(|_| reader.seek(SeekFrom::Start(header.offset)));
+
+        match read {
+            Ok(_) => Ok(buckets),
+            Err(err) => Err(format!("failed to read fastText n-gram buckets with {:?}", err)),
+        }
+    }
+
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    fn inc(&self, delta: usize) {
+        if let Some(ref signal) = self.signal {
+            signal.inc(delta as u64);
+        }
+    }
+
+    pub fn into_iter(self) -> Iter {
+        Iter {
+            iter: Rc::new(self)
+        }
+    }
+}
+
+impl<T: 'static> Iterator for FastTextBin<T>
+    where T: Read + Seek + Sized
+{
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Record> {
+        let word = self.header.words.get(self.row)?.clone();
+        let dim = self.header.dim;
+        let mut vec = vec![0f32; dim];
+
+        // word rows are read in order right after the header
+        if let Err(err) = self.reader.read_f32_into::<LittleEndian>(&mut vec) {
+            println!("failed to read a fastText matrix row {} with {:?}", self.row, err);
+            return None;
+        }
+
+        self.row += 1;
+        self.inc(dim * 4);
+
+        let buckets = if word == EOS {
+            Vec::new()
+        } else {
+            self.header.ngrams.buckets(&word)
+        };
+
+        for &bucket in buckets.iter() {
+            let row = &self.buckets[bucket as usize * dim..(bucket as usize + 1) * dim];
+
+            for (point, value) in vec.iter_mut().zip(row.iter()) {
+                *point += value;
+            }
+        }
+
+        let scale = 1.0 / (1 + buckets.len()) as f32;
+
+        for point in vec.iter_mut() {
+            *point *= scale;
+        }
+
+        Some(Record::new(word, &vec))
+    }
+}
+
+/// Rows of n-gram buckets of a fastText model in the bucket order.
+pub struct SubwordRows<T>
+    where T: Read + Sized
+{
+    reader: T,
+    bucket: u32,
+    next: u32,
+    buf: Vec<u8>,
+    signal: Option<Rc<IncSignal>>,
+}
+
+impl<T: 'static> SubwordRows<T>
+    where T: Read + Seek + Sized
+{
+    pub fn new(mut reader: T, header: &Header, signal: Option<Rc<IncSignal>>) -> Result<SubwordRows<T>, String> {
+        if let Err(err) = reader.seek(SeekFrom::Start(header.subwords_offset())) {
+            return Err(format!("failed to seek to fastText n-gram buckets with {:?}", err));
+        }
+
+        Ok(SubwordRows {
+            reader,
+            bucket: header.ngrams.bucket,
+            next: 0,
+            buf: vec![0u8; header.dim * 4],
+            signal,
+        })
+    }
+}
+
+impl<T: 'static> Iterator for SubwordRows<T>
+    where T: Read + Sized
+{
+    type Item = (i32, Vec<f32>);
+
+    fn next(&mut self) -> Option<(i32, Vec<f32>)> {
+        if self.next >= self.bucket {
+            return None;
+        }
+
+        if let Err(err) = self.reader.read_exact(&mut self.buf) {
+            println!("failed to read a fastText n-gram bucket {} with {:?}", self.next, err);
+            return None;
+        }
+
+        if let Some(ref signal) = self.signal {
+            signal.inc(self.buf.len() as u64);
+        }
+
+        let mut points = vec![0f32; self.buf.len() / 4];
+
+        LittleEndian::read_f32_into(&self.buf, &mut points);
+
+        self.next += 1;
+
+        Some((self.next as i32 - 1, points))
+    }
+}
+
+#[cfg(test)]
+pub mod testing {
+    use super::*;
+    use std::io::Cursor;
+    use wordvector::testing::TestIncCounter;
+
+    pub const NGRAMS: NGrams = NGrams {
+        minn: 2,
+        maxn: 3,
+        bucket: 8,
+    };
+
+    fn put_i32(data: &mut Vec<u8>, value: i32) {
+        let mut buf = [0u8; 4];
+
+        LittleEndian::write_i32(&mut buf, value);
+        data.extend_from_slice(&buf);
+    }
+
+    fn put_i64(data: &mut Vec<u8>, value: i64) {
+        let mut buf = [0u8; 8];
+
+        LittleEndian::write_i64(&mut buf, value);
+        data.extend_from_slice(&buf);
+    }
+
+    fn put_f32(data: &mut Vec<u8>, value: f32) {
+        let mut buf = [0u8; 4];
+
+        LittleEndian::write_f32(&mut buf, value);
+        data.extend_from_slice(&buf);
+    }
+
+    /// A model of dimension 2 with words "</s>" and "мир", the row of a word
+    /// is (index + 1, 0) and the row of a bucket is (0, bucket + 1).
+    pub fn fasttext_bin_data(version: i32, quantized: bool) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        put_i32(&mut data, MAGIC);
+        put_i32(&mut data, version);
+
+        // dim, ws, epoch, minCount, neg, wordNgrams, loss, model, bucket, minn, maxn, lrUpdateRate
+        for &arg in [2, 5, 5, 1, 5, 1, 1, 2, NGRAMS.bucket as i32, NGRAMS.minn as i32, NGRAMS.maxn as i32, 100].iter() {
+            put_i32(&mut data, arg);
+        }
+
+        let mut lr = [0u8; 8];
+
+        LittleEndian::write_f64(&mut lr, 0.0001);
+        data.extend_from_slice(&lr);
+
+        // size, nwords, nlabels, ntokens, pruneidx_size
+        put_i32(&mut data, 3);
+        put_i32(&mut data, 2);
+        put_i32(&mut data, 1);
+        put_i64(&mut data, 100);
+        put_i64(&mut data, -1);
+
+        for &(word, kind) in [("</s>", 0), ("мир", 0), ("__label__a", 1)].iter() {
+            data.extend_from_slice(word.as_bytes());
+            data.push(0);
+            put_i64(&mut data, 10);
+            data.push(kind);
+        }
+
+        data.push(quantized as u8);
+        put_i64(&mut data, 2 + NGRAMS.bucket as i64);
+        put_i64(&mut data, 2);
+
+        for word in 0..2 {
+            put_f32(&mut data, word as f32 + 1.0);
+            put_f32(&mut data, 0.0);
+        }
+
+        for bucket in 0..NGRAMS.bucket {
+            put_f32(&mut data, 0.0);
+            put_f32(&mut data, bucket as f32 + 1.0);
+        }
+
+        // an output matrix isn't read
+        data.extend_from_slice(&[0u8; 16]);
+
+        data
+    }
+
+    #[test]
+    fn test_fasttext_bin_iter() {
+        let data = fasttext_bin_data(12, false);
+        let exist_counter = Rc::new(TestIncCounter::default());
+
+        let fasttext = match FastTextBin::new(Cursor::new(data.clone()), Some(exist_counter.clone())) {
+            Ok(fasttext) => fasttext,
+            Err(err) => {
+                assert!(false, "failed to read a fastText model with {:?}", err);
+                return;
+            }
+        };
+
+        assert_eq!((fasttext.header().dim, fasttext.header().ngrams, &fasttext.header().words), (2, NGRAMS, &vec!["</s>".to_string(), "мир".to_string()]), "check header");
+        assert_eq!(fasttext.header().matrix_end(), data.len() as u64 - 16, "check matrix size");
+
+        let header_len = fasttext.header().offset;
+        let records = fasttext.into_iter().map(|record| (record.word, record.vec)).collect::<Vec<_>>();
+
+        let buckets = NGRAMS.buckets("мир");
+        let expected = vec![
+            ("</s>".to_string(), vec![1.0f32, 0.0]),
+            ("мир".to_string(), vec![
+                2.0 / (buckets.len() + 1) as f32,
+                buckets.iter().map(|&bucket| bucket as f32 + 1.0).sum::<f32>() / (buckets.len() + 1) as f32,
+            ]),
+        ];
+
+        assert_eq!(records, expected, "check averaged vectors");
+        assert_eq!(exist_counter.value(), header_len + 2 * 8, "check counter");
+    }
+
+    #[test]
+    fn test_subword_rows() {
+        let mut reader = Cursor::new(fasttext_bin_data(12, false));
+
+        let rows = match Header::read(&mut reader).and_then(|header| SubwordRows::new(reader, &header, None)) {
+            Ok(rows) => rows.collect::<Vec<_>>(),
+            Err(err) => {
+                assert!(false, "failed to read n-gram buckets with {:?}", err);
+                return;
+            }
+        };
+
+        assert_eq!(rows.len(), NGRAMS.bucket as usize, "check count");
+        assert_eq!(rows[3], (3, vec![0.0f32, 4.0]), "check row");
+    }
+
+    #[test]
+    fn test_fasttext_bin_errors() {
+        assert!(Header::read(&mut Cursor::new(fasttext_bin_data(12, true))).is_err(), "check quantized");
+        assert!(Header::read(&mut Cursor::new(fasttext_bin_data(10, false))).is_err(), "check version");
+        assert!(Header::read(&mut Cursor::new(fasttext_bin_data(12, false)[..40].to_vec())).is_err(), "check truncated");
+
+        let data = fasttext_bin_data(12, false);
+
+        assert!(FastTextBin::new(Cursor::new(data[..data.len() - 24].to_vec()), None).is_err(), "check truncated matrix");
+    }
+}
@@ -22,13 +22,15 @@ impl<T: 'static> Gensim<T>
         }
     }
 
-    fn parse_word(&self, line: &str) -> String {
+    fn parse_word(&self, line: &str) -> (Option<i64>, String) {
         let mut parser = line
             .split_whitespace()
-            .take(2)
-            .skip(1);
+            .take(2);
 
-        parser.next().unwrap_or_default().trim().to_string()
+        let index = parser.next().and_then(|index| index.parse::<i64>().ok());
+        let word = parser.next().unwrap_or_default().trim().to_string();
+
+        (index, word)
     }
 
     fn parse_weights(&self, line: &str) -> Vec<f32> {
@@ -41,18 +43,22 @@ impl<T: 'static> Gensim<T>
     fn parse(&self, line: &str) -> Record {
         let mut reader = Cursor::new(line);
 
-        let word = {
+        let (index, word) = {
             let mut header_buf: Vec<u8> = vec![];
 
             match reader.read_until(b'[', &mut header_buf) {
                 Ok(_) => self.parse_word(&String::from_utf8_lossy(&header_buf)),
-                Err(_) => "".to_string()
+                Err(_) => (None, "".to_string())
             }
         };
 
         let weights = self.parse_weights(&line[reader.position() as usize..]);
 
-        Record::new(word, &weights)
+        let mut record = Record::new(word, &weights);
+
+        record.index = index;
+
+        record
     }
 
     fn inc(&self, delta: usize) {
@@ -98,6 +104,8 @@ mod testing {
                     "check first word"
                 );
 
+                assert_eq!(record.index, Some(25454), "check first word index");
+
                 let ln = record.vec.len();
 
                 assert_eq!(
@@ -123,6 +131,8 @@ mod testing {
                     "check word"
                 );
 
+                assert_eq!(record.index, Some(25458), "check word index");
+
                 let ln = record.vec.len();
 
                 assert_eq!(
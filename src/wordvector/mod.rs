@@ -1,25 +1,44 @@
 pub mod dataiterator;
 mod fasttext;
+mod fasttextbin;
 mod gensim;
 mod glove;
+mod numberbatch;
+mod safetensors;
+mod vocab;
 mod word2vec;
 
 #[cfg(test)]
-mod testing;
+pub mod testing;
 
 use std::rc::Rc;
+use subword::NGrams;
 
 #[derive(Clone)]
 pub enum VectorFile {
     FastText(String),
+    FastTextBin(String),
     Glove(String),
     Word2Vec(String),
     Gensim(String),
+    Numberbatch(String),
+    SafeTensors {
+        file_path: String,
+        vocab_path: String,
+        tensor: String,
+    },
     Unknown,
 }
 
+pub struct Source {
+    pub file: VectorFile,
+    pub lang: Option<String>,
+}
+
 pub struct Record {
     pub word: String,
+    pub lang: Option<String>,
+    pub index: Option<i64>,
     pub vec: Vec<f32>,
 }
 
@@ -27,9 +46,24 @@ impl Record {
     fn new(word: impl ToString, vec: &[f32]) -> Record {
         Record {
             word: word.to_string(),
+            lang: None,
+            index: None,
             vec: Vec::from(vec),
         }
     }
+
+    fn with_lang(word: impl ToString, lang: impl ToString, vec: &[f32]) -> Record {
+        Record {
+            lang: Some(lang.to_string()),
+            ..Record::new(word, vec)
+        }
+    }
+}
+
+/// N-gram buckets of a fastText model to compose vectors of unknown words.
+pub struct Subwords {
+    pub ngrams: NGrams,
+    pub rows: Box<Iterator<Item=(i32, Vec<f32>)>>,
 }
 
 pub struct Iter {
@@ -57,6 +91,13 @@ mod localtesting {
         let expected_vec = vec![0.12f32, 3.14, 5.16];
 
         assert_eq!(rec.word, expected_word, "check word init");
+        assert_eq!(rec.lang, None, "check lang init");
+        assert_eq!(rec.index, None, "check index init");
         assert_eq!(rec.vec, expected_vec, "check vec init");
+
+        let rec = Record::with_lang("мир", "ru", &[0.5f32]);
+
+        assert_eq!(rec.word, "мир", "check word init");
+        assert_eq!(rec.lang, Some("ru".to_string()), "check lang init");
     }
 }
\ No newline at end of file
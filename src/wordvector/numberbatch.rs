@@ -0,0 +1,170 @@
+use std::io::BufRead;
+use std::rc::Rc;
+
+use wordvector::{Record, Iter};
+use progressbar::IncSignal;
+use io::Pieces;
+
+pub struct Numberbatch<T>
+    where T: BufRead + Sized
+{
+    iterator: Pieces<T>,
+    signal: Option<Rc<IncSignal>>,
+}
+
+impl<T: 'static> Numberbatch<T>
+    where T: BufRead + Sized
+{
+    pub fn new(reader: T, signal: Option<Rc<IncSignal>>) -> Numberbatch<T> {
+        let mut numberbatch = Numberbatch {
+            iterator: Pieces::new(reader, b'\n'),
+            signal,
+        };
+
+        // skip the first line
+        if let Some((delta, _)) = numberbatch.iterator.next() {
+            numberbatch.inc(delta);
+        }
+
+        numberbatch
+    }
+
+    // a term is a ConceptNet URI /c/<lang>/<term>[/<pos>...],
+    // English-only releases keep bare terms
+    fn parse_term(&self, term: &str) -> (String, Option<String>) {
+        let mut parts = term.split('/');
+
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(""), Some("c"), Some(lang), Some(word)) if !lang.is_empty() && !word.is_empty() =>
+                (word.to_string(), Some(lang.to_string())),
+            _ => (term.to_string(), None),
+        }
+    }
+
+    fn parse(&self, line: &str) -> Record {
+        let mut parser = line
+            .split_whitespace();
+
+        let term = parser.next().unwrap_or_default()
+            .trim();
+
+        let weights = parser
+            .map(|value|
+                value.parse::<f32>().unwrap_or_default()
+            )
+            .collect::<Vec<_>>();
+
+        match self.parse_term(term) {
+            (word, Some(lang)) => Record::with_lang(word, lang, &weights),
+            (word, None) => Record::new(word, &weights),
+        }
+    }
+
+    fn inc(&self, delta: usize) {
+        if let Some(ref signal) = self.signal {
+            signal.inc(delta as u64);
+        }
+    }
+
+    pub fn into_iter(self) -> Iter {
+        Iter {
+            iter: Rc::new(self)
+        }
+    }
+}
+
+impl<T: 'static> Iterator for Numberbatch<T>
+    where T: BufRead + Sized
+{
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Record> {
+        let (delta, line) = self.iterator.next()?;
+
+        self.inc(delta);
+
+        Some(self.parse(&line))
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use std::fs::File;
+    use std::io::{BufReader, Cursor};
+    use wordvector::testing::TestIncCounter;
+
+    fn test_numberbatch_iter(iter: &mut Iter) {
+        let records = iter
+            .map(|record| (record.word, record.lang, record.vec))
+            .collect::<Vec<_>>();
+
+        assert_eq!(records.len(), 4, "check word count");
+
+        assert_eq!(
+            (records[0].0.as_str(), records[0].1.as_ref().map(|lang| lang.as_str())),
+            ("peace", Some("en")),
+            "check first word"
+        );
+
+        assert_eq!(
+            &records[0].2,
+            &[0.0123f32, -0.0456, 0.0789, -0.1011, 0.1213],
+            "check first word vector"
+        );
+
+        assert_eq!(
+            records.iter()
+                .map(|&(ref word, ref lang, _)| format!("{}:{}", lang.as_ref().unwrap(), word))
+                .collect::<Vec<_>>(),
+            vec!["en:peace", "ru:мир", "uk:мир", "en:ice_cream"],
+            "check language-tagged words"
+        );
+    }
+
+    #[test]
+    fn test_numberbatch_buffer_iter() {
+        let test_data = include_str!("../../test/data/numberbatch.txt");
+
+        let expected_data_len = test_data.as_bytes().len() as u64;
+        let exist_counter = Rc::new(TestIncCounter::default());
+
+        test_numberbatch_iter(&mut Numberbatch::new(
+            BufReader::new(Cursor::new(test_data)),
+            Some(exist_counter.clone()),
+        ).into_iter());
+
+        assert_eq!(exist_counter.value(), expected_data_len, "check counter");
+    }
+
+    #[test]
+    fn test_numberbatch_file_iter() {
+        let file_name = "test/data/numberbatch.txt";
+
+        match File::open(&file_name) {
+            Ok(file) => {
+                test_numberbatch_iter(&mut Numberbatch::new(
+                    BufReader::new(file),
+                    None,
+                ).into_iter());
+            }
+            Err(err) => assert!(false, "failed to open file '{}' to test iter values with {:?}", &file_name, err),
+        }
+    }
+
+    #[test]
+    fn test_numberbatch_bare_terms() {
+        let mut iter = Numberbatch::new(
+            BufReader::new(Cursor::new("1 2\nice_cream 0.5 -0.5\n")),
+            None,
+        ).into_iter();
+
+        match iter.next() {
+            Some(record) => {
+                assert_eq!(record.word, "ice_cream", "check word");
+                assert_eq!(record.lang, None, "check lang");
+            }
+            None => assert!(false, "failed to read a bare term"),
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::rc::Rc;
+use byteorder::{ByteOrder, LittleEndian};
+use serde_json::{self, Value};
+
+use half::{bf16_to_f32, f16_to_f32};
+use wordvector::{Record, Iter};
+use progressbar::IncSignal;
+
+// the format limits a header to 100MB
+const MAX_HEADER_LEN: u64 = 100 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum DType {
+    F32,
+    F16,
+    BF16,
+}
+
+impl DType {
+    fn parse(dtype: &str) -> Option<DType> {
+        match dtype {
+            "F32" => Some(DType::F32),
+            "F16" => Some(DType::F16),
+            "BF16" => Some(DType::BF16),
+            _ => None,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match *self {
+            DType::F32 => 4,
+            DType::F16 | DType::BF16 => 2,
+        }
+    }
+
+    fn decode(&self, buf: &[u8]) -> Vec<f32> {
+        buf.chunks(self.size())
+            .map(|value| match *self {
+                DType::F32 => LittleEndian::read_f32(value),
+                DType::F16 => f16_to_f32(LittleEndian::read_u16(value)),
+                DType::BF16 => bf16_to_f32(LittleEndian::read_u16(value)),
+            })
+            .collect()
+    }
+}
+
+pub struct SafeTensors<T>
+    where T: Read + Seek + Sized
+{
+    reader: T,
+    vocab: Vec<String>,
+    dtype: DType,
+    rows: usize,
+    row: usize,
+    buf: Vec<u8>,
+    signal: Option<Rc<IncSignal>>,
+}
+
+impl<T: 'static> SafeTensors<T>
+    where T: Read + Seek + Sized
+{
+    pub fn new(mut reader: T, vocab: Vec<String>, tensor: &str, signal: Option<Rc<IncSignal>>) -> Result<SafeTensors<T>, String> {
+        let mut len_buf = [0u8; 8];
+
+        if let Err(err) = reader.read_exact(&mut len_buf) {
+            return Err(format!("failed to read a safetensors header size with {:?}", err));
+        }
+
+        let file_len = match reader.seek(SeekFrom::End(0)).and_then(|len| reader.seek(SeekFrom::Start(len_buf.len() as u64)).map(|_| len)) {
+            Ok(len) => len,
+            Err(err) => return Err(format!("failed to read a safetensors size with {:?}", err)),
+        };
+
+        let header_len = LittleEndian::read_u64(&len_buf);
+
+        if header_len > MAX_HEADER_LEN || header_len > file_len - len_buf.len() as u64 {
+            return Err(format!("safetensors header size {} exceeds a limit or a file size {}", header_len, file_len));
+        }
+
+        let header_len = header_len as usize;
+        let mut header_buf = vec![0u8; header_len];
+
+        if let Err(err) = reader.read_exact(&mut header_buf) {
+            return Err(format!("failed to read a safetensors header with {:?}", err));
+        }
+
+        let header: Value = match serde_json::from_slice(&header_buf) {
+            Ok(header) => header,
+            Err(err) => return Err(format!("failed to parse a safetensors header with {:?}", err)),
+        };
+
+        let info = &header[tensor];
+
+        if info.is_null() {
+            return Err(format!("tensor '{}' doesn't found in a safetensors header", tensor));
+        }
+
+        let dtype = match info["dtype"].as_str().and_then(DType::parse) {
+            Some(dtype) => dtype,
+            None => return Err(format!("tensor '{}' has unsupported dtype {}", tensor, info["dtype"])),
+        };
+
+        let shape = info["shape"].as_array()
+            .map(|shape| shape.iter().filter_map(|dim| dim.as_u64()).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        if shape.len() != 2 {
+            return Err(format!("tensor '{}' has shape {:?}, expected [vocab, dim]", tensor, shape));
+        }
+
+        let (rows, dim) = (shape[0] as usize, shape[1] as usize);
+
+        let (begin, end) = match (info["data_offsets"][0].as_u64(), info["data_offsets"][1].as_u64()) {
+            (Some(begin), Some(end)) => (begin, end),
+            _ => return Err(format!("tensor '{}' doesn't have data offsets", tensor)),
+        };
+
+        let size = match end.checked_sub(begin) {
+            Some(size) => size,
+            None => return Err(format!("tensor '{}' has data offsets [{}, {}] out of order", tensor, begin, end)),
+        };
+
+        match rows.checked_mul(dim).and_then(|len| len.checked_mul(dtype.size())) {
+            Some(expected) if expected as u64 == size => {}
+            _ => return Err(format!("tensor '{}' data size {} doesn't match shape {:?}", tensor, size, shape)),
+        }
+
+        let offset = (len_buf.len() + header_len) as u64;
+
+        match offset.checked_add(end) {
+            Some(data_end) if data_end <= file_len => {}
+            _ => return Err(format!("tensor '{}' data offsets [{}, {}] exceed a file size {}", tensor, begin, end, file_len)),
+        }
+
+        let offset = offset + begin;
+
+        if let Err(err) = reader.seek(SeekFrom::Start(offset)) {
+            return Err(format!("failed to seek to tensor '{}' with {:?}", tensor, err));
+        }
+
+        let safetensors = SafeTensors {
+            reader,
+            vocab,
+            dtype,
+            rows,
+            row: 0,
+            buf: vec![0u8; dim * dtype.size()],
+            signal,
+        };
+
+        safetensors.inc(offset as usize);
+
+        Ok(safetensors)
+    }
+
+    fn read_row(&mut self) -> Option<Vec<f32>> {
+        if let Err(err) = self.reader.read_exact(&mut self.buf) {
+            println!("failed to read a tensor row {} with {:?}", self.row, err);
+            return None;
+        }
+
+        self.row += 1;
+
+        Some(self.dtype.decode(&self.buf))
+    }
+
+    fn inc(&self, delta: usize) {
+        if let Some(ref signal) = self.signal {
+            signal.inc(delta as u64);
+        }
+    }
+
+    pub fn into_iter(self) -> Iter {
+        Iter {
+            iter: Rc::new(self)
+        }
+    }
+}
+
+impl<T: 'static> Iterator for SafeTensors<T>
+    where T: Read + Seek + Sized
+{
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Record> {
+        while self.row < self.rows {
+            let index = self.row;
+            let weights = self.read_row()?;
+
+            self.inc(self.buf.len());
+
+            // an embedding matrix is often padded beyond the vocabulary
+            match self.vocab.get(index) {
+                Some(word) if !word.is_empty() => return Some(Record::new(word.to_string(), &weights)),
+                _ => continue,
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use std::io::Cursor;
+    use wordvector::testing::TestIncCounter;
+
+    fn safetensors_data(dtype: &str, rows: usize, dim: usize, data: &[u8]) -> Vec<u8> {
+        let header = format!(
+            r#"{{"__metadata__":{{"format":"pt"}},"pooler.dense.bias":{{"dtype":"F32","shape":[1],"data_offsets":[0,4]}},"embeddings.word_embeddings.weight":{{"dtype":"{}","shape":[{},{}],"data_offsets":[4,{}]}}}}"#,
+            dtype, rows, dim, 4 + data.len(),
+        );
+
+        let mut buf = vec![0u8; 8];
+
+        LittleEndian::write_u64(&mut buf, header.len() as u64);
+
+        buf.extend_from_slice(header.as_bytes());
+        buf.extend_from_slice(&[0u8; 4]);
+        buf.extend_from_slice(data);
+
+        buf
+    }
+
+    fn vocab() -> Vec<String> {
+        vec!["[PAD]".to_string(), "".to_string(), "мир".to_string()]
+    }
+
+    fn test_records(data: Vec<u8>, expected: &[(&str, Vec<f32>)]) {
+        let expected_data_len = data.len() as u64;
+        let exist_counter = Rc::new(TestIncCounter::default());
+
+        let iter = match SafeTensors::new(
+            Cursor::new(data),
+            vocab(),
+            "embeddings.word_embeddings.weight",
+            Some(exist_counter.clone()),
+        ) {
+            Ok(safetensors) => safetensors.into_iter(),
+            Err(err) => {
+                assert!(false, "failed to open safetensors with {:?}", err);
+                return;
+            }
+        };
+
+        let records = iter.map(|record| (record.word, record.vec)).collect::<Vec<_>>();
+
+        assert_eq!(
+            records,
+            expected.iter().map(|&(word, ref vec)| (word.to_string(), vec.clone())).collect::<Vec<_>>(),
+            "check records"
+        );
+
+        assert_eq!(exist_counter.value(), expected_data_len, "check counter");
+    }
+
+    #[test]
+    fn test_safetensors_f32_iter() {
+        let mut data = vec![0u8; 4 * 4 * 2];
+
+        LittleEndian::write_f32_into(&[0.5f32, -1.0, 0.0, 0.0, 2.25, -0.125, 3.0, 4.0], &mut data);
+
+        test_records(
+            safetensors_data("F32", 4, 2, &data),
+            &[("[PAD]", vec![0.5f32, -1.0]), ("мир", vec![2.25f32, -0.125])],
+        );
+    }
+
+    #[test]
+    fn test_safetensors_f16_iter() {
+        let mut data = vec![0u8; 2 * 3 * 2];
+
+        LittleEndian::write_u16_into(&[0x3c00u16, 0xc000, 0x0000, 0x0000, 0x3800, 0x4200], &mut data);
+
+        test_records(
+            safetensors_data("F16", 3, 2, &data),
+            &[("[PAD]", vec![1.0f32, -2.0]), ("мир", vec![0.5f32, 3.0])],
+        );
+    }
+
+    #[test]
+    fn test_safetensors_bf16_iter() {
+        let mut data = vec![0u8; 2 * 3 * 2];
+
+        LittleEndian::write_u16_into(&[0x3f80u16, 0xc040, 0x0000, 0x0000, 0x3e20, 0x4000], &mut data);
+
+        test_records(
+            safetensors_data("BF16", 3, 2, &data),
+            &[("[PAD]", vec![1.0f32, -3.0]), ("мир", vec![0.15625f32, 2.0])],
+        );
+    }
+
+    #[test]
+    fn test_safetensors_errors() {
+        let data = safetensors_data("F32", 2, 2, &[0u8; 16]);
+
+        assert!(SafeTensors::new(Cursor::new(data.clone()), vocab(), "missing.weight", None).is_err(), "check missing tensor");
+        assert!(SafeTensors::new(Cursor::new(data.clone()), vocab(), "pooler.dense.bias", None).is_err(), "check tensor shape");
+
+        let data = safetensors_data("I64", 2, 2, &[0u8; 32]);
+
+        assert!(SafeTensors::new(Cursor::new(data), vocab(), "embeddings.word_embeddings.weight", None).is_err(), "check dtype");
+    }
+
+    fn replace(data: &[u8], from: &str, to: &str) -> Vec<u8> {
+        let header_len = LittleEndian::read_u64(&data[..8]) as usize;
+        let header = String::from_utf8_lossy(&data[8..8 + header_len]).replace(from, to);
+        let mut buf = vec![0u8; 8];
+
+        LittleEndian::write_u64(&mut buf, header.len() as u64);
+
+        buf.extend_from_slice(header.as_bytes());
+        buf.extend_from_slice(&data[8 + header_len..]);
+
+        buf
+    }
+
+    #[test]
+    fn test_safetensors_corrupt_header() {
+        let tensor = "embeddings.word_embeddings.weight";
+        let data = safetensors_data("F32", 2, 2, &[0u8; 16]);
+
+        let mut oversized = data.clone();
+
+        LittleEndian::write_u64(&mut oversized[..8], u64::max_value());
+
+        assert!(SafeTensors::new(Cursor::new(oversized), vocab(), tensor, None).is_err(), "check header size");
+
+        let mut truncated = data.clone();
+
+        LittleEndian::write_u64(&mut truncated[..8], data.len() as u64);
+
+        assert!(SafeTensors::new(Cursor::new(truncated), vocab(), tensor, None).is_err(), "check header beyond a file");
+        assert!(SafeTensors::new(Cursor::new(replace(&data, "[4,20]", "[20,4]")), vocab(), tensor, None).is_err(), "check reversed offsets");
+        assert!(SafeTensors::new(Cursor::new(replace(&data, r#"[2,2],"data_offsets":[4,20]"#, r#"[4,2],"data_offsets":[4,36]"#)), vocab(), tensor, None).is_err(), "check offsets beyond a file");
+        assert!(SafeTensors::new(Cursor::new(replace(&data, "[2,2]", "[4611686018427387904,4]")), vocab(), tensor, None).is_err(), "check shape overflow");
+    }
+}
@@ -0,0 +1,120 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use serde_json::{self, Value};
+
+pub fn read_vocab(file_path: &str) -> Result<Vec<String>, String> {
+    let file = match File::open(file_path) {
+        Ok(file) => file,
+        Err(err) => return Err(format!("failed to open vocabulary '{}' with {:?}", file_path, err)),
+    };
+
+    if file_path.ends_with(".json") {
+        parse_tokenizer(BufReader::new(file))
+    } else {
+        parse_vocab(BufReader::new(file))
+    }
+}
+
+pub fn parse_vocab<T: BufRead>(reader: T) -> Result<Vec<String>, String> {
+    reader.lines()
+        .map(|line| match line {
+            Ok(line) => Ok(line.trim_end_matches('\r').to_string()),
+            Err(err) => Err(format!("failed to read vocabulary with {:?}", err)),
+        })
+        .collect()
+}
+
+pub fn parse_tokenizer<T: Read>(reader: T) -> Result<Vec<String>, String> {
+    let tokenizer: Value = match serde_json::from_reader(reader) {
+        Ok(tokenizer) => tokenizer,
+        Err(err) => return Err(format!("failed to parse tokenizer with {:?}", err)),
+    };
+
+    let mut tokens: Vec<(usize, String)> = Vec::new();
+
+    match tokenizer["model"]["vocab"] {
+        // WordPiece, BPE and WordLevel keep a token to id map
+        Value::Object(ref vocab) => for (token, id) in vocab.iter() {
+            if let Some(id) = id.as_u64() {
+                tokens.push((id as usize, token.to_string()));
+            }
+        },
+        // Unigram keeps a list of (token, score) pairs ordered by id
+        Value::Array(ref vocab) => for (id, entry) in vocab.iter().enumerate() {
+            if let Some(token) = entry[0].as_str() {
+                tokens.push((id, token.to_string()));
+            }
+        },
+        _ => return Err("tokenizer doesn't contain a model vocabulary".to_string()),
+    }
+
+    if let Some(added_tokens) = tokenizer["added_tokens"].as_array() {
+        for added in added_tokens.iter() {
+            if let (Some(id), Some(token)) = (added["id"].as_u64(), added["content"].as_str()) {
+                tokens.push((id as usize, token.to_string()));
+            }
+        }
+    }
+
+    // ids may skip a few tokens, but a vocabulary shouldn't be mostly gaps
+    let bound = tokens.len() * 2;
+
+    if let Some(&(id, ref token)) = tokens.iter().find(|&&(id, _)| id >= bound) {
+        return Err(format!("token '{}' id {} is out of range for {} tokens", token, id, tokens.len()));
+    }
+
+    let size = tokens.iter().map(|&(id, _)| id + 1).max().unwrap_or_default();
+    let mut vocab = vec![String::new(); size];
+
+    for (id, token) in tokens.into_iter() {
+        vocab[id] = token;
+    }
+
+    Ok(vocab)
+}
+
+#[cfg(test)]
+mod testing {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_parse_vocab() {
+        let vocab = match parse_vocab(Cursor::new("[PAD]\r\n[UNK]\nмир\n##ом\n")) {
+            Ok(vocab) => vocab,
+            Err(err) => {
+                assert!(false, "failed to parse vocabulary with {:?}", err);
+                return;
+            }
+        };
+
+        assert_eq!(vocab, vec!["[PAD]", "[UNK]", "мир", "##ом"], "check tokens");
+    }
+
+    #[test]
+    fn test_parse_tokenizer() {
+        let vocab = match parse_tokenizer(Cursor::new(include_str!("../../test/data/tokenizer.json"))) {
+            Ok(vocab) => vocab,
+            Err(err) => {
+                assert!(false, "failed to parse tokenizer with {:?}", err);
+                return;
+            }
+        };
+
+        assert_eq!(vocab, vec!["[PAD]", "the", "мир", "", "[MASK]"], "check tokens");
+
+        let vocab = match parse_tokenizer(Cursor::new(r#"{"model": {"vocab": [["<unk>", 0.0], ["▁мир", -3.5]]}}"#)) {
+            Ok(vocab) => vocab,
+            Err(err) => {
+                assert!(false, "failed to parse unigram tokenizer with {:?}", err);
+                return;
+            }
+        };
+
+        assert_eq!(vocab, vec!["<unk>", "▁мир"], "check unigram tokens");
+
+        let huge = r#"{"model": {"vocab": {"мир": 0, "мира": 18446744073709551615}}}"#;
+
+        assert!(parse_tokenizer(Cursor::new(huge)).is_err(), "check out of range id");
+    }
+}